@@ -0,0 +1,149 @@
+//! Compares how much CPU time a minute of audio costs before and after the sink started resampling.
+//!
+//! Before, the stream was handed to songbird at 44.1 kHz, which resampled it to 48 kHz with rubato's
+//! `FftFixedOut`, configured the way songbird's mixer does. Now the [`Resampler`] in the sink does this,
+//! and songbird passes the 48 kHz audio through. Both paths decode the raw stream into planar buffers
+//! the same way, so the resampling step is the only difference between them.
+//!
+//! Run with `cargo bench -p spoticord_audio --bench resampler`.
+
+use std::{
+    f32::consts::PI,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use librespot::playback::{NUM_CHANNELS, SAMPLE_RATE};
+use rubato::{FftFixedOut, Resampler as _};
+use spoticord_audio::resampler::{Resampler, OUTPUT_SAMPLE_RATE};
+
+/// The length of the audio that is resampled per run, in seconds
+const AUDIO_LENGTH: usize = 60;
+
+/// The amount of samples librespot hands to the sink per write
+const PACKET_SIZE: usize = 4096;
+
+/// The amount of frames songbird mixes at once, 20 ms at 48 kHz
+const SONGBIRD_FRAME_SIZE: usize = 960;
+
+/// The amount of sub chunks songbird splits every resampled frame into
+const SONGBIRD_SUB_CHUNKS: usize = 4;
+
+const CHANNELS: usize = NUM_CHANNELS as usize;
+
+const RUNS: usize = 10;
+
+fn main() {
+    let frames = SAMPLE_RATE as usize * AUDIO_LENGTH;
+    let input = (0..frames)
+        .flat_map(|frame| {
+            let sample = 0.5 * (2.0 * PI * 440.0 * frame as f32 / SAMPLE_RATE as f32).sin();
+            [sample; CHANNELS]
+        })
+        .collect::<Vec<_>>();
+
+    // Songbird receives the audio in planar buffers, which symphonia decodes the raw stream into for both paths
+    let planar = (0..CHANNELS)
+        .map(|channel| {
+            input
+                .iter()
+                .skip(channel)
+                .step_by(CHANNELS)
+                .copied()
+                .collect()
+        })
+        .collect::<Vec<Vec<f32>>>();
+
+    println!("resampling {AUDIO_LENGTH} s of stereo audio from 44.1 kHz to 48 kHz ({RUNS} runs)");
+
+    let before = measure("before: rubato in songbird", || resample_songbird(&planar));
+    let after = measure("after:  resampler in the sink", || resample_sink(&input));
+
+    println!(
+        "  the sink uses {:.0}% of the CPU time songbird used",
+        after.as_secs_f64() / before.as_secs_f64() * 100.0
+    );
+}
+
+/// Time a resampling path, and return the median time it took
+fn measure(name: &str, mut resample: impl FnMut() -> usize) -> Duration {
+    // Warm up, which also builds the filter banks
+    black_box(resample());
+
+    let mut times = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(resample());
+
+            start.elapsed()
+        })
+        .collect::<Vec<_>>();
+
+    times.sort();
+
+    let median = times[RUNS / 2];
+    let audio = Duration::from_secs(AUDIO_LENGTH as u64);
+
+    println!("{name}");
+    println!(
+        "  min {:?}, median {median:?}, max {:?}",
+        times[0],
+        times[RUNS - 1]
+    );
+    println!(
+        "  {:.0}x faster than real time, {:.2}% of a core per guild while playing",
+        audio.as_secs_f64() / median.as_secs_f64(),
+        median.as_secs_f64() / audio.as_secs_f64() * 100.0
+    );
+
+    median
+}
+
+/// Resample the audio the way the sink does, one packet at a time
+fn resample_sink(input: &[f32]) -> usize {
+    let mut resampler = Resampler::new();
+    let mut output = 0;
+
+    for packet in input.chunks(PACKET_SIZE) {
+        output += resampler.process(packet).len();
+    }
+
+    output + resampler.flush().len()
+}
+
+/// Resample the audio the way songbird's mixer does for a source that isn't at 48 kHz
+fn resample_songbird(planar: &[Vec<f32>]) -> usize {
+    let mut resampler = FftFixedOut::<f32>::new(
+        SAMPLE_RATE as usize,
+        OUTPUT_SAMPLE_RATE as usize,
+        SONGBIRD_FRAME_SIZE,
+        SONGBIRD_SUB_CHUNKS,
+        CHANNELS,
+    )
+    .expect("invalid resampler configuration");
+
+    let mut resampled = resampler.output_buffer_allocate(true);
+    let mut output = 0;
+    let mut position = 0;
+
+    loop {
+        let end = position + resampler.input_frames_next();
+        if end > planar[0].len() {
+            break;
+        }
+
+        let chunk = planar
+            .iter()
+            .map(|channel| &channel[position..end])
+            .collect::<Vec<_>>();
+
+        let (_, written) = resampler
+            .process_into_buffer(&chunk, &mut resampled, None)
+            .expect("resampling failed");
+
+        output += written * CHANNELS;
+        position = end;
+    }
+
+    output
+}
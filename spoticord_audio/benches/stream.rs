@@ -0,0 +1,183 @@
+//! Compares the throughput and latency of the ring buffer [`Stream`] against the mutex buffer it replaced.
+//!
+//! Run with `cargo bench -p spoticord_audio --bench stream`.
+
+use std::{
+    hint::black_box,
+    io::{Read, Write},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use spoticord_audio::stream::Stream;
+
+/// The amount of audio pushed through a stream per throughput run
+const THROUGHPUT_BYTES: usize = 256 * 1024 * 1024;
+
+/// 20 ms of 48 kHz stereo 32-bit float audio, the amount songbird reads per frame
+const FRAME_SIZE: usize = 48_000 * 2 * 4 / 50;
+
+/// The amount of single writes that are timed per latency run
+const LATENCY_SAMPLES: usize = 20_000;
+
+/// The time the writer sleeps between two timed writes, so that every write finds an empty buffer
+const LATENCY_INTERVAL: Duration = Duration::from_micros(50);
+
+/// The size of the buffer, which both streams use
+const BUFFER_SIZE: usize = 64 * 1024;
+
+fn main() {
+    println!(
+        "throughput ({} MiB, {FRAME_SIZE} byte frames)",
+        THROUGHPUT_BYTES >> 20
+    );
+    report_throughput("  mutex buffer", throughput(MutexStream::default()));
+    report_throughput("  ring buffer ", throughput(Stream::new()));
+
+    println!("write-to-read latency ({LATENCY_SAMPLES} writes)");
+    report_latency("  mutex buffer", latency(MutexStream::default()));
+    report_latency("  ring buffer ", latency(Stream::new()));
+}
+
+/// Push audio from a writer thread to a reader thread, the way the player and songbird use a stream
+fn throughput<S: Read + Write + Clone + Send + 'static>(stream: S) -> Duration {
+    let mut writer = stream.clone();
+    let mut reader = stream;
+
+    // Every byte of audio is non-zero, so the zeroes handed out on an underrun can be told apart
+    let frame = vec![1u8; FRAME_SIZE];
+
+    let start = Instant::now();
+
+    let handle = thread::spawn(move || {
+        for _ in 0..THROUGHPUT_BYTES / FRAME_SIZE {
+            writer.write_all(black_box(&frame)).unwrap();
+        }
+    });
+
+    let mut buf = vec![0u8; FRAME_SIZE];
+    let mut received = 0;
+
+    while received < THROUGHPUT_BYTES / FRAME_SIZE * FRAME_SIZE {
+        let read = reader.read(&mut buf).unwrap();
+
+        if buf[0] != 0 {
+            received += read;
+        } else {
+            thread::yield_now();
+        }
+    }
+
+    handle.join().unwrap();
+
+    start.elapsed()
+}
+
+/// Time how long it takes for a single write to become visible to a reader that is waiting for it
+fn latency<S: Read + Write + Clone + Send + 'static>(stream: S) -> Vec<Duration> {
+    let mut writer = stream.clone();
+    let mut reader = stream;
+
+    let origin = Instant::now();
+
+    let handle = thread::spawn(move || {
+        for _ in 0..LATENCY_SAMPLES {
+            thread::sleep(LATENCY_INTERVAL);
+
+            // The highest bit keeps the timestamp from ever being all zeroes
+            let timestamp = origin.elapsed().as_nanos() as u64 | 1 << 63;
+            writer.write_all(&timestamp.to_ne_bytes()).unwrap();
+        }
+    });
+
+    let mut samples = Vec::with_capacity(LATENCY_SAMPLES);
+    let mut buf = [0u8; 8];
+
+    while samples.len() < LATENCY_SAMPLES {
+        if reader.read(&mut buf).unwrap() != 8 || buf == [0; 8] {
+            thread::yield_now();
+            continue;
+        }
+
+        let timestamp = u64::from_ne_bytes(buf) & !(1 << 63);
+        samples.push(origin.elapsed() - Duration::from_nanos(timestamp));
+    }
+
+    handle.join().unwrap();
+
+    samples
+}
+
+fn report_throughput(name: &str, elapsed: Duration) {
+    let mib_per_second = (THROUGHPUT_BYTES >> 20) as f64 / elapsed.as_secs_f64();
+
+    println!("{name}: {elapsed:>10.2?} ({mib_per_second:.0} MiB/s)");
+}
+
+fn report_latency(name: &str, mut samples: Vec<Duration>) {
+    samples.sort_unstable();
+
+    let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+
+    println!(
+        "{name}: p50 {:>8.2?}, p99 {:>8.2?}, max {:>8.2?}",
+        percentile(50),
+        percentile(99),
+        percentile(100)
+    );
+}
+
+/// The mutex buffer that [`Stream`] used before it was replaced by a ring buffer
+#[derive(Clone, Default)]
+struct MutexStream {
+    inner: Arc<(Mutex<Vec<u8>>, Condvar)>,
+}
+
+impl Read for MutexStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let (mutex, condvar) = &*self.inner;
+        let mut buffer = mutex.lock().expect("Mutex was poisoned");
+
+        if buffer.is_empty() {
+            buf.fill(0);
+            condvar.notify_all();
+
+            return Ok(buf.len());
+        }
+
+        let max_read = usize::min(buf.len(), buffer.len());
+
+        buf[0..max_read].copy_from_slice(&buffer[0..max_read]);
+        buffer.drain(0..max_read);
+        condvar.notify_all();
+
+        Ok(max_read)
+    }
+}
+
+impl Write for MutexStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let (mutex, condvar) = &*self.inner;
+        let mut buffer = mutex.lock().expect("Mutex was poisoned");
+
+        while buffer.len() + buf.len() > BUFFER_SIZE {
+            buffer = condvar.wait(buffer).expect("Mutex was poisoned");
+        }
+
+        buffer.extend_from_slice(buf);
+        condvar.notify_all();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let (mutex, condvar) = &*self.inner;
+        let mut buffer = mutex.lock().expect("Mutex was poisoned");
+
+        buffer.clear();
+        condvar.notify_all();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+use std::f32::consts::PI;
+
+use librespot::playback::{NUM_CHANNELS, SAMPLE_RATE};
+
+use crate::filter::AudioFilter;
+
+/// The name under which the equalizer is registered inside of a [`FilterChain`](crate::filter::FilterChain)
+pub const FILTER_NAME: &str = "equalizer";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BandKind {
+    Peaking,
+    LowShelf,
+    HighShelf,
+    /// Removes everything below the frequency, the gain of the band is ignored
+    HighPass,
+}
+
+/// A single band of a parametric equalizer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub kind: BandKind,
+    pub frequency: f32,
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+impl Band {
+    pub const fn new(kind: BandKind, frequency: f32, gain_db: f32, q: f32) -> Self {
+        Self {
+            kind,
+            frequency,
+            gain_db,
+            q,
+        }
+    }
+}
+
+const BASS_BOOST: &[Band] = &[
+    Band::new(BandKind::LowShelf, 120.0, 6.0, 0.707),
+    Band::new(BandKind::Peaking, 400.0, -1.5, 1.0),
+];
+
+const VOCAL: &[Band] = &[
+    Band::new(BandKind::LowShelf, 150.0, -3.0, 0.707),
+    Band::new(BandKind::Peaking, 1500.0, 3.0, 0.9),
+    Band::new(BandKind::Peaking, 3500.0, 4.0, 1.2),
+    Band::new(BandKind::HighShelf, 9000.0, -2.0, 0.707),
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EqualizerPreset {
+    #[default]
+    Flat,
+    BassBoost,
+    Vocal,
+}
+
+impl EqualizerPreset {
+    pub fn bands(&self) -> &'static [Band] {
+        match self {
+            Self::Flat => &[],
+            Self::BassBoost => BASS_BOOST,
+            Self::Vocal => VOCAL,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Flat => "Flat",
+            Self::BassBoost => "Bass boost",
+            Self::Vocal => "Vocal",
+        }
+    }
+}
+
+/// Second order IIR filter using the coefficients from the Audio EQ Cookbook
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+
+    // Direct form I history per channel: [x1, x2, y1, y2]
+    state: [[f32; 4]; NUM_CHANNELS as usize],
+}
+
+impl Biquad {
+    pub fn new(band: Band, sample_rate: f32) -> Self {
+        let a = 10f32.powf(band.gain_db / 40.0);
+        let w0 = 2.0 * PI * band.frequency / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * band.q);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match band.kind {
+            BandKind::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            BandKind::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha,
+            ),
+            BandKind::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha,
+            ),
+            BandKind::HighPass => (
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+        };
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+
+            state: Default::default(),
+        }
+    }
+}
+
+impl AudioFilter for Biquad {
+    fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(NUM_CHANNELS as usize) {
+            for (sample, [x1, x2, y1, y2]) in frame.iter_mut().zip(self.state.iter_mut()) {
+                let x0 = *sample;
+                let y0 =
+                    self.b0 * x0 + self.b1 * *x1 + self.b2 * *x2 - self.a1 * *y1 - self.a2 * *y2;
+
+                *x2 = *x1;
+                *x1 = x0;
+                *y2 = *y1;
+                *y1 = y0;
+
+                *sample = y0;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = Default::default();
+    }
+}
+
+/// A parametric equalizer consisting of a cascade of [`Biquad`] filters.
+///
+/// The output is attenuated by the largest band gain to leave headroom for boosted frequencies.
+#[derive(Debug, Clone)]
+pub struct Equalizer {
+    preamp: f32,
+    filters: Vec<Biquad>,
+}
+
+impl Equalizer {
+    pub fn new(bands: &[Band]) -> Self {
+        let headroom = bands.iter().map(|band| band.gain_db).fold(0f32, f32::max);
+
+        Self {
+            preamp: 10f32.powf(-headroom / 20.0),
+            filters: bands
+                .iter()
+                .map(|band| Biquad::new(*band, SAMPLE_RATE as f32))
+                .collect(),
+        }
+    }
+
+    pub fn from_preset(preset: EqualizerPreset) -> Self {
+        Self::new(preset.bands())
+    }
+}
+
+impl AudioFilter for Equalizer {
+    fn process(&mut self, samples: &mut [f32]) {
+        if self.preamp != 1.0 {
+            samples.iter_mut().for_each(|sample| *sample *= self.preamp);
+        }
+
+        for filter in &mut self.filters {
+            filter.process(samples);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.filters.iter_mut().for_each(Biquad::reset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = SAMPLE_RATE as f32;
+
+    /// The gain in dB of the filter at the given frequency, from its transfer function
+    fn response_db(filter: &Biquad, frequency: f32) -> f32 {
+        let w = 2.0 * PI * frequency / RATE;
+
+        // H(z) = (b0 + b1 z^-1 + b2 z^-2) / (1 + a1 z^-1 + a2 z^-2), evaluated at z = e^jw
+        let (sin1, cos1) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+
+        let num_re = filter.b0 + filter.b1 * cos1 + filter.b2 * cos2;
+        let num_im = -filter.b1 * sin1 - filter.b2 * sin2;
+        let den_re = 1.0 + filter.a1 * cos1 + filter.a2 * cos2;
+        let den_im = -filter.a1 * sin1 - filter.a2 * sin2;
+
+        let magnitude =
+            ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).sqrt();
+
+        20.0 * magnitude.log10()
+    }
+
+    fn assert_db(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.05,
+            "expected {expected} dB, got {actual} dB"
+        );
+    }
+
+    #[test]
+    fn peaking_without_gain_is_transparent() {
+        let filter = Biquad::new(Band::new(BandKind::Peaking, 1000.0, 0.0, 1.0), RATE);
+
+        assert!((filter.b0 - 1.0).abs() < 1e-6);
+        assert!((filter.b1 - filter.a1).abs() < 1e-6);
+        assert!((filter.b2 - filter.a2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn peaking_reaches_gain_at_center() {
+        let filter = Biquad::new(Band::new(BandKind::Peaking, 1500.0, 3.0, 0.9), RATE);
+
+        assert_db(response_db(&filter, 1500.0), 3.0);
+        assert_db(response_db(&filter, 10.0), 0.0);
+        assert_db(response_db(&filter, 20000.0), 0.0);
+
+        let filter = Biquad::new(Band::new(BandKind::Peaking, 400.0, -1.5, 1.0), RATE);
+        assert_db(response_db(&filter, 400.0), -1.5);
+    }
+
+    #[test]
+    fn low_shelf_boosts_below_frequency() {
+        let filter = Biquad::new(Band::new(BandKind::LowShelf, 120.0, 6.0, 0.707), RATE);
+
+        assert_db(response_db(&filter, 1.0), 6.0);
+        // Shelves are halfway at their frequency
+        assert_db(response_db(&filter, 120.0), 3.0);
+        assert_db(response_db(&filter, 15000.0), 0.0);
+    }
+
+    #[test]
+    fn high_shelf_cuts_above_frequency() {
+        let filter = Biquad::new(Band::new(BandKind::HighShelf, 9000.0, -2.0, 0.707), RATE);
+
+        assert_db(response_db(&filter, 10.0), 0.0);
+        assert_db(response_db(&filter, 9000.0), -1.0);
+        assert_db(response_db(&filter, RATE / 2.0 - 1.0), -2.0);
+    }
+
+    #[test]
+    fn high_pass_removes_low_frequencies() {
+        let filter = Biquad::new(
+            Band::new(
+                BandKind::HighPass,
+                100.0,
+                12.0,
+                std::f32::consts::FRAC_1_SQRT_2,
+            ),
+            RATE,
+        );
+
+        assert!(response_db(&filter, 5.0) < -40.0);
+        // A Butterworth high pass is 3 dB down at its cutoff, regardless of the gain of the band
+        assert_db(response_db(&filter, 100.0), -3.01);
+        assert_db(response_db(&filter, 10000.0), 0.0);
+    }
+
+    #[test]
+    fn process_matches_coefficients() {
+        let mut filter = Biquad::new(Band::new(BandKind::LowShelf, 120.0, 6.0, 0.707), RATE);
+
+        // A constant signal settles at the DC gain of the filter on every channel
+        let mut samples = vec![0.25; SAMPLE_RATE as usize * NUM_CHANNELS as usize];
+        filter.process(&mut samples);
+
+        let expected = 0.25 * 10f32.powf(6.0 / 20.0);
+        for sample in &samples[samples.len() - NUM_CHANNELS as usize..] {
+            assert!((sample - expected).abs() < 1e-3, "{sample} != {expected}");
+        }
+
+        filter.reset();
+        assert_eq!(filter.state, <[[f32; 4]; NUM_CHANNELS as usize]>::default());
+    }
+
+    #[test]
+    fn equalizer_leaves_headroom() {
+        let flat = Equalizer::from_preset(EqualizerPreset::Flat);
+        assert_eq!(flat.preamp, 1.0);
+        assert!(flat.filters.is_empty());
+
+        let bass_boost = Equalizer::from_preset(EqualizerPreset::BassBoost);
+        assert!((bass_boost.preamp - 10f32.powf(-6.0 / 20.0)).abs() < 1e-6);
+        assert_eq!(bass_boost.filters.len(), BASS_BOOST.len());
+
+        // Presets that only cut don't need any headroom
+        let cut = Equalizer::new(&[Band::new(BandKind::Peaking, 400.0, -3.0, 1.0)]);
+        assert_eq!(cut.preamp, 1.0);
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    f32::consts::FRAC_PI_2,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use librespot::{
+    core::SpotifyId,
+    playback::{NUM_CHANNELS, SAMPLE_RATE},
+};
+
+use crate::{resampler::OUTPUT_SAMPLE_RATE, stream::Stream};
+
+/// The longest crossfade that can be configured
+pub const MAX_CROSSFADE: Duration = Duration::from_secs(12);
+
+/// The length of the short fades that are applied when pausing, resuming and skipping
+const TRANSITION: Duration = Duration::from_millis(60);
+
+/// How far a reported position may drift from our own bookkeeping before it is considered a seek
+const SEEK_TOLERANCE: Duration = Duration::from_secs(1);
+
+/// Shared handle through which the player informs the sink about the track that is playing,
+/// and through which the crossfade length can be changed while playing.
+///
+/// The loudness limiter also reads the volume and the normalisation of the current track from here.
+#[derive(Clone, Default)]
+pub struct FadeControl {
+    inner: Arc<FadeState>,
+}
+
+#[derive(Default)]
+struct FadeState {
+    crossfade_ms: AtomicU32,
+    next_track_ready: AtomicBool,
+    timing: Mutex<Option<TrackTiming>>,
+    timing_changed: AtomicBool,
+
+    /// Whether librespot normalised the current track using its loudness metadata
+    track_normalised: AtomicBool,
+    /// The gain of librespot's software volume in dB, stored as the bits of an f32
+    volume_db: AtomicU32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrackTiming {
+    track: SpotifyId,
+    position_ms: u32,
+    duration_ms: u32,
+}
+
+impl FadeControl {
+    pub fn new(crossfade: Duration) -> Self {
+        let this = Self::default();
+        this.set_crossfade(crossfade);
+
+        this
+    }
+
+    /// The length of the crossfade between tracks, zero if crossfading is disabled
+    pub fn crossfade(&self) -> Duration {
+        Duration::from_millis(self.inner.crossfade_ms.load(Ordering::Acquire) as u64)
+    }
+
+    /// Change the length of the crossfade between tracks, capped at [`MAX_CROSSFADE`]
+    pub fn set_crossfade(&self, crossfade: Duration) {
+        let crossfade = crossfade.min(MAX_CROSSFADE);
+
+        self.inner
+            .crossfade_ms
+            .store(crossfade.as_millis() as u32, Ordering::Release);
+    }
+
+    /// Report the position of the track that is currently being played
+    pub fn update_position(&self, track: SpotifyId, position_ms: u32, duration_ms: u32) {
+        let mut timing = self.inner.timing.lock().expect("Mutex was poisoned");
+
+        *timing = Some(TrackTiming {
+            track,
+            position_ms,
+            duration_ms,
+        });
+
+        self.inner.timing_changed.store(true, Ordering::Release);
+    }
+
+    /// Report whether the track after the current one has been loaded.
+    ///
+    /// A crossfade is only started if there is a track to fade into, so that the end of the last
+    /// track of a context is played normally.
+    pub fn set_next_track_ready(&self, ready: bool) {
+        self.inner.next_track_ready.store(ready, Ordering::Release);
+    }
+
+    /// Report whether librespot normalised the track that just started, from its loudness metadata
+    pub fn set_track_normalised(&self, normalised: bool) {
+        self.inner
+            .track_normalised
+            .store(normalised, Ordering::Release);
+    }
+
+    pub fn track_normalised(&self) -> bool {
+        self.inner.track_normalised.load(Ordering::Acquire)
+    }
+
+    /// Report the attenuation factor of the software volume, which librespot applies before the sink
+    pub fn set_volume(&self, attenuation_factor: f64) {
+        let volume_db = if attenuation_factor > 0.0 {
+            20.0 * attenuation_factor.log10() as f32
+        } else {
+            0.0
+        };
+
+        self.inner
+            .volume_db
+            .store(volume_db.to_bits(), Ordering::Release);
+    }
+
+    /// The gain of the software volume in dB, which is 0 at full volume
+    pub fn volume_db(&self) -> f32 {
+        f32::from_bits(self.inner.volume_db.load(Ordering::Acquire))
+    }
+
+    fn take_timing(&self) -> Option<TrackTiming> {
+        if !self.inner.timing_changed.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+
+        *self.inner.timing.lock().expect("Mutex was poisoned")
+    }
+}
+
+enum State {
+    Playing,
+    /// Collecting the end of the current track so it can be mixed with the next one
+    Capturing,
+    /// Mixing the captured end of the previous track into the start of the current one
+    Mixing,
+}
+
+/// Applies crossfades and short fades to the audio that passes through a sink
+pub(crate) struct Fader {
+    control: FadeControl,
+    stream: Stream,
+    state: State,
+
+    track: Option<SpotifyId>,
+    /// The amount of samples left in the current track, if known
+    remaining: Option<u64>,
+
+    tail: Vec<f32>,
+    tail_position: usize,
+
+    fade_in: usize,
+}
+
+impl Fader {
+    pub fn new(control: FadeControl, stream: Stream) -> Self {
+        Self {
+            control,
+            stream,
+            state: State::Playing,
+
+            track: None,
+            remaining: None,
+
+            tail: Vec::new(),
+            tail_position: 0,
+
+            fade_in: 0,
+        }
+    }
+
+    /// Fade in the audio that comes after this call
+    pub fn start(&mut self) {
+        self.fade_in = samples(TRANSITION);
+    }
+
+    /// Fade out the audio that has not been played yet and discard it.
+    ///
+    /// If the current track has ended whilst its end was being held back for a crossfade, the
+    /// held back audio is returned so it can still be played.
+    pub fn stop(&mut self) -> Vec<f32> {
+        if !matches!(self.state, State::Capturing) || self.remaining != Some(0) {
+            self.stream.fade_out(bytes(TRANSITION));
+
+            return Vec::new();
+        }
+
+        // There is nothing to fade into, so just fade out what is left of the track
+        self.state = State::Playing;
+        let length = self.tail.len() as f32;
+
+        let mut tail = std::mem::take(&mut self.tail);
+        for (i, sample) in tail.iter_mut().enumerate() {
+            *sample *= 1.0 - i as f32 / length;
+        }
+
+        tail
+    }
+
+    pub fn process(&mut self, input: Vec<f32>) -> Vec<f32> {
+        self.sync_timing();
+
+        let crossfade = samples(self.control.crossfade()) as u64;
+        let next_track_ready = self.control.inner.next_track_ready.load(Ordering::Acquire);
+
+        let mut output = Vec::with_capacity(input.len());
+
+        for sample in input {
+            let remaining = self.remaining;
+            self.remaining = remaining.map(|remaining| remaining.saturating_sub(1));
+
+            match self.state {
+                State::Playing => {
+                    let crossfade_point =
+                        remaining.is_some_and(|remaining| remaining > 0 && remaining <= crossfade);
+
+                    if crossfade > 0 && next_track_ready && crossfade_point {
+                        self.state = State::Capturing;
+                        self.tail.clear();
+                        self.tail.push(sample);
+                    } else {
+                        output.push(sample);
+                    }
+                }
+
+                State::Capturing if remaining == Some(0) => {
+                    self.state = State::Mixing;
+                    self.tail_position = 0;
+
+                    output.push(self.mix(sample));
+                }
+
+                State::Capturing => self.tail.push(sample),
+                State::Mixing => output.push(self.mix(sample)),
+            }
+        }
+
+        self.apply_fade_in(&mut output);
+
+        output
+    }
+
+    /// Process changes in the position of the track reported by the player
+    fn sync_timing(&mut self) {
+        let Some(timing) = self.control.take_timing() else {
+            return;
+        };
+
+        let remaining = samples(Duration::from_millis(
+            timing.duration_ms.saturating_sub(timing.position_ms) as u64,
+        )) as u64;
+
+        let tolerance = samples(SEEK_TOLERANCE) as u64;
+        let same_track = self.track == Some(timing.track);
+        let drifted = match self.remaining {
+            Some(current) => current.abs_diff(remaining) > tolerance,
+            None => true,
+        };
+
+        match self.state {
+            // The next track started before the end of the current one was reached (i.e. a skip),
+            // so fade into it from whatever has been captured so far
+            State::Capturing if !same_track => {
+                self.state = State::Mixing;
+                self.tail_position = 0;
+            }
+
+            // Seeked within the track that was being captured, the captured audio is out of date
+            State::Capturing if drifted => {
+                self.state = State::Playing;
+                self.tail.clear();
+            }
+
+            // Skipped in the middle of a track, fade out what's left in the stream of the old one
+            State::Playing if !same_track && self.remaining.is_some_and(|r| r > tolerance) => {
+                self.stream.fade_out(bytes(TRANSITION));
+                self.start();
+            }
+
+            _ => {}
+        }
+
+        self.track = Some(timing.track);
+        self.remaining = Some(remaining);
+    }
+
+    fn mix(&mut self, sample: f32) -> f32 {
+        let Some(&previous) = self.tail.get(self.tail_position) else {
+            self.state = State::Playing;
+            self.tail.clear();
+
+            return sample;
+        };
+
+        // Equal power crossfade, to keep the loudness constant throughout the fade
+        let progress = self.tail_position as f32 / self.tail.len() as f32 * FRAC_PI_2;
+        self.tail_position += 1;
+
+        previous * progress.cos() + sample * progress.sin()
+    }
+
+    fn apply_fade_in(&mut self, output: &mut [f32]) {
+        let length = samples(TRANSITION) as f32;
+
+        for sample in output.iter_mut().take(self.fade_in) {
+            *sample *= 1.0 - self.fade_in as f32 / length;
+            self.fade_in -= 1;
+        }
+    }
+}
+
+/// The amount of interleaved samples that make up `duration` of audio
+fn samples(duration: Duration) -> usize {
+    (duration.as_millis() as usize * SAMPLE_RATE as usize / 1000) * NUM_CHANNELS as usize
+}
+
+/// The amount of bytes that make up `duration` of audio in the (resampled) stream
+fn bytes(duration: Duration) -> usize {
+    let samples = duration.as_millis() as usize * OUTPUT_SAMPLE_RATE as usize / 1000;
+
+    samples * NUM_CHANNELS as usize * std::mem::size_of::<f32>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A crossfade of 100 ms, in interleaved samples
+    const CROSSFADE: usize = 8820;
+
+    fn track(id: &str) -> SpotifyId {
+        SpotifyId::from_uri(&format!("spotify:track:{id}")).unwrap()
+    }
+
+    fn fader() -> (FadeControl, Fader) {
+        let control = FadeControl::new(Duration::from_millis(100));
+        control.set_next_track_ready(true);
+
+        let fader = Fader::new(control.clone(), Stream::new());
+
+        (control, fader)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn crossfades_at_the_end_of_a_track() {
+        let (control, mut fader) = fader();
+
+        // 200 ms of the first track, the last 100 ms of which are held back
+        control.update_position(track("4uLU6hMCjMI75M1A2tKUQC"), 0, 200);
+        let output = fader.process(vec![1.0; 2 * CROSSFADE]);
+        assert_eq!(output, vec![1.0; CROSSFADE]);
+
+        // The next track starts before the player reports it
+        let output = fader.process(vec![0.5; CROSSFADE]);
+        assert_eq!(output.len(), CROSSFADE);
+        assert_close(output[0], 1.0);
+        assert_close(output[CROSSFADE / 2], 1.5 * std::f32::consts::FRAC_1_SQRT_2);
+        assert_close(output[CROSSFADE - 1], 0.5);
+
+        control.update_position(track("7GhIk7Il098yCjg4BQjzvb"), 100, 10_000);
+        assert_eq!(fader.process(vec![0.5; 100]), vec![0.5; 100]);
+    }
+
+    #[test]
+    fn fades_into_a_track_that_was_skipped_to_while_capturing() {
+        let (control, mut fader) = fader();
+
+        control.update_position(track("4uLU6hMCjMI75M1A2tKUQC"), 0, 200);
+        let output = fader.process(vec![1.0; CROSSFADE + 1000]);
+        assert_eq!(output.len(), CROSSFADE);
+
+        // Only the 1000 samples that were captured before the skip are mixed in
+        control.update_position(track("7GhIk7Il098yCjg4BQjzvb"), 0, 10_000);
+        let output = fader.process(vec![0.5; 2000]);
+        assert_eq!(output.len(), 2000);
+        assert_close(output[0], 1.0);
+        assert!(output[..1000].iter().all(|&sample| sample > 0.5));
+        assert_eq!(output[1000..], vec![0.5; 1000]);
+    }
+
+    #[test]
+    fn drops_the_capture_after_a_seek() {
+        let (control, mut fader) = fader();
+        let first = track("4uLU6hMCjMI75M1A2tKUQC");
+
+        // The seek tolerance is a second, so the track has to be longer than that
+        control.update_position(first, 0, 3000);
+        let length = samples(Duration::from_secs(3));
+        let output = fader.process(vec![1.0; length - CROSSFADE + 1000]);
+        assert_eq!(output.len(), length - CROSSFADE);
+
+        // Seeking back plays on from the new position, without the audio that was captured
+        control.update_position(first, 1000, 3000);
+        assert_eq!(fader.process(vec![0.25; 100]), vec![0.25; 100]);
+        assert!(fader.stop().is_empty());
+    }
+
+    #[test]
+    fn stop_returns_the_faded_tail_at_the_end_of_a_track() {
+        let (control, mut fader) = fader();
+
+        control.update_position(track("4uLU6hMCjMI75M1A2tKUQC"), 0, 200);
+        let output = fader.process(vec![1.0; 2 * CROSSFADE]);
+        assert_eq!(output.len(), CROSSFADE);
+
+        // The track has ended without a next one starting, so what was held back is faded out
+        let tail = fader.stop();
+        assert_eq!(tail.len(), CROSSFADE);
+        assert_close(tail[0], 1.0);
+        assert_close(tail[CROSSFADE / 2], 0.5);
+        assert!(tail.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(tail[CROSSFADE - 1] < 0.01);
+
+        // Nothing is held back anymore
+        assert!(fader.stop().is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::{Arc, Mutex};
+
+/// A processing stage for audio that runs inside of the [`StreamSink`](crate::sink::StreamSink)
+///
+/// Filters receive interleaved stereo f32 samples at [`SAMPLE_RATE`](librespot::playback::SAMPLE_RATE).
+pub trait AudioFilter: Send {
+    fn process(&mut self, samples: &mut [f32]);
+
+    /// Clear any internal state, called whenever playback is interrupted
+    fn reset(&mut self) {}
+}
+
+type NamedFilter = (&'static str, Box<dyn AudioFilter>);
+
+/// An ordered, shareable collection of named filters.
+///
+/// The chain can be modified from anywhere while the sink is running, changes apply to the next audio packet.
+#[derive(Clone, Default)]
+pub struct FilterChain {
+    filters: Arc<Mutex<Vec<NamedFilter>>>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a filter to the end of the chain, or replace the filter that has the same name
+    pub fn insert(&self, name: &'static str, filter: impl AudioFilter + 'static) {
+        let mut filters = self.filters.lock().expect("Mutex was poisoned");
+        let filter: Box<dyn AudioFilter> = Box::new(filter);
+
+        match filters.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, existing)) => *existing = filter,
+            None => filters.push((name, filter)),
+        }
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.filters
+            .lock()
+            .expect("Mutex was poisoned")
+            .retain(|(existing, _)| *existing != name);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.filters
+            .lock()
+            .expect("Mutex was poisoned")
+            .iter()
+            .any(|(existing, _)| *existing == name)
+    }
+
+    pub fn process(&self, samples: &mut [f32]) {
+        for (_, filter) in self.filters.lock().expect("Mutex was poisoned").iter_mut() {
+            filter.process(samples);
+        }
+    }
+
+    pub fn reset(&self) {
+        for (_, filter) in self.filters.lock().expect("Mutex was poisoned").iter_mut() {
+            filter.reset();
+        }
+    }
+}
@@ -1,2 +1,7 @@
+pub mod equalizer;
+pub mod fade;
+pub mod filter;
+pub mod loudness;
+pub mod resampler;
 pub mod sink;
 pub mod stream;
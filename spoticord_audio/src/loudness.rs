@@ -0,0 +1,292 @@
+use std::{collections::VecDeque, f32::consts::FRAC_1_SQRT_2};
+
+use librespot::playback::{NUM_CHANNELS, SAMPLE_RATE};
+
+use crate::{
+    equalizer::{Band, BandKind, Biquad},
+    fade::FadeControl,
+    filter::AudioFilter,
+};
+
+/// The name under which the limiter is registered inside of a [`FilterChain`](crate::filter::FilterChain)
+pub const FILTER_NAME: &str = "loudness";
+
+/// The loudness Spotify normalizes to by default, in LUFS
+pub const DEFAULT_TARGET_LOUDNESS: f64 = -14.0;
+
+/// How far (in LU) the measured loudness may exceed the target before the limiter intervenes
+const THRESHOLD: f32 = 1.0;
+
+/// Loudness is measured in blocks of 100ms, as described in ITU-R BS.1770
+const BLOCK_FRAMES: usize = SAMPLE_RATE as usize / 10;
+
+/// The amount of blocks that make up the short-term (3 second) loudness window
+const WINDOW_BLOCKS: usize = 30;
+
+/// Time constants (in seconds) of the gain changes applied by the limiter
+const ATTACK: f32 = 0.5;
+const RELEASE: f32 = 3.0;
+
+/// Attenuates audio whose short-term loudness exceeds a target loudness.
+///
+/// Librespot only normalizes tracks that come with loudness metadata, this catches everything
+/// else by measuring the K-weighted loudness of the audio itself. Tracks that librespot normalised
+/// pass through untouched, and the limiter never boosts audio.
+pub struct LoudnessLimiter {
+    target: f32,
+    /// Tells whether the current track has been normalised, and how loud the software volume is
+    control: FadeControl,
+
+    weighting: [Biquad; 2],
+    scratch: Vec<f32>,
+
+    block_energy: f64,
+    block_frames: usize,
+    blocks: VecDeque<f64>,
+
+    desired_gain_db: f32,
+    gain_db: f32,
+}
+
+impl LoudnessLimiter {
+    pub fn new(target_loudness: f64, control: FadeControl) -> Self {
+        let sample_rate = SAMPLE_RATE as f32;
+
+        Self {
+            target: target_loudness as f32,
+            control,
+
+            // K-weighting: a high shelf modelling the acoustic effect of the head, followed by a high pass
+            weighting: [
+                Biquad::new(
+                    Band::new(BandKind::HighShelf, 1681.97, 4.0, FRAC_1_SQRT_2),
+                    sample_rate,
+                ),
+                Biquad::new(Band::new(BandKind::HighPass, 38.14, 0.0, 0.5), sample_rate),
+            ],
+            scratch: Vec::new(),
+
+            block_energy: 0.0,
+            block_frames: 0,
+            blocks: VecDeque::with_capacity(WINDOW_BLOCKS),
+
+            desired_gain_db: 0.0,
+            gain_db: 0.0,
+        }
+    }
+
+    /// Feed the K-weighted samples into the loudness measurement
+    fn measure(&mut self) {
+        for frame in self.scratch.chunks_exact(NUM_CHANNELS as usize) {
+            self.block_energy += frame
+                .iter()
+                .map(|sample| (*sample as f64).powi(2))
+                .sum::<f64>();
+            self.block_frames += 1;
+
+            if self.block_frames < BLOCK_FRAMES {
+                continue;
+            }
+
+            if self.blocks.len() == WINDOW_BLOCKS {
+                self.blocks.pop_front();
+            }
+
+            self.blocks
+                .push_back(self.block_energy / BLOCK_FRAMES as f64);
+            self.block_energy = 0.0;
+            self.block_frames = 0;
+
+            let energy = self.blocks.iter().sum::<f64>() / self.blocks.len() as f64;
+            let measured = (-0.691 + 10.0 * energy.max(f64::MIN_POSITIVE).log10()) as f32;
+
+            // The audio has already been turned down by the software volume, which shouldn't make
+            // a track count as quieter than it is
+            let loudness = measured - self.control.volume_db();
+
+            self.desired_gain_db = (self.target + THRESHOLD - loudness).min(0.0);
+        }
+    }
+}
+
+impl AudioFilter for LoudnessLimiter {
+    fn process(&mut self, samples: &mut [f32]) {
+        if self.control.track_normalised() {
+            // Start measuring from scratch once a track comes along that hasn't been normalised
+            if self.gain_db != 0.0 || !self.blocks.is_empty() {
+                self.reset();
+                self.desired_gain_db = 0.0;
+                self.gain_db = 0.0;
+            }
+
+            return;
+        }
+
+        self.scratch.clear();
+        self.scratch.extend_from_slice(samples);
+
+        for filter in &mut self.weighting {
+            filter.process(&mut self.scratch);
+        }
+
+        self.measure();
+
+        let attack = 1.0 / (ATTACK * SAMPLE_RATE as f32);
+        let release = 1.0 / (RELEASE * SAMPLE_RATE as f32);
+
+        for frame in samples.chunks_exact_mut(NUM_CHANNELS as usize) {
+            let rate = if self.desired_gain_db < self.gain_db {
+                attack
+            } else {
+                release
+            };
+
+            self.gain_db += (self.desired_gain_db - self.gain_db) * rate;
+
+            if self.gain_db < -0.01 {
+                let gain = 10f32.powf(self.gain_db / 20.0);
+                frame.iter_mut().for_each(|sample| *sample *= gain);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.weighting.iter_mut().for_each(Biquad::reset);
+
+        self.block_energy = 0.0;
+        self.block_frames = 0;
+        self.blocks.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    const CHANNELS: usize = NUM_CHANNELS as usize;
+
+    /// A 1 kHz stereo sine wave, which K-weighting leaves practically untouched
+    fn sine(amplitude: f32, seconds: f32) -> Vec<f32> {
+        let frames = (seconds * SAMPLE_RATE as f32) as usize;
+
+        (0..frames)
+            .flat_map(|frame| {
+                let sample =
+                    amplitude * (2.0 * PI * 1000.0 * frame as f32 / SAMPLE_RATE as f32).sin();
+                [sample; CHANNELS]
+            })
+            .collect()
+    }
+
+    /// Run audio through the limiter one loudness block at a time
+    fn run(limiter: &mut LoudnessLimiter, samples: &mut [f32]) {
+        for chunk in samples.chunks_mut(BLOCK_FRAMES * CHANNELS) {
+            limiter.process(chunk);
+        }
+    }
+
+    #[test]
+    fn attenuates_audio_above_the_target() {
+        let mut limiter = LoudnessLimiter::new(DEFAULT_TARGET_LOUDNESS, FadeControl::default());
+
+        // A sine with an amplitude of 0.5 measures about -6 LUFS, 7 LU above the threshold
+        let mut samples = sine(0.5, 10.0);
+        run(&mut limiter, &mut samples);
+
+        assert!(
+            (-7.5..-6.5).contains(&limiter.desired_gain_db),
+            "{}",
+            limiter.desired_gain_db
+        );
+        assert!(
+            (limiter.gain_db - limiter.desired_gain_db).abs() < 0.1,
+            "{} dB instead of {} dB",
+            limiter.gain_db,
+            limiter.desired_gain_db
+        );
+
+        let tail = &samples[samples.len() - BLOCK_FRAMES * CHANNELS..];
+        let peak = tail
+            .iter()
+            .fold(0f32, |peak, sample| peak.max(sample.abs()));
+        let expected = 0.5 * 10f32.powf(limiter.gain_db / 20.0);
+        assert!(
+            (peak - expected).abs() < 0.01,
+            "{peak} instead of {expected}"
+        );
+    }
+
+    #[test]
+    fn never_boosts_audio_below_the_target() {
+        let mut limiter = LoudnessLimiter::new(DEFAULT_TARGET_LOUDNESS, FadeControl::default());
+
+        // About -26 LUFS, well below the threshold
+        let input = sine(0.05, 5.0);
+        let mut samples = input.clone();
+        run(&mut limiter, &mut samples);
+
+        assert_eq!(limiter.desired_gain_db, 0.0);
+        assert_eq!(samples, input);
+    }
+
+    #[test]
+    fn leaves_normalised_tracks_alone() {
+        let control = FadeControl::default();
+        control.set_track_normalised(true);
+
+        let mut limiter = LoudnessLimiter::new(DEFAULT_TARGET_LOUDNESS, control);
+
+        let input = sine(0.5, 5.0);
+        let mut samples = input.clone();
+        run(&mut limiter, &mut samples);
+
+        assert_eq!(samples, input);
+    }
+
+    #[test]
+    fn measures_loudness_before_the_volume() {
+        let control = FadeControl::default();
+        let mut limiter = LoudnessLimiter::new(DEFAULT_TARGET_LOUDNESS, control.clone());
+
+        // The same track as above, played at half volume
+        control.set_volume(0.5);
+        let mut samples = sine(0.25, 5.0);
+        run(&mut limiter, &mut samples);
+
+        assert!(
+            (-7.5..-6.5).contains(&limiter.desired_gain_db),
+            "{}",
+            limiter.desired_gain_db
+        );
+    }
+
+    #[test]
+    fn attacks_within_its_time_constant() {
+        let mut limiter = LoudnessLimiter::new(DEFAULT_TARGET_LOUDNESS, FadeControl::default());
+
+        let mut samples = sine(0.5, ATTACK);
+        run(&mut limiter, &mut samples);
+
+        // A one pole smoother covers 1 - 1/e of the distance within its time constant
+        let progress = limiter.gain_db / limiter.desired_gain_db;
+        assert!((0.58..0.68).contains(&progress), "{progress}");
+    }
+
+    #[test]
+    fn releases_within_its_time_constant() {
+        let mut limiter = LoudnessLimiter::new(DEFAULT_TARGET_LOUDNESS, FadeControl::default());
+        limiter.gain_db = -10.0;
+
+        let mut samples = vec![0.0; RELEASE as usize * SAMPLE_RATE as usize * CHANNELS];
+        run(&mut limiter, &mut samples);
+
+        assert_eq!(limiter.desired_gain_db, 0.0);
+        assert!(
+            (-4.0..-3.4).contains(&limiter.gain_db),
+            "{}",
+            limiter.gain_db
+        );
+    }
+}
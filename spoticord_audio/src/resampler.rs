@@ -0,0 +1,260 @@
+use std::{f64::consts::PI, sync::LazyLock};
+
+use librespot::playback::{NUM_CHANNELS, SAMPLE_RATE};
+
+/// The sample rate Discord expects, audio at this rate passes through songbird without being resampled
+pub const OUTPUT_SAMPLE_RATE: u32 = 48000;
+
+/// Interpolation factor, 44100 * 160 / 147 = 48000
+const UP: usize = 160;
+/// Decimation factor
+const DOWN: usize = 147;
+
+const _: () = assert!(SAMPLE_RATE as usize * UP / DOWN == OUTPUT_SAMPLE_RATE as usize);
+
+/// The amount of input frames every output frame is computed from
+const TAPS: usize = 48;
+
+/// The tap that lines up with the output frame in the first phase, the delay of the filter in input frames
+const CENTER: usize = TAPS / 2 - 1;
+
+const CHANNELS: usize = NUM_CHANNELS as usize;
+
+/// The cutoff frequency of the low pass filter, relative to the Nyquist frequency of the input
+const CUTOFF: f64 = 0.92;
+
+/// Polyphase filter bank, shared between all resamplers as it only depends on the constants above
+static PHASES: LazyLock<Vec<[f32; TAPS]>> = LazyLock::new(build_phases);
+
+/// Converts interleaved stereo audio from [`SAMPLE_RATE`] to [`OUTPUT_SAMPLE_RATE`] using a
+/// windowed sinc polyphase filter.
+pub struct Resampler {
+    /// Input frames that are still needed to compute upcoming output frames
+    input: Vec<f32>,
+    /// The sub-sample position of the next output frame, in steps of 1/[`UP`] input frames
+    phase: usize,
+}
+
+impl Resampler {
+    pub fn new() -> Self {
+        Self {
+            // Silence in front of the first frame, so the first output frame lines up with it
+            input: vec![0.0; CENTER * CHANNELS],
+            phase: 0,
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.input.extend_from_slice(samples);
+
+        let frames = self.input.len() / CHANNELS;
+        let mut output = Vec::with_capacity((samples.len() * UP).div_ceil(DOWN) + CHANNELS);
+        let mut start = 0;
+
+        while start + TAPS <= frames {
+            let coefficients = &PHASES[self.phase];
+            let window = &self.input[start * CHANNELS..(start + TAPS) * CHANNELS];
+
+            for channel in 0..CHANNELS {
+                let sample = window
+                    .iter()
+                    .skip(channel)
+                    .step_by(CHANNELS)
+                    .zip(coefficients)
+                    .map(|(sample, coefficient)| sample * coefficient)
+                    .sum();
+
+                output.push(sample);
+            }
+
+            self.phase += DOWN;
+            start += self.phase / UP;
+            self.phase %= UP;
+        }
+
+        self.input.drain(..start * CHANNELS);
+
+        output
+    }
+
+    /// Push the audio that is still buffered inside of the filter out, and start over.
+    ///
+    /// The returned samples end with the last frame that was passed to [`Resampler::process`].
+    pub fn flush(&mut self) -> Vec<f32> {
+        let output = self.process(&[0.0; (TAPS - 1 - CENTER) * CHANNELS]);
+        self.reset();
+
+        output
+    }
+
+    /// Discard the audio that is buffered inside of the filter
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for Resampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn build_phases() -> Vec<[f32; TAPS]> {
+    let half = TAPS as f64 / 2.0;
+
+    (0..UP)
+        .map(|phase| {
+            let offset = half - 1.0 + phase as f64 / UP as f64;
+            let mut coefficients = [0f64; TAPS];
+
+            for (tap, coefficient) in coefficients.iter_mut().enumerate() {
+                // Distance (in input frames) between this tap and the output frame
+                let x = offset - tap as f64;
+
+                *coefficient = CUTOFF * sinc(CUTOFF * x) * blackman_harris(x / half);
+            }
+
+            // Normalize every phase to unity gain, so that no phase is louder than another
+            let sum = coefficients.iter().sum::<f64>();
+            coefficients.map(|coefficient| (coefficient / sum) as f32)
+        })
+        .collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman-Harris window over the range -1..=1
+fn blackman_harris(x: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+
+    let n = (x + 1.0) / 2.0;
+
+    0.35875 - 0.48829 * (2.0 * PI * n).cos() + 0.14128 * (4.0 * PI * n).cos()
+        - 0.01168 * (6.0 * PI * n).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stereo sine wave, with the right channel in opposite phase of the left channel
+    fn sine(frequency: f64, sample_rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|frame| {
+                let sample = 0.5 * (2.0 * PI * frequency * frame as f64 / sample_rate as f64).sin();
+                [sample as f32, -sample as f32]
+            })
+            .collect()
+    }
+
+    fn resample(samples: &[f32]) -> Vec<f32> {
+        let mut resampler = Resampler::new();
+
+        let mut output = resampler.process(samples);
+        output.extend(resampler.flush());
+
+        output
+    }
+
+    /// Signal to noise ratio in dB of the output against a perfect sine wave at the output rate,
+    /// leaving out the edges where the filter starts and stops
+    fn snr(frequency: f64) -> f64 {
+        let output = resample(&sine(frequency, SAMPLE_RATE, SAMPLE_RATE as usize));
+        let expected = sine(frequency, OUTPUT_SAMPLE_RATE, output.len() / CHANNELS);
+
+        let edge = TAPS * 2 * CHANNELS;
+        let (signal, noise) = output[edge..output.len() - edge]
+            .iter()
+            .zip(&expected[edge..])
+            .fold((0.0, 0.0), |(signal, noise), (&actual, &expected)| {
+                let error = (actual - expected) as f64;
+                (signal + (expected as f64).powi(2), noise + error * error)
+            });
+
+        10.0 * (signal / noise).log10()
+    }
+
+    #[test]
+    fn output_length_matches_rate() {
+        for frames in [1, 147, 1000, SAMPLE_RATE as usize] {
+            let output = resample(&vec![0.1; frames * CHANNELS]);
+            let expected = (frames * UP).div_ceil(DOWN);
+
+            assert_eq!(output.len() % CHANNELS, 0);
+            assert!(
+                (output.len() / CHANNELS).abs_diff(expected) <= 1,
+                "{frames} frames became {} frames instead of {expected}",
+                output.len() / CHANNELS
+            );
+        }
+    }
+
+    #[test]
+    fn phases_have_unity_gain() {
+        for coefficients in PHASES.iter() {
+            let sum = coefficients.iter().sum::<f32>();
+            assert!((sum - 1.0).abs() < 1e-5, "{sum}");
+        }
+
+        // Constant input stays constant, once the filter is filled
+        let output = resample(&vec![0.25; 4410 * CHANNELS]);
+        for sample in &output[TAPS * CHANNELS..output.len() - TAPS * CHANNELS] {
+            assert!((sample - 0.25).abs() < 1e-5, "{sample}");
+        }
+    }
+
+    #[test]
+    fn passband_is_clean() {
+        // Both images and the ripple of the filter stay far below what 16 bit audio can represent
+        for frequency in [100.0, 1000.0, 10000.0] {
+            let snr = snr(frequency);
+            assert!(snr > 115.0, "{frequency} Hz: {snr:.1} dB");
+        }
+    }
+
+    #[test]
+    fn chunk_size_does_not_matter() {
+        let input = sine(440.0, SAMPLE_RATE, 10_000);
+        let expected = resample(&input);
+
+        let mut resampler = Resampler::new();
+        let mut output = Vec::new();
+
+        // Odd chunk sizes, which end in the middle of a frame
+        for chunk in input.chunks(333) {
+            output.extend(resampler.process(chunk));
+        }
+        output.extend(resampler.flush());
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn flush_keeps_the_end() {
+        let mut input = vec![0.0; 1000 * CHANNELS];
+        // A click on the very last frame must still make it out of the filter
+        input[999 * CHANNELS] = 1.0;
+
+        let output = resample(&input);
+        let peak = output
+            .iter()
+            .step_by(CHANNELS)
+            .fold(0f32, |peak, sample| peak.max(sample.abs()));
+
+        assert!(peak > 0.5, "{peak}");
+
+        // And the next run starts over from silence
+        let mut resampler = Resampler::new();
+        resampler.process(&input);
+        resampler.flush();
+        assert!(resampler.flush().iter().all(|sample| *sample == 0.0));
+    }
+}
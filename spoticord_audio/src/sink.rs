@@ -1,23 +1,56 @@
-use crate::stream::Stream;
+use crate::fade::{FadeControl, Fader};
+use crate::filter::FilterChain;
+use crate::resampler::Resampler;
+use crate::stream::{Stream, StreamStats};
 use librespot::playback::audio_backend::{Sink, SinkAsBytes, SinkError, SinkResult};
 use librespot::playback::convert::Converter;
 use librespot::playback::decoder::AudioPacket;
 use std::io::Write;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::UnboundedSender;
+use zerocopy::IntoBytes;
+
+/// How often the sink reports stream telemetry whilst audio is being written
+const STATS_INTERVAL: Duration = Duration::from_secs(10);
 
 pub enum SinkEvent {
     Start,
     Stop,
+    Stats(StreamStats),
 }
 
 pub struct StreamSink {
     stream: Stream,
     sender: UnboundedSender<SinkEvent>,
+    filters: FilterChain,
+    fader: Fader,
+    resampler: Resampler,
+
+    last_stats: Instant,
 }
 
 impl StreamSink {
-    pub fn new(stream: Stream, sender: UnboundedSender<SinkEvent>) -> Self {
-        Self { stream, sender }
+    pub fn new(
+        stream: Stream,
+        sender: UnboundedSender<SinkEvent>,
+        filters: FilterChain,
+        fade: FadeControl,
+    ) -> Self {
+        Self {
+            fader: Fader::new(fade, stream.clone()),
+            stream,
+            sender,
+            filters,
+            resampler: Resampler::new(),
+
+            last_stats: Instant::now(),
+        }
+    }
+
+    fn send_stats(&mut self) {
+        self.last_stats = Instant::now();
+
+        _ = self.sender.send(SinkEvent::Stats(self.stream.stats()));
     }
 }
 
@@ -29,6 +62,9 @@ impl Sink for StreamSink {
             // return Err(SinkError::ConnectionRefused(_why.to_string()));
         }
 
+        self.stream.set_active(true);
+        self.fader.start();
+
         Ok(())
     }
 
@@ -39,19 +75,45 @@ impl Sink for StreamSink {
             // return Err(SinkError::ConnectionRefused(_why.to_string()));
         }
 
-        self.stream.flush().ok();
+        // Let the end of the track play out if it was held back for a crossfade that won't happen
+        let remainder = self.fader.stop();
+
+        if remainder.is_empty() {
+            // The stream has been faded out, so the frames held back by the resampler come from
+            // before the fade and must not be played after it
+            self.resampler.reset();
+        } else {
+            let mut samples = self.resampler.process(&remainder);
+
+            // The resampler holds back a few frames, which would otherwise cut off the end of the track
+            samples.extend(self.resampler.flush());
+
+            self.write_bytes(samples.as_bytes())?;
+        }
+
+        self.stream.set_active(false);
+        self.filters.reset();
+        self.send_stats();
 
         Ok(())
     }
 
     fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
-        use zerocopy::IntoBytes;
-
         let AudioPacket::Samples(samples) = packet else {
             return Ok(());
         };
 
-        self.write_bytes(converter.f64_to_f32(&samples).as_bytes())?;
+        let mut samples = converter.f64_to_f32(&samples);
+        self.filters.process(&mut samples);
+
+        let samples = self.fader.process(samples);
+        let samples = self.resampler.process(&samples);
+
+        self.write_bytes(samples.as_bytes())?;
+
+        if self.last_stats.elapsed() >= STATS_INTERVAL {
+            self.send_stats();
+        }
 
         Ok(())
     }
@@ -66,3 +128,62 @@ impl SinkAsBytes for StreamSink {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use librespot::playback::{NUM_CHANNELS, SAMPLE_RATE};
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::resampler::OUTPUT_SAMPLE_RATE;
+
+    /// The length of the fade that is applied when pausing, in bytes of the stream
+    const FADE_BYTES: usize = 60 * OUTPUT_SAMPLE_RATE as usize / 1000
+        * NUM_CHANNELS as usize
+        * std::mem::size_of::<f32>();
+
+    #[test]
+    fn pause_is_silent_after_the_fade() {
+        let stream = Stream::with_capacity(1024 * 1024);
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let mut sink = StreamSink::new(
+            stream.clone(),
+            sender,
+            FilterChain::new(),
+            FadeControl::default(),
+        );
+        let mut converter = Converter::new(None);
+
+        sink.start().unwrap();
+
+        // Half a second of a loud constant signal, paused in the middle of the stream
+        let samples = vec![0.5; SAMPLE_RATE as usize / 2 * NUM_CHANNELS as usize];
+        sink.write(AudioPacket::Samples(samples), &mut converter)
+            .unwrap();
+        sink.stop().unwrap();
+
+        let mut reader = stream.clone();
+        let mut audio = Vec::new();
+        let mut buf = [0; 4096];
+
+        // Read well past the fade, the stream hands out zeroes once it runs out of audio
+        while audio.len() < FADE_BYTES * 4 {
+            let read = reader.read(&mut buf).unwrap();
+            audio.extend_from_slice(&buf[..read]);
+        }
+
+        let samples = audio
+            .chunks_exact(4)
+            .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect::<Vec<_>>();
+        let fade = FADE_BYTES / 4;
+
+        assert!(samples[..fade].iter().any(|sample| *sample != 0.0));
+        assert!(
+            samples[fade..].iter().all(|sample| *sample == 0.0),
+            "audio was played after the fade"
+        );
+    }
+}
@@ -1,6 +1,11 @@
 use std::{
+    cell::UnsafeCell,
     io::{Read, Seek, Write},
-    sync::{Arc, Condvar, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use songbird::input::core::io::MediaSource;
@@ -10,62 +15,181 @@ use songbird::input::core::io::MediaSource;
 /// Too low of a value results in jittery audio
 const BUFFER_SIZE: usize = 64 * 1024;
 
-#[derive(Clone, Default)]
+/// The maximum amount of time a blocked writer sleeps before checking for free space again.
+///
+/// This is only a safety net, the reader wakes up the writer as soon as it has consumed any data.
+const PARK_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// The size of a single sample in the stream (32-bit float)
+const SAMPLE_SIZE: u64 = std::mem::size_of::<f32>() as u64;
+
+/// The states of the reader side of the stream, see [`ReaderGuard`]
+const READER_IDLE: u8 = 0;
+const READER_READING: u8 = 1;
+const READER_FLUSHING: u8 = 2;
+
+/// An audio stream backed by a fixed-capacity single producer, single consumer ring buffer.
+///
+/// The stream can be cloned freely, but at most one clone may be writing and at most one clone
+/// may be reading at any given time. Concurrent writes (or concurrent reads) are rejected.
+#[derive(Clone)]
 pub struct Stream {
-    inner: Arc<(Mutex<Vec<u8>>, Condvar)>,
+    inner: Arc<RingBuffer>,
 }
 
 impl Stream {
     pub fn new() -> Self {
-        Self::default()
+        Self::with_capacity(BUFFER_SIZE)
+    }
+
+    /// Create a new stream that can hold at most `capacity` bytes of audio
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "stream capacity must be greater than zero");
+
+        Self {
+            inner: Arc::new(RingBuffer::new(capacity)),
+        }
+    }
+
+    /// The maximum amount of bytes this stream can hold
+    pub fn capacity(&self) -> usize {
+        self.inner.data.len()
+    }
+
+    /// The amount of bytes that are currently waiting to be read
+    pub fn fill_level(&self) -> usize {
+        self.inner.fill_level()
+    }
+
+    /// The total amount of bytes that have been written into this stream
+    pub fn bytes_written(&self) -> u64 {
+        self.inner.head.load(Ordering::Acquire)
+    }
+
+    /// The total amount of audio bytes that have been read from this stream.
+    ///
+    /// This excludes bytes that were discarded by a flush, and zeroes that were handed out
+    /// because the stream had no audio available.
+    pub fn bytes_read(&self) -> u64 {
+        self.inner.bytes_read.load(Ordering::Acquire)
+    }
+
+    /// Mark whether audio is expected to flow through this stream.
+    ///
+    /// Reads from an empty stream are only counted as underruns while the stream is active.
+    pub fn set_active(&self, active: bool) {
+        self.inner.active.store(active, Ordering::Release);
+    }
+
+    /// Discard all audio that has not been read yet, after fading out the first `len` bytes of it.
+    ///
+    /// The fade is applied by the reader, so it only ever touches audio that has not been played yet.
+    pub fn fade_out(&self, len: usize) {
+        let inner = &self.inner;
+
+        inner.fade_len.store(len as u64, Ordering::Release);
+        inner
+            .flush_to
+            .fetch_max(inner.head.load(Ordering::Acquire), Ordering::AcqRel);
+    }
+
+    /// Retrieve a snapshot of the telemetry of this stream
+    pub fn stats(&self) -> StreamStats {
+        let inner = &self.inner;
+
+        StreamStats {
+            capacity: self.capacity(),
+            fill_level: self.fill_level(),
+            max_fill_level: inner.max_fill_level.load(Ordering::Acquire),
+            bytes_written: self.bytes_written(),
+            bytes_read: self.bytes_read(),
+            underruns: inner.underruns.load(Ordering::Acquire),
+            zero_filled_bytes: inner.zero_filled_bytes.load(Ordering::Acquire),
+            writer_blocked: Duration::from_nanos(inner.writer_blocked_ns.load(Ordering::Acquire)),
+            longest_writer_block: Duration::from_nanos(
+                inner.longest_writer_block_ns.load(Ordering::Acquire),
+            ),
+        }
+    }
+}
+
+/// Telemetry of a [`Stream`], accumulated over the entire lifetime of the stream
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamStats {
+    /// The maximum amount of bytes the stream can hold
+    pub capacity: usize,
+    /// The amount of bytes waiting to be read at the time of the snapshot
+    pub fill_level: usize,
+    /// The highest fill level that has been observed
+    pub max_fill_level: usize,
+
+    pub bytes_written: u64,
+    pub bytes_read: u64,
+
+    /// The amount of reads that found no audio whilst the stream was active
+    pub underruns: u64,
+    /// The amount of zeroes that were handed out to the reader because of underruns
+    pub zero_filled_bytes: u64,
+
+    /// The total time the writer spent waiting for the buffer to free up
+    pub writer_blocked: Duration,
+    /// The longest single period of time the writer had to wait for the buffer to free up
+    pub longest_writer_block: Duration,
+}
+
+impl Default for Stream {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Read for Stream {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let (mutex, condvar) = &*self.inner;
-        let mut buffer = mutex.lock().expect("Mutex was poisoned");
+        let _guard = ReaderGuard::read(&self.inner.reader)?;
+
+        let read = self.inner.pop(buf);
 
         // Prevent Discord jitter by filling buffer with zeroes if we don't have any audio
         // (i.e. when you skip too far ahead in a song which hasn't been downloaded yet)
-        if buffer.is_empty() {
+        if read == 0 {
             buf.fill(0);
-            condvar.notify_all();
+            self.inner.record_underrun(buf.len());
 
             return Ok(buf.len());
         }
 
-        let max_read = usize::min(buf.len(), buffer.len());
-
-        buf[0..max_read].copy_from_slice(&buffer[0..max_read]);
-        buffer.drain(0..max_read);
-        condvar.notify_all();
-
-        Ok(max_read)
+        Ok(read)
     }
 }
 
 impl Write for Stream {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let (mutex, condvar) = &*self.inner;
-        let mut buffer = mutex.lock().expect("Mutex was poisoned");
-
-        while buffer.len() + buf.len() > BUFFER_SIZE {
-            buffer = condvar.wait(buffer).expect("Mutex was poisoned");
+        if buf.is_empty() {
+            return Ok(0);
         }
 
-        buffer.extend_from_slice(buf);
-        condvar.notify_all();
+        let _guard = RoleGuard::acquire(&self.inner.writing, "writer")?;
 
-        Ok(buf.len())
+        loop {
+            let written = self.inner.push(buf);
+            if written > 0 {
+                return Ok(written);
+            }
+
+            let start = Instant::now();
+            self.inner.wait_for_space();
+            self.inner.record_writer_block(start.elapsed());
+        }
     }
 
+    /// Discard all audio that has not been read yet.
+    ///
+    /// The space is freed right away, so a writer never has to wait for the reader after a flush,
+    /// even if nothing is reading from the stream (like while playback is paused).
     fn flush(&mut self) -> std::io::Result<()> {
-        let (mutex, condvar) = &*self.inner;
-        let mut buffer = mutex.lock().expect("Mutex was poisoned");
+        let _guard = ReaderGuard::flush(&self.inner.reader);
 
-        buffer.clear();
-        condvar.notify_all();
+        self.inner.clear();
 
         Ok(())
     }
@@ -86,3 +210,488 @@ impl MediaSource for Stream {
         false
     }
 }
+
+/// Lock-free SPSC ring buffer.
+///
+/// `head` and `tail` are monotonically increasing byte counters, the actual position inside of the
+/// buffer is the counter modulo the capacity. Only the writer advances `head`, and only the reader
+/// advances `tail`, so neither side ever touches bytes that the other side is working on.
+struct RingBuffer {
+    data: Box<[UnsafeCell<u8>]>,
+
+    /// Total amount of bytes written into the buffer
+    head: AtomicU64,
+    /// Total amount of bytes consumed from the buffer (including flushed bytes)
+    tail: AtomicU64,
+    /// Everything before this position has been flushed and must be skipped by the reader
+    flush_to: AtomicU64,
+    /// Total amount of bytes that were handed out to the reader
+    bytes_read: AtomicU64,
+
+    /// Amount of flushed bytes the reader should fade out instead of skipping
+    fade_len: AtomicU64,
+    /// The fade the reader is currently applying, only touched by the reader
+    fade_start: AtomicU64,
+    fade_end: AtomicU64,
+
+    writing: AtomicBool,
+    /// Whether the reader side is idle, reading, or being cleared by a flush
+    reader: AtomicU8,
+    active: AtomicBool,
+
+    // Telemetry
+    max_fill_level: AtomicUsize,
+    underruns: AtomicU64,
+    zero_filled_bytes: AtomicU64,
+    writer_blocked_ns: AtomicU64,
+    longest_writer_block_ns: AtomicU64,
+
+    // Only used to park the writer while the buffer is full, never touched on the hot path
+    writer_parked: AtomicBool,
+    park_lock: Mutex<()>,
+    park_condvar: Condvar,
+}
+
+// SAFETY: The contents of `data` are only ever accessed through `push` and `pop`, which are
+// guarded by the `writing` flag and the `reader` state respectively. The head/tail counters guarantee
+// that the writer and the reader never access the same bytes at the same time.
+unsafe impl Sync for RingBuffer {}
+
+impl RingBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            data: (0..capacity).map(|_| UnsafeCell::new(0)).collect(),
+
+            head: AtomicU64::new(0),
+            tail: AtomicU64::new(0),
+            flush_to: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+
+            fade_len: AtomicU64::new(0),
+            fade_start: AtomicU64::new(0),
+            fade_end: AtomicU64::new(0),
+
+            writing: AtomicBool::new(false),
+            reader: AtomicU8::new(READER_IDLE),
+            active: AtomicBool::new(false),
+
+            max_fill_level: AtomicUsize::new(0),
+            underruns: AtomicU64::new(0),
+            zero_filled_bytes: AtomicU64::new(0),
+            writer_blocked_ns: AtomicU64::new(0),
+            longest_writer_block_ns: AtomicU64::new(0),
+
+            writer_parked: AtomicBool::new(false),
+            park_lock: Mutex::new(()),
+            park_condvar: Condvar::new(),
+        }
+    }
+
+    fn capacity(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn fill_level(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        let head = self.head.load(Ordering::Acquire);
+        let flush_to = self.flush_to.load(Ordering::Acquire);
+
+        (head - tail.max(flush_to).min(head)) as usize
+    }
+
+    fn base(&self) -> *mut u8 {
+        // UnsafeCell<u8> has the same in-memory representation as u8
+        UnsafeCell::raw_get(self.data.as_ptr())
+    }
+
+    /// Copy as many bytes as currently fit into the buffer, returning the amount copied.
+    ///
+    /// Must only be called by the writer.
+    fn push(&self, buf: &[u8]) -> usize {
+        let capacity = self.capacity();
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+
+        let free = (capacity - (head - tail)) as usize;
+        let amount = free.min(buf.len());
+        if amount == 0 {
+            return 0;
+        }
+
+        let start = (head % capacity) as usize;
+        let first = amount.min(self.data.len() - start);
+
+        // SAFETY: The region [head, head + amount) is not readable by the reader until `head`
+        // is advanced, and it lies entirely after `tail`, so the reader is not accessing it.
+        unsafe {
+            std::ptr::copy_nonoverlapping(buf.as_ptr(), self.base().add(start), first);
+            std::ptr::copy_nonoverlapping(buf.as_ptr().add(first), self.base(), amount - first);
+        }
+
+        self.head.store(head + amount as u64, Ordering::Release);
+        self.max_fill_level
+            .fetch_max((head + amount as u64 - tail) as usize, Ordering::AcqRel);
+
+        amount
+    }
+
+    /// Copy as many bytes as are available into `buf`, returning the amount copied.
+    ///
+    /// Must only be called by the reader.
+    fn pop(&self, buf: &mut [u8]) -> usize {
+        let capacity = self.capacity();
+        let head = self.head.load(Ordering::Acquire);
+        let mut tail = self.tail.load(Ordering::Relaxed);
+
+        // Skip over audio that has been flushed by the writer, unless it has to be faded out first
+        let flush_to = self.flush_to.load(Ordering::Acquire);
+        let mut fade_end = self.fade_end.load(Ordering::Relaxed);
+
+        if flush_to > tail && fade_end == 0 {
+            let end = (tail + self.fade_len.swap(0, Ordering::AcqRel)).min(flush_to);
+            let end = end - end % SAMPLE_SIZE;
+
+            if end > tail {
+                fade_end = end;
+                self.fade_start.store(tail, Ordering::Relaxed);
+                self.fade_end.store(fade_end, Ordering::Relaxed);
+            } else {
+                tail = flush_to.min(head);
+                self.tail.store(tail, Ordering::Release);
+                self.wake_writer();
+            }
+        }
+
+        let mut amount = ((head - tail) as usize).min(buf.len());
+        if fade_end > tail {
+            // Only hand out whole samples during a fade, so that no sample is half faded
+            let end = (tail + amount as u64).min(fade_end);
+            let end = end - end % SAMPLE_SIZE;
+
+            if end > tail {
+                amount = (end - tail) as usize;
+            }
+        }
+
+        if amount == 0 {
+            return 0;
+        }
+
+        let start = (tail % capacity) as usize;
+        let first = amount.min(self.data.len() - start);
+
+        // SAFETY: The region [tail, tail + amount) has been published by the writer through
+        // `head`, and the writer will not touch it again until `tail` has been advanced.
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.base().add(start), buf.as_mut_ptr(), first);
+            std::ptr::copy_nonoverlapping(self.base(), buf.as_mut_ptr().add(first), amount - first);
+        }
+
+        if fade_end > tail {
+            self.apply_fade(tail, &mut buf[..amount]);
+
+            if tail + amount as u64 >= fade_end {
+                // The flushed audio is skipped on the next read, along with any fades that were
+                // requested while this one was in progress
+                self.fade_end.store(0, Ordering::Relaxed);
+                self.fade_len.store(0, Ordering::Release);
+            }
+        }
+
+        self.tail.store(tail + amount as u64, Ordering::Release);
+        self.bytes_read.fetch_add(amount as u64, Ordering::AcqRel);
+        self.wake_writer();
+
+        amount
+    }
+
+    /// Discard everything that has been written, including a fade that is in progress.
+    ///
+    /// Must only be called while holding the reader side, see [`ReaderGuard::flush`].
+    fn clear(&self) {
+        let head = self.head.load(Ordering::Acquire);
+
+        self.fade_end.store(0, Ordering::Relaxed);
+        self.fade_len.store(0, Ordering::Release);
+        self.flush_to.fetch_max(head, Ordering::AcqRel);
+        self.tail.store(head, Ordering::Release);
+
+        self.wake_writer();
+    }
+
+    /// Linearly fade out the samples in `buf`, which were read starting at position `tail`.
+    ///
+    /// Must only be called by the reader.
+    fn apply_fade(&self, tail: u64, buf: &mut [u8]) {
+        let fade_start = self.fade_start.load(Ordering::Relaxed);
+        let fade_end = self.fade_end.load(Ordering::Relaxed);
+        let length = (fade_end - fade_start) as f32;
+
+        // Skip the remainder of a sample that was partially read before the fade started
+        let skip = (((SAMPLE_SIZE - tail % SAMPLE_SIZE) % SAMPLE_SIZE) as usize).min(buf.len());
+        let mut position = tail + skip as u64;
+
+        for bytes in buf[skip..].chunks_exact_mut(SAMPLE_SIZE as usize) {
+            let gain = fade_end.saturating_sub(position) as f32 / length;
+            let sample = f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) * gain;
+
+            bytes.copy_from_slice(&sample.to_ne_bytes());
+            position += SAMPLE_SIZE;
+        }
+    }
+
+    fn wait_for_space(&self) {
+        let guard = self.park_lock.lock().expect("Mutex was poisoned");
+        self.writer_parked.store(true, Ordering::SeqCst);
+
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::SeqCst);
+
+        if head - tail >= self.capacity() {
+            _ = self
+                .park_condvar
+                .wait_timeout(guard, PARK_TIMEOUT)
+                .expect("Mutex was poisoned");
+        }
+
+        self.writer_parked.store(false, Ordering::SeqCst);
+    }
+
+    fn record_underrun(&self, zero_filled: usize) {
+        if !self.active.load(Ordering::Acquire) {
+            return;
+        }
+
+        self.underruns.fetch_add(1, Ordering::AcqRel);
+        self.zero_filled_bytes
+            .fetch_add(zero_filled as u64, Ordering::AcqRel);
+    }
+
+    fn record_writer_block(&self, duration: Duration) {
+        let nanos = duration.as_nanos() as u64;
+
+        self.writer_blocked_ns.fetch_add(nanos, Ordering::AcqRel);
+        self.longest_writer_block_ns
+            .fetch_max(nanos, Ordering::AcqRel);
+    }
+
+    fn wake_writer(&self) {
+        if self.writer_parked.load(Ordering::SeqCst) {
+            let _guard = self.park_lock.lock().expect("Mutex was poisoned");
+            self.park_condvar.notify_one();
+        }
+    }
+}
+
+/// Marks the stream as being used by a writer or reader for as long as the guard lives
+struct RoleGuard<'a>(&'a AtomicBool);
+
+impl<'a> RoleGuard<'a> {
+    fn acquire(flag: &'a AtomicBool, role: &str) -> std::io::Result<Self> {
+        if flag.swap(true, Ordering::Acquire) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                format!("stream already has an active {role}"),
+            ));
+        }
+
+        Ok(Self(flag))
+    }
+}
+
+impl Drop for RoleGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Gives exclusive access to the reader side of the stream for as long as the guard lives.
+///
+/// A flush takes over the reader side for a moment to discard the buffered audio, so a reader that
+/// comes in during a flush waits for it to finish instead of being rejected like a second reader.
+struct ReaderGuard<'a>(&'a AtomicU8);
+
+impl<'a> ReaderGuard<'a> {
+    fn read(state: &'a AtomicU8) -> std::io::Result<Self> {
+        loop {
+            match state.compare_exchange(
+                READER_IDLE,
+                READER_READING,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Ok(Self(state)),
+                Err(READER_READING) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::WouldBlock,
+                        "stream already has an active reader",
+                    ))
+                }
+                Err(_) => std::thread::yield_now(),
+            }
+        }
+    }
+
+    /// Wait for a read that is in progress to finish, which never takes longer than a single copy
+    fn flush(state: &'a AtomicU8) -> Self {
+        while state
+            .compare_exchange(
+                READER_IDLE,
+                READER_FLUSHING,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            std::thread::yield_now();
+        }
+
+        Self(state)
+    }
+}
+
+impl Drop for ReaderGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(READER_IDLE, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Read exactly `len` bytes of audio, skipping the zeroes that are handed out while the stream is empty
+    fn read_audio(stream: &mut Stream, len: usize) -> Vec<u8> {
+        let mut audio = Vec::with_capacity(len);
+        let mut buf = [0; 5];
+
+        while audio.len() < len {
+            if stream.fill_level() == 0 {
+                thread::yield_now();
+                continue;
+            }
+
+            let want = buf.len().min(len - audio.len());
+            let read = stream.read(&mut buf[..want]).unwrap();
+            audio.extend_from_slice(&buf[..read]);
+        }
+
+        audio
+    }
+
+    #[test]
+    fn wraps_around_the_end_of_the_buffer() {
+        let mut stream = Stream::with_capacity(8);
+
+        stream.write_all(&[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(read_audio(&mut stream, 4), [1, 2, 3, 4]);
+
+        // Only 2 bytes fit before the end of the buffer, the rest wraps around to the start
+        stream.write_all(&[7, 8, 9, 10, 11, 12]).unwrap();
+        assert_eq!(stream.fill_level(), 8);
+        assert_eq!(read_audio(&mut stream, 8), [5, 6, 7, 8, 9, 10, 11, 12]);
+
+        assert_eq!(stream.bytes_written(), 12);
+        assert_eq!(stream.bytes_read(), 12);
+    }
+
+    #[test]
+    fn reads_zeroes_from_an_empty_stream() {
+        let mut stream = Stream::with_capacity(8);
+        let mut buf = [1; 4];
+
+        assert_eq!(stream.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0; 4]);
+        assert_eq!(stream.bytes_read(), 0);
+    }
+
+    #[test]
+    fn flush_discards_unread_audio() {
+        let mut stream = Stream::with_capacity(8);
+
+        stream.write_all(&[1, 2, 3, 4, 5]).unwrap();
+        stream.flush().unwrap();
+        assert_eq!(stream.fill_level(), 0);
+
+        stream.write_all(&[6, 7]).unwrap();
+        assert_eq!(read_audio(&mut stream, 2), [6, 7]);
+    }
+
+    #[test]
+    fn flush_frees_space_without_a_reader() {
+        let mut stream = Stream::with_capacity(8);
+        stream.write_all(&[1; 8]).unwrap();
+        stream.flush().unwrap();
+
+        // Nothing reads from the stream, so this would block forever if the space wasn't freed
+        let (tx, rx) = mpsc::channel();
+        let mut writer = stream.clone();
+        thread::spawn(move || {
+            writer.write_all(&[2; 8]).unwrap();
+            _ = tx.send(());
+        });
+
+        rx.recv_timeout(TIMEOUT)
+            .expect("writer is still blocked after a flush");
+        assert_eq!(read_audio(&mut stream, 8), [2; 8]);
+    }
+
+    #[test]
+    fn flush_wakes_up_a_blocked_writer() {
+        let mut stream = Stream::with_capacity(8);
+
+        let (tx, rx) = mpsc::channel();
+        let mut writer = stream.clone();
+        thread::spawn(move || {
+            writer.write_all(&[1; 12]).unwrap();
+            _ = tx.send(());
+        });
+
+        // Wait for the writer to fill up the buffer and block on the remaining bytes
+        while stream.fill_level() < 8 {
+            thread::yield_now();
+        }
+
+        stream.flush().unwrap();
+        rx.recv_timeout(TIMEOUT)
+            .expect("writer is still blocked after a flush");
+        assert_eq!(read_audio(&mut stream, 4), [1; 4]);
+    }
+
+    #[test]
+    fn concurrent_reads_and_writes_keep_the_audio_intact() {
+        const LEN: usize = 1024 * 1024;
+
+        let audio = (0..LEN).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut stream = Stream::with_capacity(1000);
+
+        let mut writer = stream.clone();
+        let written = audio.clone();
+        let handle = thread::spawn(move || {
+            // Odd chunk sizes make both sides wrap around at different positions
+            for chunk in written.chunks(777) {
+                writer.write_all(chunk).unwrap();
+            }
+        });
+
+        assert!(read_audio(&mut stream, LEN) == audio);
+        handle.join().unwrap();
+
+        assert_eq!(stream.bytes_written(), LEN as u64);
+        assert_eq!(stream.bytes_read(), LEN as u64);
+        assert!(stream.stats().max_fill_level <= 1000);
+    }
+
+    #[test]
+    fn rejects_a_second_writer() {
+        let stream = Stream::with_capacity(8);
+        let _guard = RoleGuard::acquire(&stream.inner.writing, "writer").unwrap();
+
+        let error = stream.clone().write(&[1]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::WouldBlock);
+    }
+}
@@ -12,9 +12,8 @@ pub static WEB_PORT: LazyLock<u16> = LazyLock::new(|| {
         .parse()
         .expect("WEB_PORT must be a valid port number")
 });
-pub static DATA_DIR: LazyLock<String> = LazyLock::new(|| {
-    std::env::var("DATA_DIR").unwrap_or_else(|_| "./data".to_string())
-});
+pub static DATA_DIR: LazyLock<String> =
+    LazyLock::new(|| std::env::var("DATA_DIR").unwrap_or_else(|_| "./data".to_string()));
 pub static SPOTIFY_CLIENT_ID: LazyLock<String> = LazyLock::new(|| {
     std::env::var("SPOTIFY_CLIENT_ID").expect("missing SPOTIFY_CLIENT_ID environment variable")
 });
@@ -22,3 +21,24 @@ pub static SPOTIFY_CLIENT_SECRET: LazyLock<String> = LazyLock::new(|| {
     std::env::var("SPOTIFY_CLIENT_SECRET")
         .expect("missing SPOTIFY_CLIENT_SECRET environment variable")
 });
+pub static BITRATE: LazyLock<u16> = LazyLock::new(|| {
+    let bitrate = std::env::var("BITRATE")
+        .unwrap_or_else(|_| "160".to_string())
+        .parse()
+        .expect("BITRATE must be a number");
+
+    assert!(
+        matches!(bitrate, 96 | 160 | 320),
+        "BITRATE must be one of 96, 160 or 320"
+    );
+
+    bitrate
+});
+pub static CACHE_SIZE: LazyLock<u64> = LazyLock::new(|| {
+    std::env::var("CACHE_SIZE")
+        .unwrap_or_else(|_| (1024 * 1024 * 1024).to_string())
+        .parse()
+        .expect("CACHE_SIZE must be a number of bytes")
+});
+pub static CREDENTIALS_KEY: LazyLock<Option<String>> =
+    LazyLock::new(|| std::env::var("CREDENTIALS_KEY").ok());
@@ -43,6 +43,28 @@ pub fn spotify_client_secret() -> &'static str {
     &env::SPOTIFY_CLIENT_SECRET
 }
 
+/// The bitrate in kbps that Spotify audio is streamed at, unless a guild has chosen another one
+pub fn bitrate() -> u16 {
+    *env::BITRATE
+}
+
+/// The directory that downloaded Spotify audio is cached in
+pub fn cache_dir() -> std::path::PathBuf {
+    std::path::Path::new(data_dir()).join("cache")
+}
+
+/// The maximum size in bytes of the audio cache, `0` disables caching
+pub fn cache_size() -> u64 {
+    *env::CACHE_SIZE
+}
+
+/// The secret that stored Spotify credentials are encrypted with, defaults to the Spotify client secret
+pub fn credentials_key() -> &'static str {
+    env::CREDENTIALS_KEY
+        .as_deref()
+        .unwrap_or(&env::SPOTIFY_CLIENT_SECRET)
+}
+
 pub fn get_spotify(token: Token) -> AuthCodeSpotify {
     AuthCodeSpotify::from_token_with_config(
         token,
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use librespot::metadata::Lyrics;
+use spoticord_audio::stream::StreamStats;
+
+use crate::info::{PlaybackContext, PlaybackInfo, RepeatMode, UpcomingTrack};
+
+/// Something that plays music into a voice call, controlled through a [`PlayerHandle`](crate::PlayerHandle)
+#[async_trait]
+pub trait PlaybackBackend: Send + Sync {
+    /// Whether the backend is still accepting commands
+    fn is_valid(&self) -> bool;
+
+    /// The ID of the Spotify Connect device of this backend, as used by the Spotify Web API
+    fn device_id(&self) -> &str;
+
+    async fn next_track(&self);
+    async fn previous_track(&self);
+    async fn pause(&self);
+    async fn play(&self);
+
+    /// Jump to a position (in milliseconds) in the current track
+    async fn seek(&self, position_ms: u32);
+
+    async fn set_shuffle(&self, shuffle: bool);
+    async fn set_repeat(&self, repeat: RepeatMode);
+    async fn set_upcoming(&self, context: Option<PlaybackContext>, next_tracks: Vec<UpcomingTrack>);
+
+    /// Change the volume, in percent
+    async fn set_volume(&self, volume: u8);
+
+    /// Retrieve the current volume, in percent
+    async fn volume(&self) -> Result<u8>;
+
+    async fn playback_info(&self) -> Result<Option<PlaybackInfo>>;
+    async fn lyrics(&self) -> Result<Option<Lyrics>>;
+    async fn audio_stats(&self) -> Result<StreamStats>;
+
+    async fn shutdown(&self);
+}
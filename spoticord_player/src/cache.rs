@@ -0,0 +1,80 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+
+use librespot::{core::cache::Cache, metadata::audio::AudioItem};
+
+/// An on-disk cache of Spotify audio files, which can be shared between all players.
+///
+/// Once the cache exceeds its size limit, the least recently used files are removed.
+#[derive(Clone)]
+pub struct AudioCache {
+    cache: Cache,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+/// How often tracks could be played from the [`AudioCache`] instead of being downloaded
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// The fraction of tracks that were played from the cache, or `None` if nothing has been played yet
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+}
+
+impl AudioCache {
+    /// Create a cache in `dir` that holds at most `size_limit` bytes of audio
+    pub fn new(dir: impl AsRef<Path>, size_limit: u64) -> Result<Self, librespot::core::Error> {
+        let cache = Cache::new(None, None, Some(dir.as_ref()), Some(size_limit))?;
+
+        Ok(Self {
+            cache,
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn cache(&self) -> Cache {
+        self.cache.clone()
+    }
+
+    /// Count a track that started playing as a hit if librespot could take it from the cache.
+    ///
+    /// Files that librespot wrote into the cache after `started` were downloaded for this load, rather than played
+    /// from the cache.
+    pub(crate) fn record_load(&self, audio_item: &AudioItem, started: SystemTime) {
+        let cached = audio_item.files.values().any(|file_id| {
+            self.cache
+                .file_path(*file_id)
+                .and_then(|path| path.metadata().ok())
+                .and_then(|metadata| metadata.modified().ok())
+                .is_some_and(|modified| modified < started)
+        });
+
+        if cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
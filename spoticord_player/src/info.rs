@@ -8,6 +8,32 @@ use librespot::{
     },
 };
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    /// Repeat the entire playlist, album or other context
+    Context,
+    /// Repeat the current track
+    Track,
+}
+
+/// The playlist, album, artist or show that tracks are being played from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaybackContext {
+    pub uri: String,
+    pub name: Option<String>,
+}
+
+/// A track or episode that will be played after the current one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpcomingTrack {
+    pub uri: String,
+    pub name: String,
+    /// The artists of a track, or the show of an episode
+    pub artists: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct PlaybackInfo {
     audio_item: AudioItem,
@@ -15,6 +41,14 @@ pub struct PlaybackInfo {
     updated_at: u128,
     position: u32,
     playing: bool,
+
+    shuffle: bool,
+    repeat: RepeatMode,
+    /// The volume of the player, in percent
+    volume: u8,
+
+    context: Option<PlaybackContext>,
+    next_tracks: Vec<UpcomingTrack>,
 }
 
 impl PlaybackInfo {
@@ -25,6 +59,13 @@ impl PlaybackInfo {
             updated_at: spoticord_utils::get_time(),
             position,
             playing,
+
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            volume: 0,
+
+            context: None,
+            next_tracks: Vec::new(),
         }
     }
 
@@ -113,14 +154,65 @@ impl PlaybackInfo {
         self.playing
     }
 
+    pub fn shuffle(&self) -> bool {
+        self.shuffle
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub fn context(&self) -> Option<&PlaybackContext> {
+        self.context.as_ref()
+    }
+
+    pub fn next_tracks(&self) -> &[UpcomingTrack] {
+        &self.next_tracks
+    }
+
     pub fn update_playback(&mut self, position: u32, playing: bool) {
         self.position = position;
         self.playing = playing;
         self.updated_at = spoticord_utils::get_time();
     }
 
+    /// Move on to the next track.
+    ///
+    /// If it is one of the upcoming tracks, we've moved up in the queue. Otherwise the upcoming
+    /// tracks are no longer known, and are cleared until they are provided again.
     pub fn update_track(&mut self, audio_item: AudioItem) {
         self.audio_item = audio_item;
+
+        match self
+            .next_tracks
+            .iter()
+            .position(|track| track.uri == self.audio_item.uri)
+        {
+            Some(index) => _ = self.next_tracks.drain(..=index),
+            None => self.next_tracks.clear(),
+        }
+    }
+
+    pub fn update_modes(&mut self, shuffle: bool, repeat: RepeatMode) {
+        self.shuffle = shuffle;
+        self.repeat = repeat;
+    }
+
+    pub fn update_volume(&mut self, volume: u8) {
+        self.volume = volume;
+    }
+
+    pub fn update_upcoming(
+        &mut self,
+        context: Option<PlaybackContext>,
+        next_tracks: Vec<UpcomingTrack>,
+    ) {
+        self.context = context;
+        self.next_tracks = next_tracks;
     }
 
     pub fn is_episode(&self) -> bool {
@@ -1,53 +1,156 @@
+pub mod backend;
+pub mod cache;
 pub mod info;
+pub mod synthetic;
+
+pub use spoticord_audio::stream::StreamStats;
 
 use anyhow::Result;
-use info::PlaybackInfo;
+use async_trait::async_trait;
+use backend::PlaybackBackend;
+use cache::AudioCache;
+use info::{PlaybackContext, PlaybackInfo, RepeatMode, UpcomingTrack};
 use librespot::{
     connect::{config::ConnectConfig, spirc::Spirc},
     core::{
         connection::AuthenticationError, http_client::HttpClientError, Session as SpotifySession,
-        SessionConfig,
+        SessionConfig, SpotifyId,
     },
     discovery::Credentials,
-    metadata::Lyrics,
+    metadata::{
+        audio::{AudioFileFormat, AudioItem},
+        Lyrics,
+    },
     playback::{
-        config::{Bitrate, PlayerConfig, VolumeCtrl},
-        mixer::{self, MixerConfig},
+        config::{Bitrate, NormalisationType, PlayerConfig, VolumeCtrl},
+        mixer::{self, Mixer, MixerConfig, VolumeGetter},
         player::{Player as SpotifyPlayer, PlayerEvent as SpotifyPlayerEvent},
     },
+    protocol::authentication::AuthenticationType,
 };
 use log::{error, trace};
-use songbird::{input::RawAdapter, tracks::TrackHandle, Call};
+use songbird::{
+    input::RawAdapter,
+    tracks::{PlayMode, TrackHandle},
+    Call, Event, EventContext, EventHandler, TrackEvent,
+};
 use spoticord_audio::{
+    fade::FadeControl,
+    filter::FilterChain,
+    loudness::DEFAULT_TARGET_LOUDNESS,
+    resampler::OUTPUT_SAMPLE_RATE,
     sink::{SinkEvent, StreamSink},
     stream::Stream,
 };
 use std::{
     io::Write,
     sync::{atomic::AtomicBool, Arc},
+    time::{Duration, SystemTime},
 };
 use tokio::sync::{mpsc, oneshot, Mutex};
 
+/// How long before the end a repeated track is started over.
+///
+/// Spirc moves on to the next track as soon as the current one ends, so a repeated track has to
+/// be restarted before that. The player decodes ahead of what is audible by the size of the
+/// stream buffer, which this has to cover.
+const REPEAT_TRACK_MARGIN: Duration = Duration::from_millis(500);
+
 #[derive(Debug)]
 enum PlayerCommand {
     NextTrack,
     PreviousTrack,
     Pause,
     Play,
+    Seek(u32),
+    SetVolume(u8),
+    SetShuffle(bool),
+    SetRepeat(RepeatMode),
+    SetUpcoming(Option<PlaybackContext>, Vec<UpcomingTrack>),
 
     GetPlaybackInfo(oneshot::Sender<Option<PlaybackInfo>>),
+    GetVolume(oneshot::Sender<u8>),
     GetLyrics(oneshot::Sender<Option<Lyrics>>),
+    GetAudioStats(oneshot::Sender<StreamStats>),
 
     Shutdown,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum PlayerEvent {
     Pause,
     Play,
     Stopped,
     TrackChanged(Box<PlaybackInfo>),
     ConnectionReset,
+    AudioStats(StreamStats),
+    /// The volume has changed, either through the bot or through Spotify, in percent
+    VolumeChanged(u8),
+    /// Shuffle or repeat has been toggled
+    ModeChanged {
+        shuffle: bool,
+        repeat: RepeatMode,
+    },
+    /// The context or upcoming tracks of the playback have been updated
+    UpcomingChanged,
+    /// A track failed to load, which may be fixed by streaming at the given lower bitrate
+    BitrateFallback {
+        track: SpotifyId,
+        from: Bitrate,
+        to: Bitrate,
+    },
+    /// A track could not be played, for example because it isn't available in the region of the account
+    TrackUnavailable {
+        track: SpotifyId,
+        name: Option<String>,
+        /// The track failed while it was being preloaded, so Spotify skips it once it gets there
+        preloading: bool,
+    },
+    /// The audio track in the voice call has failed, and won't play anything anymore
+    PlaybackError(String),
+}
+
+/// Options that control how a [`Player`] presents itself and processes its audio
+#[derive(Clone)]
+pub struct PlayerOptions {
+    /// The name of the Spotify Connect device
+    pub device_name: String,
+    /// Filters that are applied to all audio before it is sent to Discord
+    pub filters: FilterChain,
+    /// Controls the crossfade between tracks
+    pub fade: FadeControl,
+
+    /// Have librespot normalize tracks using their loudness metadata, `None` disables normalisation
+    pub normalisation: Option<NormalisationType>,
+    /// The loudness in LUFS that normalisation aims for
+    pub target_loudness: f64,
+
+    /// The volume the player starts at, in percent
+    pub initial_volume: u8,
+
+    pub bitrate: Bitrate,
+
+    /// The cache that downloaded audio is stored in and played from
+    pub cache: Option<AudioCache>,
+}
+
+impl Default for PlayerOptions {
+    fn default() -> Self {
+        Self {
+            device_name: String::new(),
+            filters: FilterChain::default(),
+            fade: FadeControl::default(),
+
+            normalisation: None,
+            target_loudness: DEFAULT_TARGET_LOUDNESS,
+
+            initial_volume: 75,
+
+            bitrate: Bitrate::default(),
+
+            cache: None,
+        }
+    }
 }
 
 pub struct Player {
@@ -55,15 +158,27 @@ pub struct Player {
     spirc: Spirc,
     track: TrackHandle,
     stream: Stream,
+    fade: FadeControl,
+    mixer: Arc<dyn Mixer>,
+    bitrate: Bitrate,
+    cache: Option<AudioCache>,
+    /// The track that is being preloaded
+    preloaded: Option<SpotifyId>,
+    /// When librespot started loading the tracks that haven't started playing yet
+    loads: Vec<(SpotifyId, SystemTime)>,
 
     playback_info: Option<PlaybackInfo>,
 
+    shuffle: bool,
+    repeat: RepeatMode,
+
     // Communication
     events: mpsc::Sender<PlayerEvent>,
 
     commands: mpsc::Receiver<PlayerCommand>,
     spotify_events: mpsc::UnboundedReceiver<SpotifyPlayerEvent>,
     sink_events: mpsc::UnboundedReceiver<SinkEvent>,
+    track_errors: mpsc::UnboundedReceiver<String>,
 
     /// A shared boolean that reflects whether this Player has shut down
     shutdown: Arc<AtomicBool>,
@@ -73,52 +188,77 @@ impl Player {
     pub async fn create(
         credentials: Credentials,
         call: Arc<Mutex<Call>>,
-        device_name: impl Into<String>,
-    ) -> Result<(PlayerHandle, mpsc::Receiver<PlayerEvent>, Vec<u8>), librespot::core::Error> {
+        options: PlayerOptions,
+    ) -> Result<(PlayerHandle, mpsc::Receiver<PlayerEvent>, Credentials), librespot::core::Error>
+    {
         let (event_tx, event_rx) = mpsc::channel(16);
 
         let mut call_lock = call.lock().await;
         let stream = Stream::new();
 
         // Create songbird audio track
-        let adapter = RawAdapter::new(stream.clone(), 44100, 2);
+        // The sink resamples to 48kHz itself, which allows songbird to skip its own resampling
+        let adapter = RawAdapter::new(stream.clone(), OUTPUT_SAMPLE_RATE, 2);
         let track = call_lock.play_only_input(adapter.into());
         _ = track.pause();
 
+        let (tx_track_errors, rx_track_errors) = mpsc::unbounded_channel();
+        if let Err(why) = track.add_event(
+            Event::Track(TrackEvent::Error),
+            TrackErrorNotifier(tx_track_errors),
+        ) {
+            error!("Failed to listen for songbird track errors: {why}");
+        }
+
         // Free call lock before creating session
         drop(call_lock);
 
         // Create librespot audio streamer
-        let session = SpotifySession::new(SessionConfig::default(), None);
+        let session = SpotifySession::new(
+            SessionConfig::default(),
+            options.cache.as_ref().map(AudioCache::cache),
+        );
         let mixer = (mixer::find(Some("softvol")).expect("missing softvol mixer"))(MixerConfig {
             volume_ctrl: VolumeCtrl::Log(VolumeCtrl::DEFAULT_DB_RANGE),
             ..Default::default()
         });
 
+        // The loudness limiter measures the audio after the software volume has been applied
+        options
+            .fade
+            .set_volume(mixer.get_soft_volume().attenuation_factor());
+
+        let mut config = PlayerConfig {
+            bitrate: options.bitrate,
+            ..Default::default()
+        };
+
+        if let Some(normalisation_type) = options.normalisation {
+            config.normalisation = true;
+            config.normalisation_type = normalisation_type;
+
+            // The loudness metadata of Spotify tracks is relative to -14 LUFS
+            config.normalisation_pregain_db = options.target_loudness - DEFAULT_TARGET_LOUDNESS;
+        }
+
         let (tx_sink, rx_sink) = mpsc::unbounded_channel();
-        let player = SpotifyPlayer::new(
-            PlayerConfig {
-                // 96kbps causes audio key errors, so enjoy the quality upgrade
-                bitrate: Bitrate::Bitrate160,
-                ..Default::default()
-            },
-            session.clone(),
-            mixer.get_soft_volume(),
-            {
-                let stream = stream.clone();
-                move || Box::new(StreamSink::new(stream, tx_sink))
-            },
-        );
+        let player = SpotifyPlayer::new(config, session.clone(), mixer.get_soft_volume(), {
+            let stream = stream.clone();
+            let filters = options.filters;
+            let fade = options.fade.clone();
+
+            move || Box::new(StreamSink::new(stream, tx_sink, filters, fade))
+        });
         let rx_player = player.get_player_event_channel();
 
-        let device_name = device_name.into();
+        let device_name = options.device_name;
         let mut tries = 0;
 
         let (spirc, spirc_task) = loop {
             match Spirc::new(
                 ConnectConfig {
                     name: device_name.clone(),
-                    initial_volume: Some((0.75f32 * u16::MAX as f32) as u16),
+                    initial_volume: Some(percent_to_volume(options.initial_volume)),
                     ..Default::default()
                 },
                 session.clone(),
@@ -153,7 +293,12 @@ impl Player {
         };
 
         // Keep auth data to reuse later for faster reconnections and less authentication requests to Spotify
-        let auth_data = session.auth_data();
+        let reusable_credentials = Credentials {
+            username: Some(session.username()),
+            auth_type: AuthenticationType::AUTHENTICATION_STORED_SPOTIFY_CREDENTIALS,
+            auth_data: session.auth_data(),
+        };
+        let device_id = session.device_id().to_string();
 
         let shutdown = Arc::new(AtomicBool::new(false));
         let (tx, rx) = mpsc::channel(16);
@@ -162,14 +307,24 @@ impl Player {
             spirc,
             track,
             stream,
+            fade: options.fade,
+            mixer,
+            bitrate: options.bitrate,
+            cache: options.cache,
+            preloaded: None,
+            loads: Vec::new(),
 
             playback_info: None,
 
+            shuffle: false,
+            repeat: RepeatMode::Off,
+
             events: event_tx.clone(),
 
             commands: rx,
             spotify_events: rx_player,
             sink_events: rx_sink,
+            track_errors: rx_track_errors,
 
             shutdown: shutdown.clone(),
         };
@@ -185,11 +340,20 @@ impl Player {
         });
         tokio::spawn(player.run());
 
-        Ok((PlayerHandle { commands: tx }, event_rx, auth_data))
+        Ok((
+            PlayerHandle::new(SpotifyBackend {
+                commands: tx,
+                device_id,
+            }),
+            event_rx,
+            reusable_credentials,
+        ))
     }
 
     async fn run(mut self) {
         loop {
+            let repeat_in = self.repeat_track_in();
+
             tokio::select! {
                 opt_command = self.commands.recv() => {
                     let command = match opt_command {
@@ -208,6 +372,16 @@ impl Player {
                     self.handle_sink_event(event).await;
                 }
 
+                Some(reason) = self.track_errors.recv() => {
+                    error!("Songbird track failed: {reason}");
+
+                    _ = self.events.send(PlayerEvent::PlaybackError(reason)).await;
+                }
+
+                _ = tokio::time::sleep(repeat_in.unwrap_or_default()), if repeat_in.is_some() => {
+                    self.repeat_track();
+                }
+
                 else => break,
             }
         }
@@ -224,9 +398,31 @@ impl Player {
             PlayerCommand::PreviousTrack => _ = self.spirc.prev(),
             PlayerCommand::Pause => _ = self.spirc.pause(),
             PlayerCommand::Play => _ = self.spirc.play(),
+            PlayerCommand::Seek(position_ms) => _ = self.spirc.set_position_ms(position_ms),
+            PlayerCommand::SetVolume(volume) => {
+                _ = self.spirc.set_volume(percent_to_volume(volume))
+            }
+            PlayerCommand::SetShuffle(shuffle) => {
+                _ = self.spirc.shuffle(shuffle);
+                self.update_modes(shuffle, self.repeat).await;
+            }
+            PlayerCommand::SetRepeat(repeat) => {
+                // Spirc can only repeat the context, repeating a single track is handled by us
+                _ = self.spirc.repeat(repeat != RepeatMode::Off);
+                self.update_modes(self.shuffle, repeat).await;
+            }
+            PlayerCommand::SetUpcoming(context, next_tracks) => {
+                if let Some(playback_info) = self.playback_info.as_mut() {
+                    playback_info.update_upcoming(context, next_tracks);
+
+                    _ = self.events.send(PlayerEvent::UpcomingChanged).await;
+                }
+            }
 
             PlayerCommand::GetPlaybackInfo(tx) => _ = tx.send(self.playback_info.clone()),
+            PlayerCommand::GetVolume(tx) => _ = tx.send(volume_to_percent(self.mixer.volume())),
             PlayerCommand::GetLyrics(tx) => self.get_lyrics(tx).await,
+            PlayerCommand::GetAudioStats(tx) => _ = tx.send(self.stream.stats()),
 
             PlayerCommand::Shutdown => self.commands.close(),
         };
@@ -241,6 +437,8 @@ impl Player {
                 if let Some(playback_info) = self.playback_info.as_mut() {
                     playback_info.update_playback(position_ms, true);
                 }
+
+                self.report_position(position_ms);
             }
             SpotifyPlayerEvent::Playing { position_ms, .. } => {
                 _ = self.events.send(PlayerEvent::Play).await;
@@ -248,6 +446,8 @@ impl Player {
                 if let Some(playback_info) = self.playback_info.as_mut() {
                     playback_info.update_playback(position_ms, true);
                 }
+
+                self.report_position(position_ms);
             }
             SpotifyPlayerEvent::Paused { position_ms, .. } => {
                 _ = self.events.send(PlayerEvent::Pause).await;
@@ -263,15 +463,31 @@ impl Player {
 
                 _ = self.events.send(PlayerEvent::Pause).await;
 
-                self.playback_info = None;
+                // Keep the playback info after a disconnect, so the session knows where to resume after reconnecting
+                if matches!(event, SpotifyPlayerEvent::Stopped { .. }) {
+                    self.playback_info = None;
+                }
+
+                self.fade.set_next_track_ready(false);
             }
             SpotifyPlayerEvent::TrackChanged { audio_item } => {
+                self.record_cache_load(&audio_item);
+                self.fade
+                    .set_track_normalised(has_loudness_metadata(&audio_item));
+
                 if let Some(playback_info) = self.playback_info.as_mut() {
                     playback_info.update_track(*audio_item);
                 } else {
-                    self.playback_info = Some(PlaybackInfo::new(*audio_item, 0, false));
+                    let mut playback_info = PlaybackInfo::new(*audio_item, 0, false);
+                    playback_info.update_modes(self.shuffle, self.repeat);
+                    playback_info.update_volume(volume_to_percent(self.mixer.volume()));
+
+                    self.playback_info = Some(playback_info);
                 }
 
+                self.fade.set_next_track_ready(false);
+                self.report_position(0);
+
                 _ = self
                     .events
                     .send(PlayerEvent::TrackChanged(Box::new(
@@ -279,15 +495,175 @@ impl Player {
                     )))
                     .await;
             }
+            SpotifyPlayerEvent::ShuffleChanged { shuffle } => {
+                self.update_modes(shuffle, self.repeat).await
+            }
+            SpotifyPlayerEvent::RepeatChanged { repeat } => {
+                let repeat = match (repeat, self.repeat) {
+                    (false, _) => RepeatMode::Off,
+                    (true, RepeatMode::Track) => RepeatMode::Track,
+                    (true, _) => RepeatMode::Context,
+                };
+
+                self.update_modes(self.shuffle, repeat).await;
+            }
+            SpotifyPlayerEvent::Unavailable { track_id, .. } => {
+                // Find out why in the background, the track itself won't be loaded anymore
+                let session = self.session.clone();
+                let events = self.events.clone();
+                let bitrate = self.bitrate;
+
+                let preloading = self.preloaded == Some(track_id);
+                if preloading {
+                    self.preloaded = None;
+                    self.fade.set_next_track_ready(false);
+                }
+
+                tokio::spawn(async move {
+                    let audio_item = AudioItem::get_file(&session, track_id).await.ok();
+
+                    // Audio key errors show up as unavailable tracks, and some bitrates are known to cause them
+                    if let (Some(audio_item), Some(fallback)) =
+                        (&audio_item, lower_bitrate(bitrate))
+                    {
+                        if is_audio_key_error(&session, audio_item, bitrate).await {
+                            _ = events
+                                .send(PlayerEvent::BitrateFallback {
+                                    track: track_id,
+                                    from: bitrate,
+                                    to: fallback,
+                                })
+                                .await;
+
+                            return;
+                        }
+                    }
+
+                    _ = events
+                        .send(PlayerEvent::TrackUnavailable {
+                            track: track_id,
+                            name: audio_item.map(|audio_item| audio_item.name),
+                            preloading,
+                        })
+                        .await;
+                });
+            }
+            SpotifyPlayerEvent::Loading { track_id, .. } => {
+                // Preloaded tracks started loading when preloading started
+                if self.preloaded.take() != Some(track_id) {
+                    self.loads.push((track_id, SystemTime::now()));
+                }
+            }
+            SpotifyPlayerEvent::Preloading { track_id } => {
+                // A repeated track is started over instead of crossfading into the next one
+                self.fade
+                    .set_next_track_ready(self.repeat != RepeatMode::Track);
+
+                self.preloaded = Some(track_id);
+                self.loads.push((track_id, SystemTime::now()));
+            }
+            SpotifyPlayerEvent::VolumeChanged { volume } => {
+                self.fade
+                    .set_volume(self.mixer.get_soft_volume().attenuation_factor());
+
+                if let Some(playback_info) = self.playback_info.as_mut() {
+                    playback_info.update_volume(volume_to_percent(volume));
+                }
+
+                _ = self
+                    .events
+                    .send(PlayerEvent::VolumeChanged(volume_to_percent(volume)))
+                    .await;
+            }
             _ => {}
         }
     }
 
+    async fn update_modes(&mut self, shuffle: bool, repeat: RepeatMode) {
+        if self.shuffle == shuffle && self.repeat == repeat {
+            return;
+        }
+
+        self.shuffle = shuffle;
+        self.repeat = repeat;
+
+        self.fade
+            .set_next_track_ready(repeat != RepeatMode::Track && self.preloaded.is_some());
+
+        if let Some(playback_info) = self.playback_info.as_mut() {
+            playback_info.update_modes(shuffle, repeat);
+        }
+
+        _ = self
+            .events
+            .send(PlayerEvent::ModeChanged { shuffle, repeat })
+            .await;
+    }
+
+    /// How long until the current track has to be started over, if it is being repeated
+    fn repeat_track_in(&self) -> Option<Duration> {
+        let playback_info = self.playback_info.as_ref()?;
+
+        if self.repeat != RepeatMode::Track || !playback_info.playing() {
+            return None;
+        }
+
+        let end = Duration::from_millis(playback_info.duration() as u64);
+        let position = Duration::from_millis(playback_info.current_position() as u64);
+
+        Some(
+            end.saturating_sub(REPEAT_TRACK_MARGIN)
+                .saturating_sub(position),
+        )
+    }
+
+    /// Start the current track over, before spirc gets the chance to move on to the next one
+    fn repeat_track(&mut self) {
+        _ = self.spirc.set_position_ms(0);
+
+        // Assume the seek went through until spirc reports it, so that it isn't repeated right away
+        if let Some(playback_info) = self.playback_info.as_mut() {
+            playback_info.update_playback(0, true);
+        }
+    }
+
+    /// Count whether a track that started playing was played from the cache
+    fn record_cache_load(&mut self, audio_item: &AudioItem) {
+        let loads = std::mem::take(&mut self.loads);
+
+        // Anything else that was loading has been skipped before it got to play
+        let Some((_, started)) = loads
+            .into_iter()
+            .find(|(track_id, _)| *track_id == audio_item.track_id)
+        else {
+            return;
+        };
+
+        if let Some(cache) = &self.cache {
+            cache.record_load(audio_item, started);
+        }
+    }
+
+    /// Let the sink know where we are in the current track, so it knows when to start crossfading
+    fn report_position(&self, position_ms: u32) {
+        if let Some(playback_info) = &self.playback_info {
+            self.fade.update_position(
+                playback_info.track_id(),
+                position_ms,
+                playback_info.duration(),
+            );
+        }
+    }
+
     async fn handle_sink_event(&self, event: SinkEvent) {
-        if let SinkEvent::Start = event {
-            if let Err(why) = self.track.play() {
-                error!("Failed to resume songbird track: {why}");
+        match event {
+            SinkEvent::Start => {
+                if let Err(why) = self.track.play() {
+                    error!("Failed to resume songbird track: {why}");
+                }
             }
+            SinkEvent::Stats(stats) => _ = self.events.send(PlayerEvent::AudioStats(stats)).await,
+            SinkEvent::Stop => {}
         }
     }
 
@@ -325,33 +701,91 @@ impl Drop for Player {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct PlayerHandle {
+/// Forwards errors of the songbird track to the player
+struct TrackErrorNotifier(mpsc::UnboundedSender<String>);
+
+#[async_trait]
+impl EventHandler for TrackErrorNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            for (state, _) in *tracks {
+                if let PlayMode::Errored(why) = &state.playing {
+                    _ = self.0.send(why.to_string());
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// The backend of a [`Player`], which passes commands on to the player task
+struct SpotifyBackend {
     commands: mpsc::Sender<PlayerCommand>,
+    device_id: String,
 }
 
-impl PlayerHandle {
-    pub fn is_valid(&self) -> bool {
+#[async_trait]
+impl PlaybackBackend for SpotifyBackend {
+    fn is_valid(&self) -> bool {
         !self.commands.is_closed()
     }
 
-    pub async fn next_track(&self) {
+    fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    async fn next_track(&self) {
         _ = self.commands.send(PlayerCommand::NextTrack).await;
     }
 
-    pub async fn previous_track(&self) {
+    async fn previous_track(&self) {
         _ = self.commands.send(PlayerCommand::PreviousTrack).await;
     }
 
-    pub async fn pause(&self) {
+    async fn pause(&self) {
         _ = self.commands.send(PlayerCommand::Pause).await;
     }
 
-    pub async fn play(&self) {
+    async fn play(&self) {
         _ = self.commands.send(PlayerCommand::Play).await;
     }
 
-    pub async fn playback_info(&self) -> Result<Option<PlaybackInfo>> {
+    async fn seek(&self, position_ms: u32) {
+        _ = self.commands.send(PlayerCommand::Seek(position_ms)).await;
+    }
+
+    async fn set_shuffle(&self, shuffle: bool) {
+        _ = self.commands.send(PlayerCommand::SetShuffle(shuffle)).await;
+    }
+
+    async fn set_repeat(&self, repeat: RepeatMode) {
+        _ = self.commands.send(PlayerCommand::SetRepeat(repeat)).await;
+    }
+
+    async fn set_upcoming(
+        &self,
+        context: Option<PlaybackContext>,
+        next_tracks: Vec<UpcomingTrack>,
+    ) {
+        _ = self
+            .commands
+            .send(PlayerCommand::SetUpcoming(context, next_tracks))
+            .await;
+    }
+
+    async fn set_volume(&self, volume: u8) {
+        _ = self.commands.send(PlayerCommand::SetVolume(volume)).await;
+    }
+
+    async fn volume(&self) -> Result<u8> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(PlayerCommand::GetVolume(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    async fn playback_info(&self) -> Result<Option<PlaybackInfo>> {
         let (tx, rx) = oneshot::channel();
         self.commands
             .send(PlayerCommand::GetPlaybackInfo(tx))
@@ -360,14 +794,181 @@ impl PlayerHandle {
         Ok(rx.await?)
     }
 
-    pub async fn get_lyrics(&self) -> Result<Option<Lyrics>> {
+    async fn lyrics(&self) -> Result<Option<Lyrics>> {
         let (tx, rx) = oneshot::channel();
         self.commands.send(PlayerCommand::GetLyrics(tx)).await?;
 
         Ok(rx.await?)
     }
 
-    pub async fn shutdown(&self) {
+    async fn audio_stats(&self) -> Result<StreamStats> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(PlayerCommand::GetAudioStats(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    async fn shutdown(&self) {
         _ = self.commands.send(PlayerCommand::Shutdown).await;
     }
 }
+
+#[derive(Clone)]
+pub struct PlayerHandle {
+    backend: Arc<dyn PlaybackBackend>,
+}
+
+impl PlayerHandle {
+    pub fn new(backend: impl PlaybackBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.backend.is_valid()
+    }
+
+    /// The ID of the Spotify Connect device of this player, as used by the Spotify Web API
+    pub fn device_id(&self) -> &str {
+        self.backend.device_id()
+    }
+
+    pub async fn next_track(&self) {
+        self.backend.next_track().await
+    }
+
+    pub async fn previous_track(&self) {
+        self.backend.previous_track().await
+    }
+
+    pub async fn pause(&self) {
+        self.backend.pause().await
+    }
+
+    pub async fn play(&self) {
+        self.backend.play().await
+    }
+
+    /// Jump to a position (in milliseconds) in the current track
+    pub async fn seek(&self, position_ms: u32) {
+        self.backend.seek(position_ms).await
+    }
+
+    pub async fn set_shuffle(&self, shuffle: bool) {
+        self.backend.set_shuffle(shuffle).await
+    }
+
+    pub async fn set_repeat(&self, repeat: RepeatMode) {
+        self.backend.set_repeat(repeat).await
+    }
+
+    /// Provide the context and upcoming tracks of the current playback, which the player can't find out by itself
+    pub async fn set_upcoming(
+        &self,
+        context: Option<PlaybackContext>,
+        next_tracks: Vec<UpcomingTrack>,
+    ) {
+        self.backend.set_upcoming(context, next_tracks).await
+    }
+
+    /// Change the volume of the player, in percent
+    pub async fn set_volume(&self, volume: u8) {
+        self.backend.set_volume(volume.min(100)).await
+    }
+
+    /// Retrieve the current volume of the player, in percent
+    pub async fn volume(&self) -> Result<u8> {
+        self.backend.volume().await
+    }
+
+    pub async fn playback_info(&self) -> Result<Option<PlaybackInfo>> {
+        self.backend.playback_info().await
+    }
+
+    pub async fn get_lyrics(&self) -> Result<Option<Lyrics>> {
+        self.backend.lyrics().await
+    }
+
+    /// Retrieve the telemetry of the audio stream that feeds the voice call
+    pub async fn audio_stats(&self) -> Result<StreamStats> {
+        self.backend.audio_stats().await
+    }
+
+    pub async fn shutdown(&self) {
+        self.backend.shutdown().await
+    }
+}
+
+impl std::fmt::Debug for PlayerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlayerHandle")
+            .field("device_id", &self.device_id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The lower bitrate to try when the audio key of a track can't be retrieved, or `None` if there is none.
+///
+/// Every account can stream at 160 kbps, so the bitrate is never lowered any further than that.
+fn lower_bitrate(bitrate: Bitrate) -> Option<Bitrate> {
+    match bitrate {
+        Bitrate::Bitrate320 => Some(Bitrate::Bitrate160),
+        Bitrate::Bitrate160 | Bitrate::Bitrate96 => None,
+    }
+}
+
+/// Check whether librespot can normalise a track, which it only does with the loudness metadata in
+/// the header of Spotify's Ogg Vorbis files
+fn has_loudness_metadata(audio_item: &AudioItem) -> bool {
+    audio_item.files.keys().any(|format| {
+        matches!(
+            format,
+            AudioFileFormat::OGG_VORBIS_96
+                | AudioFileFormat::OGG_VORBIS_160
+                | AudioFileFormat::OGG_VORBIS_320
+        )
+    })
+}
+
+/// Check whether a track failed to load because Spotify refused the audio key of the file at
+/// the given bitrate, rather than because the track isn't available at all
+async fn is_audio_key_error(
+    session: &SpotifySession,
+    audio_item: &AudioItem,
+    bitrate: Bitrate,
+) -> bool {
+    if audio_item.availability.is_err() {
+        return false;
+    }
+
+    let format = match bitrate {
+        Bitrate::Bitrate96 => AudioFileFormat::OGG_VORBIS_96,
+        Bitrate::Bitrate160 => AudioFileFormat::OGG_VORBIS_160,
+        Bitrate::Bitrate320 => AudioFileFormat::OGG_VORBIS_320,
+    };
+
+    let Some(file_id) = audio_item.files.get(&format) else {
+        return false;
+    };
+
+    match session
+        .audio_key()
+        .request(audio_item.track_id, *file_id)
+        .await
+    {
+        Ok(_) => false,
+        Err(why) => {
+            trace!("Failed to retrieve audio key: {why}");
+            true
+        }
+    }
+}
+
+fn percent_to_volume(percent: u8) -> u16 {
+    (percent.min(100) as u32 * u16::MAX as u32 / 100) as u16
+}
+
+fn volume_to_percent(volume: u16) -> u8 {
+    ((volume as u32 * 100 + u16::MAX as u32 / 2) / u16::MAX as u32) as u8
+}
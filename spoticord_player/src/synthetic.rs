@@ -0,0 +1,461 @@
+//! An offline playback backend that doesn't talk to Spotify at all.
+//!
+//! It plays a tone into the audio stream, emits a scripted list of player events and serves canned lyrics,
+//! which allows a session to be driven end to end without a Spotify account or network access.
+
+use std::{
+    f32::consts::TAU,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use librespot::metadata::Lyrics;
+use log::{error, trace};
+use songbird::{input::RawAdapter, tracks::TrackHandle, Call};
+use spoticord_audio::{
+    resampler::OUTPUT_SAMPLE_RATE,
+    stream::{Stream, StreamStats},
+};
+use tokio::sync::{mpsc, Mutex, Notify};
+
+use crate::{
+    backend::PlaybackBackend,
+    info::{PlaybackContext, PlaybackInfo, RepeatMode, UpcomingTrack},
+    PlayerEvent, PlayerHandle,
+};
+
+/// The device ID that the synthetic backend reports
+pub const SYNTHETIC_DEVICE_ID: &str = "synthetic";
+
+/// The length of a single chunk of tone that is written into the stream
+const CHUNK_DURATION: Duration = Duration::from_millis(20);
+
+/// An event that the synthetic backend emits after waiting for `delay` since the previous scripted event
+#[derive(Debug, Clone)]
+pub struct ScriptedEvent {
+    pub delay: Duration,
+    pub event: PlayerEvent,
+}
+
+impl ScriptedEvent {
+    pub fn new(delay: Duration, event: PlayerEvent) -> Self {
+        Self { delay, event }
+    }
+}
+
+/// Options that control what a [`SyntheticBackend`] plays and reports
+#[derive(Debug, Clone)]
+pub struct SyntheticOptions {
+    /// The frequency (in Hz) of the tone that is played
+    pub frequency: f32,
+    /// The events that are emitted, in order.
+    ///
+    /// Skipping a track emits everything up to and including the next [`PlayerEvent::TrackChanged`] right away,
+    /// and stops playback if the script doesn't change tracks anymore.
+    pub script: Vec<ScriptedEvent>,
+    /// The track that is current from the start, like after playback has been transferred to the backend
+    pub playback_info: Option<PlaybackInfo>,
+    /// The lyrics that are returned for every track
+    pub lyrics: Option<Lyrics>,
+    /// The volume the backend starts at, in percent
+    pub initial_volume: u8,
+}
+
+impl Default for SyntheticOptions {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            script: Vec::new(),
+            playback_info: None,
+            lyrics: None,
+            initial_volume: 75,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    playback_info: Option<PlaybackInfo>,
+    volume: u8,
+    shuffle: bool,
+    repeat: RepeatMode,
+}
+
+impl State {
+    /// Update the state to reflect an event that is about to be emitted
+    fn apply(&mut self, event: &mut PlayerEvent) {
+        match event {
+            PlayerEvent::TrackChanged(playback_info) => {
+                playback_info.update_modes(self.shuffle, self.repeat);
+                playback_info.update_volume(self.volume);
+
+                self.playback_info = Some(*playback_info.clone());
+            }
+            PlayerEvent::Play | PlayerEvent::Pause => {
+                if let Some(playback_info) = self.playback_info.as_mut() {
+                    let position = playback_info.current_position();
+                    playback_info.update_playback(position, matches!(event, PlayerEvent::Play));
+                }
+            }
+            PlayerEvent::Stopped => self.playback_info = None,
+            PlayerEvent::VolumeChanged(volume) => {
+                self.volume = *volume;
+
+                if let Some(playback_info) = self.playback_info.as_mut() {
+                    playback_info.update_volume(*volume);
+                }
+            }
+            PlayerEvent::ModeChanged { shuffle, repeat } => {
+                self.shuffle = *shuffle;
+                self.repeat = *repeat;
+
+                if let Some(playback_info) = self.playback_info.as_mut() {
+                    playback_info.update_modes(*shuffle, *repeat);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn playing(&self) -> bool {
+        self.playback_info
+            .as_ref()
+            .is_some_and(PlaybackInfo::playing)
+    }
+}
+
+/// A [`PlaybackBackend`] that plays a tone and a scripted list of events instead of Spotify
+pub struct SyntheticBackend {
+    state: Arc<StdMutex<State>>,
+    /// Taken on shutdown, so the event channel closes once the script has stopped as well
+    events: StdMutex<Option<mpsc::Sender<PlayerEvent>>>,
+    /// Asks the script to move on to its next track, which fails once the script has ended
+    skip: mpsc::UnboundedSender<()>,
+    stream: Stream,
+    track: Option<TrackHandle>,
+    lyrics: Option<Lyrics>,
+
+    /// A shared boolean that reflects whether this backend has shut down
+    shutdown: Arc<AtomicBool>,
+    /// Wakes up the script when the backend shuts down
+    stop_script: Arc<Notify>,
+}
+
+impl SyntheticBackend {
+    /// Create a synthetic player, which plays into `call` if one is given
+    pub async fn create(
+        call: Option<Arc<Mutex<Call>>>,
+        options: SyntheticOptions,
+    ) -> (PlayerHandle, mpsc::Receiver<PlayerEvent>) {
+        let (event_tx, event_rx) = mpsc::channel(16);
+        let (skip_tx, mut skip_rx) = mpsc::unbounded_channel();
+        let stream = Stream::new();
+
+        let track = match call {
+            Some(call) => {
+                let adapter = RawAdapter::new(stream.clone(), OUTPUT_SAMPLE_RATE, 2);
+                let track = call.lock().await.play_only_input(adapter.into());
+                _ = track.pause();
+
+                Some(track)
+            }
+            None => None,
+        };
+
+        let state = Arc::new(StdMutex::new(State {
+            volume: options.initial_volume.min(100),
+            ..Default::default()
+        }));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let stop_script = Arc::new(Notify::new());
+
+        if let Some(playback_info) = options.playback_info {
+            let mut event = PlayerEvent::TrackChanged(Box::new(playback_info));
+            let playing = {
+                let mut state = state.lock().expect("state mutex poisoned");
+                state.apply(&mut event);
+                state.playing()
+            };
+
+            set_track_playing(track.as_ref(), playing);
+
+            _ = event_tx.send(event).await;
+        }
+
+        let backend = Self {
+            state: state.clone(),
+            events: StdMutex::new(Some(event_tx.clone())),
+            skip: skip_tx,
+            stream: stream.clone(),
+            track,
+            lyrics: options.lyrics,
+
+            shutdown: shutdown.clone(),
+            stop_script: stop_script.clone(),
+        };
+
+        std::thread::spawn({
+            let state = state.clone();
+            let frequency = options.frequency;
+
+            move || play_tone(stream, state, shutdown, frequency)
+        });
+
+        tokio::spawn({
+            let track = backend.track.clone();
+
+            async move {
+                let mut skipping = false;
+
+                for ScriptedEvent { delay, mut event } in options.script {
+                    if !skipping {
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => {},
+                            Some(()) = skip_rx.recv() => skipping = true,
+                            _ = stop_script.notified() => break,
+                        }
+                    }
+
+                    if matches!(event, PlayerEvent::TrackChanged(_)) {
+                        skipping = false;
+                    }
+
+                    let playing = {
+                        let mut state = state.lock().expect("state mutex poisoned");
+                        state.apply(&mut event);
+                        state.playing()
+                    };
+
+                    set_track_playing(track.as_ref(), playing);
+
+                    trace!("Emitting scripted event: {event:?}");
+                    if event_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+
+                trace!("End of synthetic script");
+
+                // There was no track left to skip to, including for skips that were still on their way
+                skip_rx.close();
+                if skipping || skip_rx.try_recv().is_ok() {
+                    let mut event = PlayerEvent::Stopped;
+                    state
+                        .lock()
+                        .expect("state mutex poisoned")
+                        .apply(&mut event);
+                    set_track_playing(track.as_ref(), false);
+
+                    _ = event_tx.send(event).await;
+                }
+            }
+        });
+
+        (PlayerHandle::new(backend), event_rx)
+    }
+
+    /// Apply an event to the state and emit it, like the real player does after Spotify acknowledges a command
+    async fn emit(&self, mut event: PlayerEvent) {
+        let playing = {
+            let mut state = self.state();
+            state.apply(&mut event);
+            state.playing()
+        };
+
+        set_track_playing(self.track.as_ref(), playing);
+
+        if let Some(events) = self.events() {
+            _ = events.send(event).await;
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("state mutex poisoned")
+    }
+
+    fn events(&self) -> Option<mpsc::Sender<PlayerEvent>> {
+        self.events.lock().expect("events mutex poisoned").clone()
+    }
+}
+
+#[async_trait]
+impl PlaybackBackend for SyntheticBackend {
+    fn is_valid(&self) -> bool {
+        !self.shutdown.load(Ordering::SeqCst)
+    }
+
+    fn device_id(&self) -> &str {
+        SYNTHETIC_DEVICE_ID
+    }
+
+    async fn next_track(&self) {
+        // Once the script has ended, there is nothing left to play
+        if self.skip.send(()).is_err() {
+            self.emit(PlayerEvent::Stopped).await;
+        }
+    }
+
+    async fn previous_track(&self) {
+        self.seek(0).await;
+    }
+
+    async fn pause(&self) {
+        self.emit(PlayerEvent::Pause).await;
+    }
+
+    async fn play(&self) {
+        self.emit(PlayerEvent::Play).await;
+    }
+
+    async fn seek(&self, position_ms: u32) {
+        let mut state = self.state();
+        if let Some(playback_info) = state.playback_info.as_mut() {
+            let position = position_ms.min(playback_info.duration());
+            let playing = playback_info.playing();
+            playback_info.update_playback(position, playing);
+        }
+
+        let playing = state.playing();
+        drop(state);
+
+        set_track_playing(self.track.as_ref(), playing);
+    }
+
+    async fn set_shuffle(&self, shuffle: bool) {
+        let repeat = self.state().repeat;
+
+        self.emit(PlayerEvent::ModeChanged { shuffle, repeat })
+            .await;
+    }
+
+    async fn set_repeat(&self, repeat: RepeatMode) {
+        let shuffle = self.state().shuffle;
+
+        self.emit(PlayerEvent::ModeChanged { shuffle, repeat })
+            .await;
+    }
+
+    async fn set_upcoming(
+        &self,
+        context: Option<PlaybackContext>,
+        next_tracks: Vec<UpcomingTrack>,
+    ) {
+        let updated = match self.state().playback_info.as_mut() {
+            Some(playback_info) => {
+                playback_info.update_upcoming(context, next_tracks);
+                true
+            }
+            None => false,
+        };
+
+        if let (true, Some(events)) = (updated, self.events()) {
+            _ = events.send(PlayerEvent::UpcomingChanged).await;
+        }
+    }
+
+    async fn set_volume(&self, volume: u8) {
+        self.emit(PlayerEvent::VolumeChanged(volume)).await;
+    }
+
+    async fn volume(&self) -> Result<u8> {
+        Ok(self.state().volume)
+    }
+
+    async fn playback_info(&self) -> Result<Option<PlaybackInfo>> {
+        Ok(self.state().playback_info.clone())
+    }
+
+    async fn lyrics(&self) -> Result<Option<Lyrics>> {
+        if self.state().playback_info.is_none() {
+            return Ok(None);
+        }
+
+        Ok(self.lyrics.clone())
+    }
+
+    async fn audio_stats(&self) -> Result<StreamStats> {
+        Ok(self.stream.stats())
+    }
+
+    async fn shutdown(&self) {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        self.events.lock().expect("events mutex poisoned").take();
+        self.stop_script.notify_one();
+
+        if let Some(track) = &self.track {
+            _ = track.stop();
+        }
+
+        let mut stream = self.stream.clone();
+        _ = stream.flush();
+    }
+}
+
+fn set_track_playing(track: Option<&TrackHandle>, playing: bool) {
+    let Some(track) = track else {
+        return;
+    };
+
+    let result = if playing { track.play() } else { track.pause() };
+    if let Err(why) = result {
+        error!("Failed to update synthetic songbird track: {why}");
+    }
+}
+
+/// Write a stereo sine wave into the stream whilst playing, keeping at most half of the buffer filled
+fn play_tone(
+    mut stream: Stream,
+    state: Arc<StdMutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    frequency: f32,
+) {
+    let frames = (OUTPUT_SAMPLE_RATE as u128 * CHUNK_DURATION.as_millis() / 1000) as usize;
+    let step = TAU * frequency / OUTPUT_SAMPLE_RATE as f32;
+    let mut phase = 0f32;
+    let mut chunk = Vec::with_capacity(frames * 2 * std::mem::size_of::<f32>());
+
+    stream.set_active(true);
+
+    while !shutdown.load(Ordering::SeqCst) {
+        let (playing, volume) = {
+            let state = state.lock().expect("state mutex poisoned");
+            (state.playing(), state.volume)
+        };
+
+        if !playing || stream.fill_level() + chunk.capacity() > stream.capacity() / 2 {
+            std::thread::sleep(CHUNK_DURATION / 4);
+            continue;
+        }
+
+        let amplitude = 0.25 * volume as f32 / 100.0;
+        chunk.clear();
+
+        for _ in 0..frames {
+            let sample = (amplitude * phase.sin()).to_ne_bytes();
+
+            // Same sample for both channels
+            chunk.extend_from_slice(&sample);
+            chunk.extend_from_slice(&sample);
+
+            phase = (phase + step) % TAU;
+        }
+
+        if let Err(why) = stream.write_all(&chunk) {
+            error!("Failed to write synthetic tone: {why}");
+            break;
+        }
+    }
+
+    stream.set_active(false);
+
+    trace!("End of synthetic tone");
+}
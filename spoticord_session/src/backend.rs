@@ -0,0 +1,301 @@
+use crate::{
+    error::{Error, Result},
+    is_bad_credentials, save_reusable_credentials, web_api, NewPlayer, ResumePoint,
+};
+use librespot::{discovery::Credentials, protocol::authentication::AuthenticationType};
+use log::{error, warn};
+use rspotify::prelude::OAuthClient;
+use serenity::async_trait;
+use songbird::Call;
+use spoticord_player::{
+    info::PlaybackInfo,
+    synthetic::{ScriptedEvent, SyntheticBackend, SyntheticOptions},
+    Player, PlayerEvent, PlayerHandle, PlayerOptions,
+};
+use spoticord_storage::{LoginMethod, Storage};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::Mutex;
+
+/// Creates the players of sessions, and moves playback to them
+///
+/// Sessions can be driven without Spotify by handing the [`SessionManager`](crate::manager::SessionManager)
+/// a different implementation.
+#[async_trait]
+pub(crate) trait Backend: Send + Sync {
+    /// Whether sessions need a linked Spotify account to play
+    fn uses_accounts(&self) -> bool;
+
+    /// Create the player of a session that joined a call
+    async fn login(
+        &self,
+        storage: &Storage,
+        account: &str,
+        call: &Arc<Mutex<Call>>,
+        options: PlayerOptions,
+    ) -> Result<NewPlayer>;
+
+    /// Create a new player after the previous one lost its connection, preferring the credentials it logged in with
+    async fn reconnect(
+        &self,
+        storage: &Storage,
+        account: &str,
+        credentials: Option<Credentials>,
+        call: &Arc<Mutex<Call>>,
+        options: PlayerOptions,
+    ) -> Result<NewPlayer>;
+
+    /// Move playback to a player, at the position it was at before
+    async fn resume(
+        &self,
+        storage: &Storage,
+        account: &str,
+        player: &PlayerHandle,
+        resume: &ResumePoint,
+    ) -> anyhow::Result<()>;
+}
+
+/// Plays from the linked Spotify accounts through librespot
+pub(crate) struct Spotify;
+
+#[async_trait]
+impl Backend for Spotify {
+    fn uses_accounts(&self) -> bool {
+        true
+    }
+
+    /// Log in to a Spotify account, preferring the stored reusable credentials over a new access token
+    async fn login(
+        &self,
+        storage: &Storage,
+        account: &str,
+        call: &Arc<Mutex<Call>>,
+        options: PlayerOptions,
+    ) -> Result<NewPlayer> {
+        match storage.get_reusable_credentials(account).await {
+            Ok(Some(reusable)) => {
+                let credentials = Credentials {
+                    username: Some(reusable.username),
+                    auth_type: AuthenticationType::AUTHENTICATION_STORED_SPOTIFY_CREDENTIALS,
+                    auth_data: reusable.auth_data,
+                };
+
+                match Player::create(credentials, call.clone(), options.clone()).await {
+                    Ok((player, events, credentials)) => {
+                        save_reusable_credentials(
+                            storage,
+                            account,
+                            &credentials,
+                            LoginMethod::ReusableCredentials,
+                        )
+                        .await;
+
+                        return Ok((player, events, Some(credentials)));
+                    }
+                    Err(why) if is_bad_credentials(&why) => {
+                        warn!(
+                            "Spotify rejected the stored credentials, falling back to an access token"
+                        );
+
+                        if let Err(why) = storage.clear_reusable_credentials(account).await {
+                            error!("Failed to clear stored Spotify credentials: {why}");
+                        }
+                    }
+                    Err(why) => return Err(why.into()),
+                }
+            }
+            Ok(None) => {}
+            Err(why) => {
+                warn!(
+                    "Failed to load stored Spotify credentials, falling back to an access token: {why}"
+                )
+            }
+        }
+
+        let access_token = storage
+            .get_spotify_token(account)
+            .await?
+            .ok_or(Error::NoAccountLinked)?;
+
+        let (player, events, credentials) = Player::create(
+            Credentials::with_access_token(access_token),
+            call.clone(),
+            options,
+        )
+        .await?;
+
+        save_reusable_credentials(storage, account, &credentials, LoginMethod::AccessToken).await;
+
+        Ok((player, events, Some(credentials)))
+    }
+
+    async fn reconnect(
+        &self,
+        storage: &Storage,
+        account: &str,
+        credentials: Option<Credentials>,
+        call: &Arc<Mutex<Call>>,
+        options: PlayerOptions,
+    ) -> Result<NewPlayer> {
+        let Some(credentials) = credentials else {
+            return self.login(storage, account, call, options).await;
+        };
+
+        let (player, events, credentials) =
+            Player::create(credentials, call.clone(), options).await?;
+
+        Ok((player, events, Some(credentials)))
+    }
+
+    /// Transfer playback to the player through the Spotify Web API
+    async fn resume(
+        &self,
+        storage: &Storage,
+        account: &str,
+        player: &PlayerHandle,
+        resume: &ResumePoint,
+    ) -> anyhow::Result<()> {
+        let spotify = web_api::client(storage, account).await?;
+        let device_id = player.device_id();
+
+        let Some(track) = &resume.track else {
+            spotify
+                .transfer_playback(device_id, Some(resume.playing))
+                .await?;
+            spotify
+                .seek_track(
+                    chrono::Duration::milliseconds(resume.position as i64),
+                    Some(device_id),
+                )
+                .await?;
+
+            return Ok(());
+        };
+
+        web_api::restore(
+            &spotify,
+            device_id,
+            resume.context.as_deref(),
+            track,
+            resume.position,
+        )
+        .await?;
+
+        if !resume.playing {
+            spotify.pause_playback(Some(device_id)).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Plays a tone and scripted events through the offline [`SyntheticBackend`], which needs no credentials
+pub(crate) struct Synthetic {
+    options: SyntheticOptions,
+    /// The amount of scripted connection resets that players have been rebuilt after since the last login
+    resets: AtomicUsize,
+}
+
+impl Synthetic {
+    pub fn new(options: SyntheticOptions) -> Self {
+        Self {
+            options,
+            resets: AtomicUsize::new(0),
+        }
+    }
+
+    /// The rest of the script after its `resets`-th connection reset, starting out with the track that was current
+    fn script_after(&self, resets: usize) -> SyntheticOptions {
+        let mut script = self.options.script.iter();
+        let mut playback_info: Option<PlaybackInfo> = None;
+        let mut seen = 0;
+
+        for ScriptedEvent { event, .. } in script.by_ref() {
+            match event {
+                PlayerEvent::TrackChanged(info) => playback_info = Some(*info.clone()),
+                PlayerEvent::Stopped => playback_info = None,
+                PlayerEvent::ConnectionReset => {
+                    seen += 1;
+
+                    if seen == resets {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        SyntheticOptions {
+            script: script.cloned().collect(),
+            playback_info,
+            ..self.options.clone()
+        }
+    }
+}
+
+#[async_trait]
+impl Backend for Synthetic {
+    fn uses_accounts(&self) -> bool {
+        false
+    }
+
+    async fn login(
+        &self,
+        _storage: &Storage,
+        _account: &str,
+        call: &Arc<Mutex<Call>>,
+        options: PlayerOptions,
+    ) -> Result<NewPlayer> {
+        self.resets.store(0, Ordering::SeqCst);
+
+        let synthetic = SyntheticOptions {
+            initial_volume: options.initial_volume,
+            ..self.options.clone()
+        };
+        let (player, events) = SyntheticBackend::create(Some(call.clone()), synthetic).await;
+
+        Ok((player, events, None))
+    }
+
+    /// Continue the script after the connection reset that caused the reconnect
+    async fn reconnect(
+        &self,
+        _storage: &Storage,
+        _account: &str,
+        _credentials: Option<Credentials>,
+        call: &Arc<Mutex<Call>>,
+        options: PlayerOptions,
+    ) -> Result<NewPlayer> {
+        let resets = self.resets.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let synthetic = SyntheticOptions {
+            initial_volume: options.initial_volume,
+            ..self.script_after(resets)
+        };
+        let (player, events) = SyntheticBackend::create(Some(call.clone()), synthetic).await;
+
+        Ok((player, events, None))
+    }
+
+    /// The synthetic backend isn't known to Spotify and plays whatever it has been scripted to,
+    /// so it is only told whether to play and the position
+    async fn resume(
+        &self,
+        _storage: &Storage,
+        _account: &str,
+        player: &PlayerHandle,
+        resume: &ResumePoint,
+    ) -> anyhow::Result<()> {
+        if resume.playing {
+            player.play().await;
+        } else {
+            player.pause().await;
+        }
+
+        player.seek(resume.position).await;
+
+        Ok(())
+    }
+}
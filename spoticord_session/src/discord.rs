@@ -0,0 +1,57 @@
+use crate::error::{Error, Result};
+use serenity::{
+    all::{ChannelId, CreateMessage, GuildId, Http},
+    async_trait,
+};
+use songbird::{Call, Songbird};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// The parts of Discord that a session talks to on its own, outside of the interactions it responds to
+///
+/// Sessions can be driven without a connection to Discord by handing the [`SessionManager`](crate::manager::SessionManager)
+/// a different implementation.
+#[async_trait]
+pub trait Discord: Send + Sync {
+    /// Make sure a channel is a text channel of a guild, so that the session can post in it
+    async fn resolve_text_channel(&self, channel: ChannelId) -> Result<ChannelId>;
+
+    async fn send_message(&self, channel: ChannelId, message: CreateMessage) -> Result<()>;
+
+    /// Join a voice channel, or move to it if the bot is already in a call in that guild
+    async fn join(&self, guild: GuildId, channel: ChannelId) -> Result<Arc<Mutex<Call>>>;
+}
+
+/// Talks to Discord through its HTTP API, and joins calls through songbird
+pub struct Gateway {
+    http: Arc<Http>,
+    songbird: Arc<Songbird>,
+}
+
+impl Gateway {
+    pub fn new(http: Arc<Http>, songbird: Arc<Songbird>) -> Self {
+        Self { http, songbird }
+    }
+}
+
+#[async_trait]
+impl Discord for Gateway {
+    async fn resolve_text_channel(&self, channel: ChannelId) -> Result<ChannelId> {
+        channel
+            .to_channel(self.http.as_ref())
+            .await?
+            .guild()
+            .map(|channel| channel.id)
+            .ok_or(Error::InvalidChannel)
+    }
+
+    async fn send_message(&self, channel: ChannelId, message: CreateMessage) -> Result<()> {
+        channel.send_message(self.http.as_ref(), message).await?;
+
+        Ok(())
+    }
+
+    async fn join(&self, guild: GuildId, channel: ChannelId) -> Result<Arc<Mutex<Call>>> {
+        Ok(self.songbird.join(guild, channel).await?)
+    }
+}
@@ -10,6 +10,14 @@ pub enum Error {
     #[error("Authentication failed")]
     AuthenticationFailed,
 
+    /// The bot doesn't have any Spotify account linked
+    #[error("No Spotify account linked to bot")]
+    NoAccountLinked,
+
+    /// Every linked Spotify account is already in use by another session
+    #[error("All Spotify accounts are busy")]
+    AllAccountsBusy,
+
     /// Cannot perform this action on an active session
     #[error("Cannot perform this action on an active session")]
     AlreadyActive,
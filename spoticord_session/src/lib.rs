@@ -1,61 +1,308 @@
+mod backend;
+pub mod discord;
 pub mod error;
 pub mod lyrics_embed;
 pub mod manager;
 pub mod playback_embed;
+pub mod queue;
+mod web_api;
 
+use discord::Discord;
 use error::Error;
 use error::Result;
 use librespot::{
-    core::connection,
+    core::{connection, SpotifyId},
     discovery::Credentials,
+    playback::config::{Bitrate, NormalisationType},
     protocol::keyexchange::ErrorCode,
 };
-use log::{debug, error, trace};
+use log::{debug, error, info, trace, warn};
 use lyrics_embed::LyricsEmbed;
 use manager::{SessionManager, SessionQuery};
 use playback_embed::{PlaybackEmbed, PlaybackEmbedHandle};
+use queue::{GuildQueue, QueueEntry, QueueLimit, RemoveError};
 use serenity::{
-    all::{
-        ChannelId, CommandInteraction, CreateEmbed, CreateMessage, GuildChannel, GuildId, UserId,
-    },
+    all::{ChannelId, CommandInteraction, CreateEmbed, CreateMessage, GuildId, UserId},
     async_trait,
 };
 use songbird::{model::payload::ClientDisconnect, Call, CoreEvent, Event, EventContext};
-use spoticord_player::{Player, PlayerEvent, PlayerHandle};
-use spoticord_utils::discord::Colors;
-use std::{ops::ControlFlow, sync::Arc, time::Duration};
+use spoticord_audio::{
+    equalizer::{self, EqualizerPreset},
+    fade::FadeControl,
+    filter::FilterChain,
+    loudness::{self, LoudnessLimiter},
+};
+use spoticord_player::{
+    cache::AudioCache,
+    info::{PlaybackInfo, UpcomingTrack},
+    PlayerEvent, PlayerHandle, PlayerOptions,
+};
+use spoticord_storage::{
+    Equalizer, GuildSettings, LoginMethod, Normalization, ReusableCredentials, SavedSession,
+    Storage,
+};
+use spoticord_utils::discord::{escape, Colors};
+use std::{
+    ops::ControlFlow,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::{
     sync::{mpsc, oneshot, Mutex},
     task::JoinHandle,
 };
 
+/// The name of the Spotify Connect device the bot shows up as
+const DEVICE_NAME: &str = "Spoticord Bot";
+
+/// How many times the player is rebuilt after losing connection to Spotify before giving up
+const RECONNECT_ATTEMPTS: u32 = 5;
+
+/// The delay before the first reconnection attempt, which doubles after every failed attempt
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// How long Spotify gets to move on by itself after a track turned out to be unavailable
+const SKIP_UNAVAILABLE_DELAY: Duration = Duration::from_secs(2);
+
+/// How many times the player is rebuilt after playback failed within [`PLAYBACK_ERROR_WINDOW`], before giving up
+const PLAYBACK_ERROR_RECONNECTS: usize = 3;
+
+/// How long a playback error counts towards [`PLAYBACK_ERROR_RECONNECTS`]
+const PLAYBACK_ERROR_WINDOW: Duration = Duration::from_secs(300);
+
+/// How often the playback position of an active session is saved, besides whenever the playback changes
+const SAVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Build the options for a new player from the settings of the guild it plays in
+fn player_options(
+    settings: &GuildSettings,
+    filters: &FilterChain,
+    fade: &FadeControl,
+    cache: Option<AudioCache>,
+) -> PlayerOptions {
+    set_equalizer(filters, settings.equalizer);
+    set_loudness_limiter(
+        filters,
+        fade,
+        settings.normalization,
+        settings.target_loudness,
+    );
+
+    PlayerOptions {
+        device_name: DEVICE_NAME.into(),
+        filters: filters.clone(),
+        fade: fade.clone(),
+
+        normalisation: match settings.normalization {
+            Normalization::Off => None,
+            Normalization::Track => Some(NormalisationType::Track),
+            Normalization::Album => Some(NormalisationType::Album),
+        },
+        target_loudness: settings.target_loudness,
+
+        initial_volume: settings.default_volume.unwrap_or(settings.volume),
+
+        bitrate: bitrate_from_kbps(settings.bitrate.unwrap_or(spoticord_config::bitrate())),
+
+        cache,
+    }
+}
+
+/// How long the bot stays in a call without playing before it disconnects
+fn idle_timeout(settings: &GuildSettings) -> Duration {
+    Duration::from_secs(
+        settings
+            .idle_timeout
+            .unwrap_or(spoticord_config::DISCONNECT_TIME),
+    )
+}
+
+fn bitrate_from_kbps(kbps: u16) -> Bitrate {
+    match kbps {
+        96 => Bitrate::Bitrate96,
+        320 => Bitrate::Bitrate320,
+        _ => Bitrate::Bitrate160,
+    }
+}
+
+fn bitrate_to_kbps(bitrate: Bitrate) -> u16 {
+    match bitrate {
+        Bitrate::Bitrate96 => 96,
+        Bitrate::Bitrate160 => 160,
+        Bitrate::Bitrate320 => 320,
+    }
+}
+
+fn set_equalizer(filters: &FilterChain, preset: Equalizer) {
+    let preset = match preset {
+        Equalizer::Flat => return filters.remove(equalizer::FILTER_NAME),
+        Equalizer::BassBoost => EqualizerPreset::BassBoost,
+        Equalizer::Vocal => EqualizerPreset::Vocal,
+    };
+
+    filters.insert(
+        equalizer::FILTER_NAME,
+        equalizer::Equalizer::from_preset(preset),
+    );
+}
+
+/// The limiter backs up librespot's normalisation for tracks without loudness metadata, which the player reports
+/// through the [`FadeControl`]
+fn set_loudness_limiter(
+    filters: &FilterChain,
+    fade: &FadeControl,
+    normalization: Normalization,
+    target_loudness: f64,
+) {
+    match normalization {
+        Normalization::Off => filters.remove(loudness::FILTER_NAME),
+        _ => filters.insert(
+            loudness::FILTER_NAME,
+            LoudnessLimiter::new(target_loudness, fade.clone()),
+        ),
+    }
+}
+
+/// Remember the credentials of a successful login, so that the next login doesn't need a new access token
+async fn save_reusable_credentials(
+    storage: &Storage,
+    account: &str,
+    credentials: &Credentials,
+    login: LoginMethod,
+) {
+    debug!("Logged in to Spotify account {account} using {login:?}");
+
+    let reusable = ReusableCredentials {
+        username: credentials.username.clone().unwrap_or_default(),
+        auth_data: credentials.auth_data.clone(),
+    };
+
+    if let Err(why) = storage
+        .save_reusable_credentials(account, &reusable, login)
+        .await
+    {
+        error!("Failed to store reusable Spotify credentials: {why}");
+    }
+}
+
+fn is_bad_credentials(why: &librespot::core::Error) -> bool {
+    matches!(
+        why.error.downcast_ref::<connection::AuthenticationError>(),
+        Some(connection::AuthenticationError::LoginFailed(
+            ErrorCode::BadCredentials
+        ))
+    )
+}
+
 #[derive(Debug)]
 pub enum SessionCommand {
     GetOwner(oneshot::Sender<UserId>),
     GetPlayer(oneshot::Sender<PlayerHandle>),
     GetActive(oneshot::Sender<bool>),
+    GetAutoplay(oneshot::Sender<bool>),
+    GetQueue(oneshot::Sender<GuildQueue>),
 
     CreatePlaybackEmbed(
         SessionHandle,
+        serenity::all::Context,
         CommandInteraction,
         playback_embed::UpdateBehavior,
     ),
-    CreateLyricsEmbed(SessionHandle, CommandInteraction),
+    CreateLyricsEmbed(SessionHandle, serenity::all::Context, CommandInteraction),
+
+    SetEqualizer(Equalizer),
+    SetCrossfade(Duration),
+    SetNormalization(Normalization, f64),
+    SetAutoplay(bool),
+    SetIdleTimeout(Duration),
+
+    Enqueue(
+        QueueEntry,
+        oneshot::Sender<std::result::Result<usize, QueueLimit>>,
+    ),
+    RemoveFromQueue(
+        usize,
+        Option<UserId>,
+        oneshot::Sender<std::result::Result<QueueEntry, RemoveError>>,
+    ),
+    MoveInQueue(usize, usize, oneshot::Sender<Option<QueueEntry>>),
+    ShuffleQueue,
+    ClearQueue(oneshot::Sender<usize>),
+    PlayContext(String, oneshot::Sender<anyhow::Result<Option<String>>>),
+    PlayNow(QueueEntry, oneshot::Sender<anyhow::Result<()>>),
 
     Reactivate(UserId, oneshot::Sender<Result<()>>),
+    /// Pick up the playback of a session that was saved before the bot restarted
+    Restore(SavedSession),
+    /// The player has been rebuilt after a connection reset, or `None` if all attempts failed
+    Reconnected(Option<NewPlayer>, Option<ResumePoint>),
     ShutdownPlayer,
     Disconnect,
     DisconnectTimedOut,
+    /// Move on from a track that couldn't be played, if Spotify hasn't done so by itself
+    SkipUnavailable(SpotifyId),
+    /// Leave the call but keep the saved session, so it is restored once the bot is back
+    Suspend,
+}
+
+/// A freshly created player, along with its events and the credentials it can reconnect with.
+///
+/// Players that don't play from a Spotify account don't have any credentials.
+type NewPlayer = (
+    PlayerHandle,
+    mpsc::Receiver<PlayerEvent>,
+    Option<Credentials>,
+);
+
+/// Where playback was when the connection to Spotify was lost
+#[derive(Debug)]
+pub struct ResumePoint {
+    position: u32,
+    playing: bool,
+
+    /// The URI of the track to play, instead of continuing with whatever Spotify considers current
+    track: Option<String>,
+    /// The URI of the context that `track` is played in
+    context: Option<String>,
+}
+
+impl From<PlaybackInfo> for ResumePoint {
+    fn from(playback_info: PlaybackInfo) -> Self {
+        Self {
+            position: playback_info.current_position(),
+            playing: playback_info.playing(),
+
+            track: None,
+            context: None,
+        }
+    }
 }
 
 pub struct Session {
     session_manager: SessionManager,
-    context: serenity::all::Context,
+    discord: Arc<dyn Discord>,
 
     guild_id: GuildId,
-    text_channel: GuildChannel,
+    voice_channel: ChannelId,
+    text_channel: ChannelId,
     call: Arc<Mutex<Call>>,
     player: PlayerHandle,
+    filters: FilterChain,
+    fade: FadeControl,
+
+    /// The Spotify account that has been leased to this session
+    account: String,
+    /// Reusable credentials of the current Spotify session, which don't require a new access token
+    credentials: Option<Credentials>,
+    reconnecting: bool,
+    /// The bitrate of the current player, which may be lower than configured after audio key errors
+    bitrate: Bitrate,
+    /// Keep the queue filled with similar tracks once it runs out
+    autoplay: bool,
+    /// How long the bot stays in the call without playing
+    idle_timeout: Duration,
+    /// The tracks that members have queued through the bot
+    queue: GuildQueue,
 
     owner: UserId,
     active: bool,
@@ -70,13 +317,19 @@ pub struct Session {
 
     playback_embed: Option<PlaybackEmbedHandle>,
     lyrics_embed: Option<JoinHandle<()>>,
+
+    /// The amount of audio underruns reported by the current player
+    underruns: u64,
+    /// The current track, if it turned out to be unavailable and has to be skipped
+    unavailable: Option<SpotifyId>,
+    /// When playback failed recently, which is limited to [`PLAYBACK_ERROR_RECONNECTS`] reconnects
+    playback_errors: Vec<Instant>,
 }
 
 impl Session {
     pub async fn create(
         session_manager: SessionManager,
 
-        context: &serenity::all::Context,
         guild_id: GuildId,
         voice_channel_id: ChannelId,
         text_channel_id: ChannelId,
@@ -84,43 +337,55 @@ impl Session {
     ) -> Result<SessionHandle> {
         use Error::*;
 
-        // Set up communication channel
-        let (tx, rx) = mpsc::channel(16);
-        let handle = SessionHandle {
-            guild: guild_id,
-            voice_channel: voice_channel_id,
-            text_channel: text_channel_id,
+        let discord = session_manager.discord();
+        let storage = session_manager.storage();
+        let settings = storage.get_guild_settings(guild_id.get()).await?;
+
+        // Resolve text channel, preferring the announcement channel of the guild
+        let text_channel = match settings.announce_channel {
+            Some(announce_channel) => {
+                match discord
+                    .resolve_text_channel(ChannelId::new(announce_channel))
+                    .await
+                {
+                    Ok(channel) => channel,
+                    Err(why) => {
+                        warn!("Announcement channel of guild {guild_id} is unavailable: {why}");
 
-            commands: tx,
+                        discord.resolve_text_channel(text_channel_id).await?
+                    }
+                }
+            }
+            None => discord.resolve_text_channel(text_channel_id).await?,
         };
 
-        // Resolve text channel
-        let text_channel = text_channel_id
-            .to_channel(&context)
-            .await?
-            .guild()
-            .ok_or(InvalidChannel)?;
-
         // Create channel for internal command communication (timeouts hint hint)
         // This uses separate channels as to not cause a cyclic dependency
         let (inner_tx, inner_rx) = mpsc::channel(16);
 
-        // Grab user credentials and info before joining call        // With centralized Spotify account, we no longer need per-user accounts
-        // Just get the centralized token from storage
-        let access_token = session_manager
-            .storage()
-            .get_spotify_token()
-            .await?
-            .ok_or_else(|| Error::Other("No Spotify account linked to bot".into()))?;
+        // Make sure the bot has a free Spotify account before joining the call
+        let account = session_manager.lease_account(guild_id).await?;
 
-        // Use a default device name for the bot
-        let device_name = "Spoticord Bot".to_string();        let credentials = Credentials::with_access_token(access_token);
+        // Set up communication channel
+        let (tx, rx) = mpsc::channel(16);
+        let handle = SessionHandle {
+            guild: guild_id,
+            voice_channel: voice_channel_id,
+            text_channel,
+            account: account.clone(),
+
+            commands: tx,
+        };
 
         // Hello Discord I'm here
-        let call = session_manager
-            .songbird()
-            .join(guild_id, voice_channel_id)
-            .await?;
+        let call = match discord.join(guild_id, voice_channel_id).await {
+            Ok(call) => call,
+            Err(why) => {
+                session_manager.release_account(&account);
+
+                return Err(why);
+            }
+        };
 
         // Make sure call guard is dropped or else we can't execute session.run
         {
@@ -134,38 +399,57 @@ impl Session {
             call.add_global_event(Event::Core(CoreEvent::ClientDisconnect), handle.clone());
         }
 
-        let (player, events, _auth_data) =
-            match Player::create(credentials, call.clone(), device_name).await {
-                Ok(player) => player,
-                Err(why) => {
-                    // Leave call on error, otherwise bot will be stuck in call forever until manually disconnected or taken over
-                    _ = call.lock().await.leave().await;
+        let filters = FilterChain::new();
+        let fade = FadeControl::new(Duration::from_secs(settings.crossfade));
+        let options = player_options(&settings, &filters, &fade, session_manager.audio_cache());
+        let bitrate = options.bitrate;
+
+        let (player, events, credentials) = match session_manager
+            .backend()
+            .login(&storage, &account, &call, options)
+            .await
+        {
+            Ok(player) => player,
+            Err(why) => {
+                // Leave call on error, otherwise bot will be stuck in call forever until manually disconnected or taken over
+                _ = call.lock().await.leave().await;
+                session_manager.release_account(&account);
 
-                    error!("Failed to create player: {why}");
+                error!("Failed to create player: {why}");
 
-                    if let Some(connection::AuthenticationError::LoginFailed(
-                        ErrorCode::BadCredentials,
-                    )) = why.error.downcast_ref::<connection::AuthenticationError>()
-                    {
+                if let Librespot(why) = &why {
+                    if is_bad_credentials(why) {
                         // Authentication failed with centralized credentials
                         error!("Spotify authentication failed - bot credentials may be invalid");
                         return Err(AuthenticationFailed);
-                    }                    return Err(why.into());
+                    }
                 }
-            };
 
-        // No need to store credentials since they're centralized
+                return Err(why);
+            }
+        };
 
         let mut session = Self {
             session_manager,
 
-            context: context.to_owned(),
+            discord,
             text_channel,
 
             call,
             player,
+            filters,
+            fade,
+
+            account,
+            credentials,
+            reconnecting: false,
+            bitrate,
+            autoplay: settings.autoplay,
+            idle_timeout: idle_timeout(&settings),
+            queue: GuildQueue::default(),
 
             guild_id,
+            voice_channel: voice_channel_id,
             owner,
 
             active: true,
@@ -179,6 +463,10 @@ impl Session {
 
             playback_embed: None,
             lyrics_embed: None,
+
+            underruns: 0,
+            unavailable: None,
+            playback_errors: Vec::new(),
         };
         session.start_timeout();
 
@@ -188,6 +476,8 @@ impl Session {
     }
 
     pub async fn run(mut self) {
+        let mut save_interval = tokio::time::interval(SAVE_INTERVAL);
+
         loop {
             tokio::select! {
                 opt_command = self.commands.recv() => {
@@ -213,6 +503,8 @@ impl Session {
                     self.handle_event(event).await;
                 },
 
+                _ = save_interval.tick(), if self.active => self.save_state().await,
+
                 // Internal communication channel
                 Some(command) = self.commands_inner_rx.recv() => {
                     trace!("Received internal command: {command:#?}");
@@ -236,9 +528,11 @@ impl Session {
             SessionCommand::GetOwner(sender) => _ = sender.send(self.owner),
             SessionCommand::GetPlayer(sender) => _ = sender.send(self.player.clone()),
             SessionCommand::GetActive(sender) => _ = sender.send(self.active),
+            SessionCommand::GetAutoplay(sender) => _ = sender.send(self.autoplay),
+            SessionCommand::GetQueue(sender) => _ = sender.send(self.queue.clone()),
 
-            SessionCommand::CreatePlaybackEmbed(handle, interaction, behavior) => {
-                match PlaybackEmbed::create(self, handle, interaction, behavior).await {
+            SessionCommand::CreatePlaybackEmbed(handle, context, interaction, behavior) => {
+                match PlaybackEmbed::create(self, handle, context, interaction, behavior).await {
                     Ok(opt_handle) => {
                         self.playback_embed = opt_handle;
                     }
@@ -247,8 +541,8 @@ impl Session {
                     }
                 };
             }
-            SessionCommand::CreateLyricsEmbed(handle, interaction) => {
-                match LyricsEmbed::create(self, handle, interaction).await {
+            SessionCommand::CreateLyricsEmbed(handle, context, interaction) => {
+                match LyricsEmbed::create(self, handle, context, interaction).await {
                     Ok(Some(lyrics_embed)) => {
                         if let Some(current) = self.lyrics_embed.take() {
                             current.abort();
@@ -263,9 +557,108 @@ impl Session {
                 }
             }
 
+            SessionCommand::SetEqualizer(preset) => set_equalizer(&self.filters, preset),
+            SessionCommand::SetCrossfade(crossfade) => self.fade.set_crossfade(crossfade),
+            SessionCommand::SetNormalization(normalization, target_loudness) => {
+                set_loudness_limiter(&self.filters, &self.fade, normalization, target_loudness)
+            }
+            SessionCommand::SetIdleTimeout(idle_timeout) => {
+                self.idle_timeout = idle_timeout;
+
+                // Restart a running timeout, so that it uses the new length
+                if self.timeout_tx.is_some() {
+                    self.start_timeout();
+                }
+            }
+            SessionCommand::SetAutoplay(autoplay) => {
+                self.autoplay = autoplay;
+
+                if self.active {
+                    // Fill the queue right away if it has already run out
+                    self.refresh_upcoming();
+                }
+
+                if let Some(playback_embed) = &self.playback_embed {
+                    if playback_embed.invoke_update(true).await.is_err() {
+                        self.playback_embed = None;
+                    }
+                }
+            }
+
+            SessionCommand::Enqueue(entry, tx) => _ = tx.send(self.enqueue(entry).await),
+            SessionCommand::RemoveFromQueue(index, requester, tx) => {
+                _ = tx.send(self.queue.remove(index, requester))
+            }
+            SessionCommand::MoveInQueue(from, to, tx) => {
+                _ = tx.send(self.queue.move_entry(from, to).cloned())
+            }
+            SessionCommand::ShuffleQueue => self.queue.shuffle(),
+            SessionCommand::ClearQueue(tx) => _ = tx.send(self.queue.clear()),
+            SessionCommand::PlayContext(uri, tx) => _ = tx.send(self.play_context(&uri).await),
+            SessionCommand::PlayNow(entry, tx) => _ = tx.send(self.play_now(entry).await),
+
             SessionCommand::Reactivate(new_owner, tx) => {
                 _ = tx.send(self.reactivate(new_owner).await)
             }
+            SessionCommand::Restore(saved) => self.restore(saved).await,
+            SessionCommand::Reconnected(player, resume) => {
+                if !self.reconnecting {
+                    // The player has been replaced or stopped in the meantime
+                    if let Some((player, _, _)) = player {
+                        player.shutdown().await;
+                    }
+
+                    return ControlFlow::Continue(());
+                }
+
+                self.reconnecting = false;
+
+                let Some((player, events, credentials)) = player else {
+                    self.disconnect().await;
+
+                    _ = self
+                        .discord
+                        .send_message(
+                            self.text_channel,
+                            CreateMessage::new().embed(
+                                CreateEmbed::new()
+                                    .title("Spotify connection lost")
+                                    .description("The bot has lost connection to the Spotify AP servers, and was unable to reconnect.\nThis is most likely caused by a connection reset on Spotify's end.\n\nUse `/join` to resummon the bot to your voice channel.")
+                                    .color(Colors::Error),
+                            ),
+                        )
+                        .await;
+
+                    return ControlFlow::Break(());
+                };
+
+                self.player.shutdown().await;
+
+                if let Some(credentials) = &credentials {
+                    save_reusable_credentials(
+                        &self.session_manager.storage(),
+                        &self.account,
+                        credentials,
+                        LoginMethod::ReusableCredentials,
+                    )
+                    .await;
+                }
+
+                self.player = player;
+                self.events = events;
+                self.credentials = credentials;
+                self.underruns = 0;
+                self.unavailable = None;
+
+                if let Some(resume) = resume {
+                    if let Err(why) = self.resume_playback(&resume).await {
+                        error!(
+                            "Failed to resume playback in guild {} after reconnecting: {why}",
+                            self.guild_id
+                        );
+                    }
+                }
+            }
             SessionCommand::ShutdownPlayer => self.shutdown_player().await,
             SessionCommand::Disconnect => {
                 self.disconnect().await;
@@ -276,9 +669,9 @@ impl Session {
                 self.disconnect().await;
 
                 _ = self
-                    .text_channel
+                    .discord
                     .send_message(
-                        &self.context,
+                        self.text_channel,
                         CreateMessage::new().embed(
                             CreateEmbed::new()
                                 .title("It's a little quiet in here")
@@ -288,6 +681,13 @@ impl Session {
                     )
                     .await;
 
+                return ControlFlow::Break(());
+            }
+            SessionCommand::SkipUnavailable(track) => self.skip_unavailable(track).await,
+            SessionCommand::Suspend => {
+                self.save_state().await;
+                self.leave().await;
+
                 return ControlFlow::Break(());
             }
         };
@@ -297,25 +697,129 @@ impl Session {
 
     async fn handle_event(&mut self, event: PlayerEvent) {
         match event {
-            PlayerEvent::Play => self.stop_timeout(),
-            PlayerEvent::Pause => self.start_timeout(),
+            PlayerEvent::Play => {
+                self.unavailable = None;
+                self.stop_timeout();
+                self.save_state().await;
+            }
+            PlayerEvent::Pause => {
+                self.start_timeout();
+                self.save_state().await;
+            }
             PlayerEvent::Stopped => self.shutdown_player().await,
-            PlayerEvent::TrackChanged(_) => {}
-            PlayerEvent::ConnectionReset => {
-                self.disconnect().await;
+            PlayerEvent::TrackChanged(ref playback_info) => {
+                self.unavailable = None;
 
-                _ = self
-                    .text_channel
-                    .send_message(
-                        &self.context,
-                        CreateMessage::new().embed(
-                            CreateEmbed::new()
-                                .title("Spotify connection lost")
-                                .description("The bot has lost connection to the Spotify AP servers.\nThis is most likely caused by a connection reset on Spotify's end.\n\nUse `/join` to resummon the bot to your voice channel.")
-                                .color(Colors::Error),
-                        ),
+                if let Ok(uri) = playback_info.track_id().to_uri() {
+                    self.queue.track_changed(&uri);
+                }
+
+                self.feed_queue().await;
+                self.record_history(playback_info).await;
+                self.save_state().await;
+
+                // The upcoming tracks only have to be looked up again once they have all been
+                // played, or when something else than the next one started playing
+                if playback_info.next_tracks().is_empty() {
+                    self.refresh_upcoming();
+                }
+            }
+            PlayerEvent::ModeChanged { .. } | PlayerEvent::UpcomingChanged => {}
+            PlayerEvent::VolumeChanged(volume) => self.save_volume(volume).await,
+            PlayerEvent::AudioStats(stats) => {
+                if stats.underruns > self.underruns {
+                    debug!(
+                        "Audio underrun in guild {}: {} new underruns, {} zero-filled bytes in total",
+                        self.guild_id,
+                        stats.underruns - self.underruns,
+                        stats.zero_filled_bytes
+                    );
+                }
+
+                self.underruns = stats.underruns;
+
+                // Telemetry doesn't change anything about the playback embed
+                return;
+            }
+            PlayerEvent::ConnectionReset => self.reconnect().await,
+            PlayerEvent::BitrateFallback { track, from, to } => {
+                // Events from the old player may still arrive while the new one is being created
+                if from != self.bitrate || self.reconnecting {
+                    return;
+                }
+
+                warn!(
+                    "Failed to load track {} at {} kbps in guild {}, falling back to {} kbps",
+                    track.to_base62().unwrap_or_default(),
+                    bitrate_to_kbps(from),
+                    self.guild_id,
+                    bitrate_to_kbps(to)
+                );
+
+                self.bitrate = to;
+
+                // Spotify has already moved on, so go back to the track that failed
+                let context = self
+                    .player
+                    .playback_info()
+                    .await
+                    .ok()
+                    .flatten()
+                    .and_then(|info| info.context().map(|context| context.uri.clone()));
+
+                self.rebuild_player(Some(ResumePoint {
+                    position: 0,
+                    playing: true,
+
+                    track: track.to_uri().ok(),
+                    context,
+                }))
+                .await;
+
+                return;
+            }
+            PlayerEvent::TrackUnavailable {
+                track,
+                name,
+                preloading,
+            } => {
+                self.handle_unavailable(track, name, preloading).await;
+
+                return;
+            }
+            PlayerEvent::PlaybackError(reason) => {
+                error!("Playback failed in guild {}: {reason}", self.guild_id);
+
+                self.playback_errors
+                    .retain(|error| error.elapsed() < PLAYBACK_ERROR_WINDOW);
+                self.playback_errors.push(Instant::now());
+
+                if self.playback_errors.len() > PLAYBACK_ERROR_RECONNECTS {
+                    error!(
+                        "Playback keeps failing in guild {}, stopping the player",
+                        self.guild_id
+                    );
+
+                    self.shutdown_player().await;
+                    self.send_warning(
+                        "Playback failed",
+                        "Something keeps going wrong while playing audio in the voice channel, so the bot has stopped playing.\n\nUse `/join` to try again.",
                     )
                     .await;
+
+                    return;
+                }
+
+                self.send_warning(
+                    "Playback failed",
+                    "Something went wrong while playing audio in the voice channel, reconnecting the player.",
+                )
+                .await;
+
+                // The songbird track is dead, so a new player is the only way to get audio flowing again
+                self.reconnect().await;
+
+                return;
             }
         }
 
@@ -328,6 +832,119 @@ impl Session {
         }
     }
 
+    /// Let the text channel know that a track was skipped, and move on if Spotify doesn't
+    async fn handle_unavailable(
+        &mut self,
+        track: SpotifyId,
+        name: Option<String>,
+        preloading: bool,
+    ) {
+        let track_name = match name {
+            Some(name) => format!("**{}**", escape(name)),
+            None => "A track".to_string(),
+        };
+
+        warn!(
+            "Track {} is unavailable in guild {}",
+            track.to_base62().unwrap_or_default(),
+            self.guild_id
+        );
+
+        let skip = match self
+            .session_manager
+            .storage()
+            .get_guild_settings(self.guild_id.get())
+            .await
+        {
+            Ok(settings) => settings.skip_unavailable,
+            Err(why) => {
+                error!("Failed to load guild settings: {why}");
+
+                GuildSettings::default().skip_unavailable
+            }
+        };
+
+        let outcome = if preloading {
+            "will be skipped"
+        } else if skip {
+            "has been skipped"
+        } else {
+            "could not be played"
+        };
+
+        self.send_warning(
+            "Track unavailable",
+            format!("{track_name} can't be played on Spotify right now and {outcome}."),
+        )
+        .await;
+
+        // Spotify skips preloaded tracks that failed by itself once it gets to them
+        if !skip || preloading {
+            return;
+        }
+
+        self.unavailable = Some(track);
+
+        let inner_tx = self.commands_inner_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(SKIP_UNAVAILABLE_DELAY).await;
+
+            _ = inner_tx.send(SessionCommand::SkipUnavailable(track)).await;
+        });
+    }
+
+    /// Skip an unavailable track, unless something else has started playing in the meantime
+    async fn skip_unavailable(&mut self, track: SpotifyId) {
+        if self.unavailable != Some(track) {
+            return;
+        }
+
+        self.unavailable = None;
+
+        let playing = self
+            .player
+            .playback_info()
+            .await
+            .ok()
+            .flatten()
+            .is_some_and(|playback_info| playback_info.playing());
+
+        if !playing {
+            self.player.next_track().await;
+        }
+    }
+
+    async fn send_warning(&self, title: &str, description: impl Into<String>) {
+        if let Err(why) = self
+            .discord
+            .send_message(
+                self.text_channel,
+                CreateMessage::new().embed(
+                    CreateEmbed::new()
+                        .title(title)
+                        .description(description)
+                        .color(Colors::Warning),
+                ),
+            )
+            .await
+        {
+            error!("Failed to send warning to guild {}: {why}", self.guild_id);
+        }
+    }
+
+    /// Remember the volume so it can be restored the next time the bot joins this guild
+    async fn save_volume(&self, volume: u8) {
+        let storage = self.session_manager.storage();
+        let guild_id = self.guild_id.get();
+
+        if let Err(why) = storage
+            .update_guild_settings(guild_id, |settings| settings.volume = volume)
+            .await
+        {
+            error!("Failed to save volume for guild {guild_id}: {why}");
+        }
+    }
+
     fn start_timeout(&mut self) {
         if let Some(tx) = self.timeout_tx.take() {
             _ = tx.send(());
@@ -337,10 +954,10 @@ impl Session {
         self.timeout_tx = Some(tx);
 
         let inner_tx = self.commands_inner_tx.clone();
+        let idle_timeout = self.idle_timeout;
 
         tokio::spawn(async move {
-            let mut timer =
-                tokio::time::interval(Duration::from_secs(spoticord_config::DISCONNECT_TIME));
+            let mut timer = tokio::time::interval(idle_timeout);
 
             // Ignore immediate tick
             timer.tick().await;
@@ -359,57 +976,442 @@ impl Session {
         if let Some(tx) = self.timeout_tx.take() {
             _ = tx.send(());
         }
-    }    async fn reactivate(&mut self, new_owner: UserId) -> Result<()> {
+    }
+    async fn reactivate(&mut self, new_owner: UserId) -> Result<()> {
         use Error::*;
 
         if self.active {
             return Err(AlreadyActive);
         }
 
-        // With centralized Spotify account, we no longer need per-user accounts
-        // Just get the centralized token from storage
-        let access_token = self
+        let storage = self.session_manager.storage();
+        let settings = storage.get_guild_settings(self.guild_id.get()).await?;
+        let options = player_options(
+            &settings,
+            &self.filters,
+            &self.fade,
+            self.session_manager.audio_cache(),
+        );
+        let bitrate = options.bitrate;
+
+        let (player, player_events, credentials) = match self
+            .session_manager
+            .backend()
+            .login(&storage, &self.account, &self.call, options)
+            .await
+        {
+            Ok(player) => player,
+            Err(why) => {
+                if matches!(&why, Librespot(why) if is_bad_credentials(why)) {
+                    // Authentication failed with centralized credentials
+                    // Log the error but don't clear anything since it's not user-specific
+                    error!("Spotify authentication failed - bot credentials may be invalid");
+                }
+
+                return Err(why);
+            }
+        };
+
+        self.owner = new_owner;
+        self.player = player;
+        self.events = player_events;
+        self.credentials = credentials;
+        self.bitrate = bitrate;
+        self.autoplay = settings.autoplay;
+        self.idle_timeout = idle_timeout(&settings);
+        self.active = true;
+        self.reconnecting = false;
+        self.underruns = 0;
+
+        // Pick up the queue where it was left off
+        self.queue.requeue_next();
+        self.feed_queue().await;
+
+        Ok(())
+    }
+
+    /// Rebuild the player after the connection to the Spotify AP was lost, picking up where it left off
+    async fn reconnect(&mut self) {
+        let resume = self
+            .player
+            .playback_info()
+            .await
+            .ok()
+            .flatten()
+            .map(ResumePoint::from);
+
+        self.rebuild_player(resume).await;
+    }
+
+    /// Create a new player in the background, which replaces the current one once it is ready.
+    ///
+    /// The old player is kept around until then, so that its event channel stays open.
+    async fn rebuild_player(&mut self, resume: Option<ResumePoint>) {
+        if self.reconnecting {
+            return;
+        }
+
+        self.reconnecting = true;
+
+        let settings = match self
             .session_manager
             .storage()
-            .get_spotify_token()
-            .await?
-            .ok_or_else(|| Error::Other("No Spotify account linked to bot".into()))?;
+            .get_guild_settings(self.guild_id.get())
+            .await
+        {
+            Ok(settings) => settings,
+            Err(why) => {
+                error!("Failed to load guild settings, using defaults: {why}");
 
-        // Use a default device name for the bot
-        let device_name = "Spoticord Bot".to_string();
+                GuildSettings::default()
+            }
+        };
 
-        let credentials = Credentials::with_access_token(access_token);        let (player, player_events, _auth_data) =
-            match Player::create(credentials, self.call.clone(), device_name).await {
-                Ok(player) => player,
-                Err(why) => {
-                    if let Some(connection::AuthenticationError::LoginFailed(
-                        ErrorCode::BadCredentials,
-                    )) = why.error.downcast_ref::<connection::AuthenticationError>()
-                    {
-                        // Authentication failed with centralized credentials
-                        // Log the error but don't clear anything since it's not user-specific
-                        error!("Spotify authentication failed - bot credentials may be invalid");
+        let options = PlayerOptions {
+            bitrate: self.bitrate,
+            ..player_options(
+                &settings,
+                &self.filters,
+                &self.fade,
+                self.session_manager.audio_cache(),
+            )
+        };
+        let backend = self.session_manager.backend();
+        let storage = self.session_manager.storage();
+        let account = self.account.clone();
+        let credentials = self.credentials.clone();
+        let call = self.call.clone();
+        let guild_id = self.guild_id;
+        let inner_tx = self.commands_inner_tx.clone();
+
+        tokio::spawn(async move {
+            let mut delay = RECONNECT_DELAY;
+
+            for attempt in 1..=RECONNECT_ATTEMPTS {
+                tokio::time::sleep(delay).await;
+
+                let result = backend
+                    .reconnect(
+                        &storage,
+                        &account,
+                        credentials.clone(),
+                        &call,
+                        options.clone(),
+                    )
+                    .await;
+
+                match result {
+                    Ok(player) => {
+                        info!(
+                            "Reconnected to Spotify in guild {guild_id} after {attempt} attempt(s)"
+                        );
+
+                        _ = inner_tx
+                            .send(SessionCommand::Reconnected(Some(player), resume))
+                            .await;
+
+                        return;
                     }
+                    Err(why) => {
+                        warn!("Reconnection attempt {attempt}/{RECONNECT_ATTEMPTS} in guild {guild_id} failed: {why}");
 
-                    return Err(why.into());
+                        delay *= 2;
+                    }
                 }
-            };
+            }
 
-        // No need to store credentials since they're centralized
+            error!("Failed to reconnect to Spotify in guild {guild_id}, giving up");
 
-        self.owner = new_owner;
-        self.player = player;
-        self.events = player_events;
-        self.active = true;
+            _ = inner_tx
+                .send(SessionCommand::Reconnected(None, resume))
+                .await;
+        });
+    }
+
+    /// Move playback back to the bot after reconnecting, at the position it was at before the connection was lost
+    async fn resume_playback(&self, resume: &ResumePoint) -> anyhow::Result<()> {
+        self.session_manager
+            .backend()
+            .resume(
+                &self.session_manager.storage(),
+                &self.account,
+                &self.player,
+                resume,
+            )
+            .await
+    }
+
+    /// Remember where this session is, so that it can be restored if the bot restarts
+    async fn save_state(&self) {
+        let playback_info = self.player.playback_info().await.ok().flatten();
+
+        let saved = SavedSession {
+            guild_id: self.guild_id.get(),
+            voice_channel_id: self.voice_channel.get(),
+            text_channel_id: self.text_channel.get(),
+            owner_id: self.owner.get(),
+
+            context: playback_info
+                .as_ref()
+                .and_then(|info| info.context().map(|context| context.uri.clone())),
+            track: playback_info
+                .as_ref()
+                .and_then(|info| info.track_id().to_uri().ok()),
+            position: playback_info
+                .as_ref()
+                .map(PlaybackInfo::current_position)
+                .unwrap_or_default(),
+            playing: playback_info.as_ref().is_some_and(PlaybackInfo::playing),
+
+            saved_at: chrono::Utc::now(),
+        };
+
+        if let Err(why) = self.session_manager.storage().save_session(&saved).await {
+            error!("Failed to save session of guild {}: {why}", self.guild_id);
+        }
+    }
+
+    async fn remove_state(&self) {
+        if let Err(why) = self
+            .session_manager
+            .storage()
+            .remove_saved_session(self.guild_id.get())
+            .await
+        {
+            error!(
+                "Failed to remove saved session of guild {}: {why}",
+                self.guild_id
+            );
+        }
+    }
+
+    /// Continue playing where a saved session left off, and let the text channel know
+    async fn restore(&self, saved: SavedSession) {
+        let description = match &saved.track {
+            Some(track) => match self.restore_playback(&saved, track).await {
+                Ok(()) => "The bot has been restarted, and has picked up where it left off.",
+                Err(why) => {
+                    error!(
+                        "Failed to restore playback in guild {}: {why}",
+                        self.guild_id
+                    );
+
+                    "The bot has been restarted, but was unable to continue playing.\nSelect the bot in Spotify to start playing again."
+                }
+            },
+            None => "The bot has been restarted, and has rejoined the voice channel.",
+        };
+
+        _ = self
+            .discord
+            .send_message(
+                self.text_channel,
+                CreateMessage::new().embed(
+                    CreateEmbed::new()
+                        .title("Resumed after restart")
+                        .description(description)
+                        .color(Colors::Info),
+                ),
+            )
+            .await;
+    }
+
+    async fn restore_playback(&self, saved: &SavedSession, track: &str) -> anyhow::Result<()> {
+        self.resume_playback(&ResumePoint {
+            position: saved.position,
+            playing: saved.playing,
+
+            track: Some(track.to_string()),
+            context: saved.context.clone(),
+        })
+        .await
+    }
+
+    /// Add an entry to the queue within the limits of the guild, and return its position in the queue
+    async fn enqueue(&mut self, entry: QueueEntry) -> std::result::Result<usize, QueueLimit> {
+        let settings = match self
+            .session_manager
+            .storage()
+            .get_guild_settings(self.guild_id.get())
+            .await
+        {
+            Ok(settings) => settings,
+            Err(why) => {
+                error!("Failed to load guild settings, using defaults: {why}");
+
+                GuildSettings::default()
+            }
+        };
+
+        self.queue.check_limits(&entry, &settings)?;
+
+        let index = self.queue.push(entry, settings.fair_queue);
+
+        let len = self.queue.len();
+        self.feed_queue().await;
+
+        // Every entry moves up a spot if the front of the queue has been handed to Spotify right away
+        Ok((index + 1).saturating_sub(len - self.queue.len()))
+    }
+
+    /// Start playing an album, playlist, artist or show on the device of the bot, and return its name
+    async fn play_context(&self, uri: &str) -> anyhow::Result<Option<String>> {
+        let spotify = web_api::client(&self.session_manager.storage(), &self.account).await?;
+
+        web_api::play_context(&spotify, self.player.device_id(), uri).await
+    }
+
+    /// Start playing a track or episode right away, without going through the queue
+    async fn play_now(&mut self, entry: QueueEntry) -> anyhow::Result<()> {
+        let spotify = web_api::client(&self.session_manager.storage(), &self.account).await?;
+
+        web_api::enqueue(&spotify, self.player.device_id(), &entry.uri, true).await?;
+        self.queue.play_now(entry);
 
         Ok(())
     }
 
+    /// Hand the next queued entry to Spotify, unless the previous one hasn't started playing yet
+    async fn feed_queue(&mut self) {
+        if !self.active {
+            return;
+        }
+
+        let Some(entry) = self.queue.take_next() else {
+            return;
+        };
+
+        let result = async {
+            let spotify = web_api::client(&self.session_manager.storage(), &self.account).await?;
+            let playback_info = self.player.playback_info().await?;
+
+            // Nothing is going to pick up the queue if nothing is playing
+            let play_now = playback_info.is_none();
+
+            web_api::enqueue(&spotify, self.player.device_id(), &entry.uri, play_now).await?;
+
+            // Spotify plays queued tracks before the rest of the context
+            if let Some(playback_info) = playback_info {
+                let mut next_tracks = vec![UpcomingTrack {
+                    uri: entry.uri.clone(),
+                    name: entry.name.clone(),
+                    artists: entry.artists.clone(),
+                }];
+                next_tracks.extend_from_slice(playback_info.next_tracks());
+
+                self.player
+                    .set_upcoming(playback_info.context().cloned(), next_tracks)
+                    .await;
+            }
+
+            anyhow::Ok(())
+        }
+        .await;
+
+        if let Err(why) = result {
+            error!(
+                "Failed to hand queued track to Spotify in guild {}: {why}",
+                self.guild_id
+            );
+
+            self.queue.discard_next();
+            self.send_warning(
+                "Cannot play queued track",
+                format!(
+                    "**{}** could not be played and has been removed from the queue.",
+                    escape(&entry.name)
+                ),
+            )
+            .await;
+        }
+    }
+
+    /// Remember a track that started playing, so autoplay can continue with similar tracks later on
+    async fn record_history(&self, playback_info: &PlaybackInfo) {
+        if !playback_info.is_track() {
+            return;
+        }
+
+        let Ok(uri) = playback_info.track_id().to_uri() else {
+            return;
+        };
+
+        if let Err(why) = self
+            .session_manager
+            .storage()
+            .add_to_guild_history(self.guild_id.get(), &uri)
+            .await
+        {
+            error!("Failed to update history of guild {}: {why}", self.guild_id);
+        }
+    }
+
+    /// Look up the context and upcoming tracks in the background, as librespot doesn't expose them.
+    ///
+    /// If autoplay is enabled and the queue has run out, similar tracks are queued first.
+    /// The player keeps track of them from then on, so this is only needed once they run out or
+    /// when playback jumps elsewhere.
+    fn refresh_upcoming(&self) {
+        let storage = self.session_manager.storage();
+        let account = self.account.clone();
+        let player = self.player.clone();
+        let guild_id = self.guild_id;
+        let autoplay = self.autoplay;
+
+        tokio::spawn(async move {
+            let result = async {
+                let spotify = web_api::client(&storage, &account).await?;
+                let device_id = player.device_id();
+
+                // The name of the context is only looked up when it has changed
+                let known = player
+                    .playback_info()
+                    .await?
+                    .and_then(|playback_info| playback_info.context().cloned());
+
+                let Some((mut context, mut next_tracks)) =
+                    web_api::upcoming(&spotify, device_id, known.as_ref()).await?
+                else {
+                    return Ok(());
+                };
+
+                if autoplay && next_tracks.is_empty() {
+                    let history = storage.get_guild_history(guild_id.get()).await?;
+                    let queued = web_api::autoplay(&spotify, &history, device_id).await?;
+
+                    debug!("Autoplay queued {queued} track(s) in guild {guild_id}");
+
+                    if queued > 0 {
+                        if let Some(upcoming) =
+                            web_api::upcoming(&spotify, device_id, context.as_ref()).await?
+                        {
+                            (context, next_tracks) = upcoming;
+                        }
+                    }
+                }
+
+                player.set_upcoming(context, next_tracks).await;
+
+                anyhow::Ok(())
+            }
+            .await;
+
+            if let Err(why) = result {
+                debug!("Failed to retrieve upcoming tracks for guild {guild_id}: {why}");
+            }
+        });
+    }
+
     async fn shutdown_player(&mut self) {
         self.player.shutdown().await;
         self.start_timeout();
 
         self.active = false;
+        self.reconnecting = false;
+        self.unavailable = None;
+
+        // Nothing is playing anymore, so there is nothing to restore either
+        self.remove_state().await;
 
         // Remove owner from session manager
         self.session_manager
@@ -417,6 +1419,11 @@ impl Session {
     }
 
     async fn disconnect(&mut self) {
+        self.remove_state().await;
+        self.leave().await;
+    }
+
+    async fn leave(&mut self) {
         // Kill timeout if one is running
         self.stop_timeout();
 
@@ -452,6 +1459,7 @@ impl Drop for Session {
 
         session_manager.remove_session(SessionQuery::Guild(guild_id));
         session_manager.remove_session(SessionQuery::Owner(owner));
+        session_manager.release_account(&self.account);
     }
 }
 
@@ -460,6 +1468,7 @@ pub struct SessionHandle {
     guild: GuildId,
     voice_channel: ChannelId,
     text_channel: ChannelId,
+    account: String,
 
     commands: mpsc::Sender<SessionCommand>,
 }
@@ -482,6 +1491,11 @@ impl SessionHandle {
         self.text_channel
     }
 
+    /// The ID of the Spotify account that this session plays on
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
     /// Retrieve the current owner of the session
     pub async fn owner(&self) -> anyhow::Result<UserId> {
         let (tx, rx) = oneshot::channel();
@@ -527,12 +1541,14 @@ impl SessionHandle {
     /// This playback embed will automatically update when certain events happen
     pub async fn create_playback_embed(
         &self,
+        context: &serenity::all::Context,
         interaction: &CommandInteraction,
         behavior: playback_embed::UpdateBehavior,
     ) -> anyhow::Result<()> {
         self.commands
             .send(SessionCommand::CreatePlaybackEmbed(
                 self.clone(),
+                context.to_owned(),
                 interaction.to_owned(),
                 behavior,
             ))
@@ -544,9 +1560,170 @@ impl SessionHandle {
     /// Create a lyrics embed as a response to an interaction
     ///
     /// This lyrics embed will automatically retrieve the lyrics and update the embed accordingly
-    pub async fn create_lyrics_embed(&self, interaction: CommandInteraction) -> anyhow::Result<()> {
+    pub async fn create_lyrics_embed(
+        &self,
+        context: &serenity::all::Context,
+        interaction: CommandInteraction,
+    ) -> anyhow::Result<()> {
         self.commands
-            .send(SessionCommand::CreateLyricsEmbed(self.clone(), interaction))
+            .send(SessionCommand::CreateLyricsEmbed(
+                self.clone(),
+                context.to_owned(),
+                interaction,
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Change the equalizer that is applied to the audio of this session
+    pub async fn set_equalizer(&self, preset: Equalizer) -> anyhow::Result<()> {
+        self.commands
+            .send(SessionCommand::SetEqualizer(preset))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Change the loudness normalization of this session.
+    ///
+    /// The loudness limiter is updated right away, librespot's normalisation only changes once a new player is created.
+    pub async fn set_normalization(
+        &self,
+        normalization: Normalization,
+        target_loudness: f64,
+    ) -> anyhow::Result<()> {
+        self.commands
+            .send(SessionCommand::SetNormalization(
+                normalization,
+                target_loudness,
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn autoplay(&self) -> anyhow::Result<bool> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(SessionCommand::GetAutoplay(tx)).await?;
+
+        let result = rx.await?;
+        Ok(result)
+    }
+
+    /// Retrieve the tracks that members have queued through the bot
+    pub async fn queue(&self) -> anyhow::Result<GuildQueue> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(SessionCommand::GetQueue(tx)).await?;
+
+        let result = rx.await?;
+        Ok(result)
+    }
+
+    /// Add a track to the queue of the guild, and return its position in the queue.
+    ///
+    /// The position is 0 if the track is the next one to be played. If queueing the track would exceed the
+    /// limits of the guild, the limit that would be exceeded is returned instead.
+    pub async fn enqueue(
+        &self,
+        entry: QueueEntry,
+    ) -> anyhow::Result<std::result::Result<usize, QueueLimit>> {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(SessionCommand::Enqueue(entry, tx))
+            .await?;
+
+        let result = rx.await?;
+        Ok(result)
+    }
+
+    /// Remove the queued track at a zero-based index, but only if `requester` queued it when one is given
+    pub async fn remove_from_queue(
+        &self,
+        index: usize,
+        requester: Option<UserId>,
+    ) -> anyhow::Result<std::result::Result<QueueEntry, RemoveError>> {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(SessionCommand::RemoveFromQueue(index, requester, tx))
+            .await?;
+
+        let result = rx.await?;
+        Ok(result)
+    }
+
+    /// Move the queued track at a zero-based index to another index
+    pub async fn move_in_queue(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> anyhow::Result<Option<QueueEntry>> {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(SessionCommand::MoveInQueue(from, to, tx))
+            .await?;
+
+        let result = rx.await?;
+        Ok(result)
+    }
+
+    pub async fn shuffle_queue(&self) -> anyhow::Result<()> {
+        self.commands.send(SessionCommand::ShuffleQueue).await?;
+
+        Ok(())
+    }
+
+    /// Remove all queued tracks that haven't been handed to Spotify yet, and return how many were removed
+    pub async fn clear_queue(&self) -> anyhow::Result<usize> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(SessionCommand::ClearQueue(tx)).await?;
+
+        let result = rx.await?;
+        Ok(result)
+    }
+
+    /// Start playing an album, playlist, artist or show, and return its name if it can be found
+    pub async fn play_context(&self, uri: impl Into<String>) -> anyhow::Result<Option<String>> {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(SessionCommand::PlayContext(uri.into(), tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Start playing a track or episode right away, without going through the queue
+    pub async fn play_now(&self, entry: QueueEntry) -> anyhow::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(SessionCommand::PlayNow(entry, tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Turn autoplay on or off, which keeps the queue filled with similar tracks once it runs out
+    pub async fn set_autoplay(&self, autoplay: bool) -> anyhow::Result<()> {
+        self.commands
+            .send(SessionCommand::SetAutoplay(autoplay))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Change how long the bot stays in the call without playing
+    pub async fn set_idle_timeout(&self, idle_timeout: Duration) -> anyhow::Result<()> {
+        self.commands
+            .send(SessionCommand::SetIdleTimeout(idle_timeout))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Change the length of the crossfade between tracks, zero disables crossfading
+    pub async fn set_crossfade(&self, crossfade: Duration) -> anyhow::Result<()> {
+        self.commands
+            .send(SessionCommand::SetCrossfade(crossfade))
             .await?;
 
         Ok(())
@@ -572,6 +1749,20 @@ impl SessionHandle {
             error!("Failed to send command: {why}");
         }
     }
+
+    /// Instruct the session to save its playback and destroy itself, so that it can be restored after a restart
+    pub async fn suspend(&self) {
+        if let Err(why) = self.commands.send(SessionCommand::Suspend).await {
+            error!("Failed to send command: {why}");
+        }
+    }
+
+    /// Instruct the session to continue playing where a saved session left off
+    pub async fn restore(&self, saved: SavedSession) -> anyhow::Result<()> {
+        self.commands.send(SessionCommand::Restore(saved)).await?;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -611,3 +1802,291 @@ impl songbird::EventHandler for SessionHandle {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use librespot::metadata::{
+        artist::ArtistsWithRole,
+        audio::{AudioFiles, AudioItem, UniqueFields},
+        Lyrics,
+    };
+    use spoticord_player::synthetic::{ScriptedEvent, SyntheticOptions};
+    use std::sync::Mutex as StdMutex;
+
+    const GUILD: GuildId = GuildId::new(1);
+    const VOICE_CHANNEL: ChannelId = ChannelId::new(2);
+    const TEXT_CHANNEL: ChannelId = ChannelId::new(3);
+    const OWNER: UserId = UserId::new(4);
+    /// An announcement channel that no longer exists
+    const DELETED_CHANNEL: ChannelId = ChannelId::new(5);
+
+    /// Lyrics the way Spotify's lyrics endpoint returns them
+    const LYRICS: &str = r#"{
+        "colors": { "background": -1, "highlightText": -1, "text": -1 },
+        "hasVocalRemoval": false,
+        "lyrics": {
+            "alternatives": [],
+            "fullscreenAction": "FULLSCREEN_LYRICS",
+            "isDenseTypeface": false,
+            "isRtlLanguage": false,
+            "language": "en",
+            "lines": [
+                { "startTimeMs": "0", "endTimeMs": "0", "words": "First line", "syllables": [] },
+                { "startTimeMs": "5000", "endTimeMs": "0", "words": "Second line", "syllables": [] }
+            ],
+            "provider": "Synthetic",
+            "providerDisplayName": "Synthetic",
+            "providerLyricsId": "1",
+            "syncLyricsUri": "",
+            "syncType": "LINE_SYNCED"
+        }
+    }"#;
+
+    /// Stands in for Discord, joining calls that aren't connected to anything
+    #[derive(Default)]
+    struct FakeDiscord {
+        messages: StdMutex<Vec<(ChannelId, String)>>,
+    }
+
+    impl FakeDiscord {
+        fn messages(&self) -> Vec<(ChannelId, String)> {
+            self.messages.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Discord for FakeDiscord {
+        async fn resolve_text_channel(&self, channel: ChannelId) -> Result<ChannelId> {
+            if channel == DELETED_CHANNEL {
+                return Err(Error::InvalidChannel);
+            }
+
+            Ok(channel)
+        }
+
+        async fn send_message(&self, channel: ChannelId, message: CreateMessage) -> Result<()> {
+            self.messages
+                .lock()
+                .unwrap()
+                .push((channel, format!("{message:?}")));
+
+            Ok(())
+        }
+
+        async fn join(&self, guild: GuildId, _channel: ChannelId) -> Result<Arc<Mutex<Call>>> {
+            Ok(Arc::new(Mutex::new(Call::standalone(
+                guild,
+                UserId::new(99),
+            ))))
+        }
+    }
+
+    fn track(id: &str, name: &str) -> PlayerEvent {
+        let uri = format!("spotify:track:{id}");
+        let audio_item = AudioItem {
+            track_id: SpotifyId::from_uri(&uri).unwrap(),
+            uri,
+            files: AudioFiles::default(),
+            name: name.to_string(),
+            covers: Vec::new(),
+            language: Vec::new(),
+            duration_ms: 180_000,
+            is_explicit: false,
+            availability: Ok(()),
+            alternatives: None,
+            unique_fields: UniqueFields::Track {
+                artists: ArtistsWithRole(Vec::new()),
+                album: String::from("Album"),
+                album_artists: Vec::new(),
+                popularity: 0,
+                number: 1,
+                disc_number: 1,
+            },
+        };
+
+        PlayerEvent::TrackChanged(Box::new(PlaybackInfo::new(audio_item, 0, true)))
+    }
+
+    /// Poll the player until `check` holds, panicking after a few seconds
+    async fn wait_for(player: &PlayerHandle, check: impl Fn(Option<&PlaybackInfo>) -> bool) {
+        let result = tokio::time::timeout(Duration::from_secs(5), async {
+            while !check(player.playback_info().await.unwrap().as_ref()) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await;
+
+        assert!(result.is_ok(), "timed out waiting for the player");
+    }
+
+    #[tokio::test]
+    async fn synthetic_session() {
+        let data_dir =
+            std::env::temp_dir().join(format!("spoticord-session-{}", std::process::id()));
+        let storage = Storage::new(&data_dir);
+        storage.init().await.unwrap();
+        storage
+            .update_guild_settings(GUILD.get(), |settings| {
+                settings.idle_timeout = Some(1);
+                settings.announce_channel = Some(DELETED_CHANNEL.get());
+            })
+            .await
+            .unwrap();
+
+        let script = vec![
+            ScriptedEvent::new(Duration::ZERO, track("4uLU6hMCjMI75M1A2tKUQC", "First")),
+            // Only reached by skipping
+            ScriptedEvent::new(
+                Duration::from_secs(3600),
+                track("7GhIk7Il098yCjg4BQjzvb", "Second"),
+            ),
+        ];
+
+        let discord = Arc::new(FakeDiscord::default());
+        let manager = SessionManager::new(discord.clone(), storage).with_synthetic_backend(
+            SyntheticOptions {
+                script,
+                ..Default::default()
+            },
+        );
+
+        let session = manager
+            .create_session(GUILD, VOICE_CHANNEL, TEXT_CHANNEL, OWNER)
+            .await
+            .unwrap();
+
+        // The deleted announcement channel falls back to the channel the bot was summoned from
+        assert_eq!(session.text_channel(), TEXT_CHANNEL);
+        assert!(manager.get_session(SessionQuery::Owner(OWNER)).is_some());
+
+        let player = session.player().await.unwrap();
+        wait_for(&player, |info| {
+            info.is_some_and(|info| info.name() == "First")
+        })
+        .await;
+
+        player.pause().await;
+        wait_for(&player, |info| info.is_some_and(|info| !info.playing())).await;
+
+        // Resuming stops the idle timeout that pausing started
+        player.play().await;
+        wait_for(&player, |info| info.is_some_and(PlaybackInfo::playing)).await;
+
+        player.seek(60_000).await;
+        wait_for(&player, |info| {
+            info.is_some_and(|info| info.current_position() >= 60_000)
+        })
+        .await;
+
+        player.next_track().await;
+        wait_for(&player, |info| {
+            info.is_some_and(|info| info.name() == "Second")
+        })
+        .await;
+
+        // There is nothing left to skip to, so playback stops and the session times out
+        player.next_track().await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while session.is_valid() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("session did not time out");
+
+        assert!(manager.get_session(SessionQuery::Owner(OWNER)).is_none());
+
+        let messages = discord.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].0, TEXT_CHANNEL);
+        assert!(messages[0].1.contains("It's a little quiet in here"));
+
+        _ = std::fs::remove_dir_all(data_dir);
+    }
+
+    #[tokio::test]
+    async fn synthetic_session_reconnects() {
+        let data_dir = std::env::temp_dir().join(format!(
+            "spoticord-session-reconnect-{}",
+            std::process::id()
+        ));
+        let storage = Storage::new(&data_dir);
+        storage.init().await.unwrap();
+
+        let script = vec![
+            ScriptedEvent::new(Duration::ZERO, track("4uLU6hMCjMI75M1A2tKUQC", "First")),
+            ScriptedEvent::new(Duration::from_millis(500), PlayerEvent::ConnectionReset),
+            // Only reached by skipping
+            ScriptedEvent::new(
+                Duration::from_secs(3600),
+                track("7GhIk7Il098yCjg4BQjzvb", "Second"),
+            ),
+        ];
+
+        let discord = Arc::new(FakeDiscord::default());
+        let manager = SessionManager::new(discord.clone(), storage).with_synthetic_backend(
+            SyntheticOptions {
+                script,
+                lyrics: Some(serde_json::from_str::<Lyrics>(LYRICS).unwrap()),
+                ..Default::default()
+            },
+        );
+
+        let session = manager
+            .create_session(GUILD, VOICE_CHANNEL, TEXT_CHANNEL, OWNER)
+            .await
+            .unwrap();
+
+        let player = session.player().await.unwrap();
+        wait_for(&player, |info| {
+            info.is_some_and(|info| info.name() == "First")
+        })
+        .await;
+
+        let lyrics = player.get_lyrics().await.unwrap().unwrap();
+        assert_eq!(lyrics.lyrics.lines[1].words, "Second line");
+
+        // Move playback along before the connection is lost
+        player.seek(60_000).await;
+        player.pause().await;
+        wait_for(&player, |info| info.is_some_and(|info| !info.playing())).await;
+
+        let position = player
+            .playback_info()
+            .await
+            .unwrap()
+            .unwrap()
+            .current_position();
+        assert!(position >= 60_000);
+
+        // The scripted connection reset has the session replace its player
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while player.is_valid() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("player was not rebuilt");
+
+        // The rebuilt player picks up the same track, at the same position
+        let rebuilt = session.player().await.unwrap();
+        wait_for(&rebuilt, |info| {
+            info.is_some_and(|info| {
+                info.name() == "First" && !info.playing() && info.current_position() == position
+            })
+        })
+        .await;
+
+        assert!(rebuilt.is_valid());
+        assert!(session.is_valid());
+        assert!(rebuilt.get_lyrics().await.unwrap().is_some());
+
+        // The call stays connected, so nobody was told the connection was lost
+        assert!(discord.messages().is_empty());
+
+        session.disconnect().await;
+
+        _ = std::fs::remove_dir_all(data_dir);
+    }
+}
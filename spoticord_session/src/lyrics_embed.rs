@@ -41,10 +41,9 @@ impl LyricsEmbed {
     pub async fn create(
         session: &Session,
         handle: SessionHandle,
+        ctx: Context,
         interaction: CommandInteraction,
     ) -> Result<Option<JoinHandle<()>>> {
-        let ctx = session.context.clone();
-
         if !session.active {
             respond_not_playing(&ctx, interaction).await?;
 
@@ -1,8 +1,12 @@
 use super::{Session, SessionHandle};
-use crate::error::Result;
+use crate::{
+    backend::{Backend, Spotify, Synthetic},
+    discord::Discord,
+    error::{Error, Result},
+};
 use serenity::all::{ChannelId, GuildId, UserId};
-use songbird::Songbird;
-use spoticord_storage::Storage;
+use spoticord_player::{cache::AudioCache, synthetic::SyntheticOptions};
+use spoticord_storage::{SavedSession, Storage};
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
@@ -10,11 +14,18 @@ use std::{
 
 #[derive(Clone)]
 pub struct SessionManager {
-    songbird: Arc<Songbird>,
+    discord: Arc<dyn Discord>,
     storage: Storage,
 
     sessions: Arc<Mutex<HashMap<GuildId, SessionHandle>>>,
     owners: Arc<Mutex<HashMap<UserId, SessionHandle>>>,
+    /// The Spotify accounts that are in use, and the guilds that are using them
+    leases: Arc<Mutex<HashMap<String, GuildId>>>,
+
+    /// The audio cache that is shared by the players of all sessions
+    cache: Option<AudioCache>,
+    /// Creates the players of all sessions
+    backend: Arc<dyn Backend>,
 }
 
 pub enum SessionQuery {
@@ -23,19 +34,36 @@ pub enum SessionQuery {
 }
 
 impl SessionManager {
-    pub fn new(songbird: Arc<Songbird>, storage: Storage) -> Self {
+    pub fn new(discord: Arc<dyn Discord>, storage: Storage) -> Self {
         Self {
-            songbird,
+            discord,
             storage,
 
             sessions: Arc::new(Mutex::new(HashMap::new())),
             owners: Arc::new(Mutex::new(HashMap::new())),
+            leases: Arc::new(Mutex::new(HashMap::new())),
+
+            cache: None,
+            backend: Arc::new(Spotify),
         }
     }
 
+    /// Have all sessions store the audio they download in, and play it back from, a shared cache
+    pub fn with_audio_cache(mut self, cache: AudioCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Have all sessions play a tone and scripted events instead of logging in to Spotify.
+    ///
+    /// This allows sessions to be tested without a Spotify account or network access.
+    pub fn with_synthetic_backend(mut self, options: SyntheticOptions) -> Self {
+        self.backend = Arc::new(Synthetic::new(options));
+        self
+    }
+
     pub async fn create_session(
         &self,
-        context: &serenity::all::Context,
         guild_id: GuildId,
         voice_channel_id: ChannelId,
         text_channel_id: ChannelId,
@@ -43,7 +71,6 @@ impl SessionManager {
     ) -> Result<SessionHandle> {
         let handle = Session::create(
             self.clone(),
-            context,
             guild_id,
             voice_channel_id,
             text_channel_id,
@@ -63,6 +90,24 @@ impl SessionManager {
         Ok(handle)
     }
 
+    /// Rejoin the voice channel of a session that was saved before the bot restarted, and continue playing
+    pub async fn restore_session(&self, saved: SavedSession) -> Result<SessionHandle> {
+        let handle = self
+            .create_session(
+                GuildId::new(saved.guild_id),
+                ChannelId::new(saved.voice_channel_id),
+                ChannelId::new(saved.text_channel_id),
+                UserId::new(saved.owner_id),
+            )
+            .await?;
+
+        if let Err(why) = handle.restore(saved).await {
+            return Err(Error::Other(why.to_string()));
+        }
+
+        Ok(handle)
+    }
+
     pub fn get_session(&self, query: SessionQuery) -> Option<SessionHandle> {
         match query {
             SessionQuery::Guild(guild) => self
@@ -91,6 +136,39 @@ impl SessionManager {
         };
     }
 
+    /// Reserve a linked Spotify account that isn't used by any other session
+    pub(crate) async fn lease_account(&self, guild_id: GuildId) -> Result<String> {
+        // The synthetic backend doesn't use an account at all
+        if !self.backend.uses_accounts() {
+            return Ok(String::from("synthetic"));
+        }
+
+        let accounts = self.storage.list_spotify_accounts().await?;
+        if accounts.is_empty() {
+            return Err(Error::NoAccountLinked);
+        }
+
+        let mut leases = self.leases.lock().expect("mutex poisoned");
+        let account = accounts
+            .into_iter()
+            .find(|account| !leases.contains_key(account))
+            .ok_or(Error::AllAccountsBusy)?;
+
+        leases.insert(account.clone(), guild_id);
+
+        Ok(account)
+    }
+
+    /// Hand a leased Spotify account back, so that other sessions can use it
+    pub(crate) fn release_account(&self, account: &str) {
+        self.leases.lock().expect("mutex poisoned").remove(account);
+    }
+
+    /// Retrieve which guild is using which Spotify account
+    pub fn get_leases(&self) -> HashMap<String, GuildId> {
+        self.leases.lock().expect("mutex poisoned").clone()
+    }
+
     pub fn get_all_sessions(&self) -> Vec<SessionHandle> {
         self.sessions
             .lock()
@@ -115,9 +193,33 @@ impl SessionManager {
         self.sessions.lock().expect("mutex poisoned").clear();
     }
 
-    pub fn songbird(&self) -> Arc<Songbird> {
-        self.songbird.clone()
-    }    pub fn storage(&self) -> Storage {
+    /// Disconnects all active sessions like [`SessionManager::shutdown_all`], but keeps them saved.
+    ///
+    /// The saved sessions are restored the next time the bot starts.
+    pub async fn suspend_all(&self) {
+        let sessions = self.get_all_sessions();
+
+        for session in sessions {
+            session.suspend().await;
+        }
+
+        self.owners.lock().expect("mutex poisoned").clear();
+        self.sessions.lock().expect("mutex poisoned").clear();
+    }
+
+    pub fn discord(&self) -> Arc<dyn Discord> {
+        self.discord.clone()
+    }
+
+    pub fn storage(&self) -> Storage {
         self.storage.clone()
     }
+
+    pub fn audio_cache(&self) -> Option<AudioCache> {
+        self.cache.clone()
+    }
+
+    pub(crate) fn backend(&self) -> Arc<dyn Backend> {
+        self.backend.clone()
+    }
 }
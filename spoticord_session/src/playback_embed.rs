@@ -6,16 +6,29 @@ use serenity::{
         ButtonStyle, CommandInteraction, ComponentInteraction, ComponentInteractionCollector,
         Context, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter,
         CreateInteractionResponse, CreateInteractionResponseFollowup,
-        CreateInteractionResponseMessage, CreateMessage, EditMessage, Message, User,
+        CreateInteractionResponseMessage, CreateMessage, EditMessage, Message, User, UserId,
     },
     futures::StreamExt,
 };
-use spoticord_player::{info::PlaybackInfo, PlayerHandle};
-use spoticord_utils::discord::Colors;
+use spoticord_player::{
+    info::{PlaybackInfo, RepeatMode},
+    PlayerHandle,
+};
+use spoticord_storage::Storage;
+use spoticord_utils::discord::{escape, Colors};
 use std::{ops::ControlFlow, time::Duration};
 use tokio::{sync::mpsc, time::Instant};
 
-use crate::{Session, SessionHandle};
+use crate::{queue::GuildQueue, Session, SessionHandle};
+
+/// How much the volume buttons change the volume, in percent
+const VOLUME_STEP: u8 = 10;
+
+/// How far the rewind and fast-forward buttons jump, in milliseconds
+const SEEK_STEP: u32 = 15_000;
+
+/// The amount of upcoming tracks that are shown
+const UP_NEXT: usize = 3;
 
 #[derive(Debug)]
 pub enum Command {
@@ -49,6 +62,7 @@ pub struct PlaybackEmbed {
     id: u64,
     ctx: Context,
     session: SessionHandle,
+    storage: Storage,
     message: Message,
 
     last_update: Instant,
@@ -63,11 +77,10 @@ impl PlaybackEmbed {
     pub async fn create(
         session: &Session,
         handle: SessionHandle,
+        ctx: Context,
         interaction: CommandInteraction,
         update_behavior: UpdateBehavior,
     ) -> Result<Option<PlaybackEmbedHandle>> {
-        let ctx = session.context.clone();
-
         if !session.active {
             respond_not_playing(&ctx, interaction).await?;
 
@@ -90,8 +103,13 @@ impl PlaybackEmbed {
                 &ctx,
                 CreateInteractionResponse::Message(
                     CreateInteractionResponseMessage::new()
-                        .embed(build_embed(&playback_info, &owner))
-                        .components(vec![build_buttons(ctx_id, playback_info.playing())]),
+                        .embed(build_embed(
+                            &playback_info,
+                            &owner,
+                            requester(&session.queue, &playback_info),
+                            session.autoplay,
+                        ))
+                        .components(build_buttons(ctx_id, &playback_info)),
                 ),
             )
             .await?;
@@ -113,6 +131,7 @@ impl PlaybackEmbed {
             id: ctx_id,
             ctx,
             session: handle,
+            storage: session.session_manager.storage(),
             message,
             last_update: Instant::now(),
             update_in: None,
@@ -204,7 +223,7 @@ impl PlaybackEmbed {
             return;
         };
 
-        if press.user.id != owner.id {
+        if press.user.id != owner.id && !self.is_dj(&press).await {
             _ = press
                 .create_followup(
                     &self.ctx,
@@ -212,7 +231,7 @@ impl PlaybackEmbed {
                         .embed(
                             CreateEmbed::new()
                                 .title("Cannot perform action")
-                                .description("Only the host may use the media buttons"),
+                                .description("Only the host or a DJ may use the media buttons"),
                         )
                         .ephemeral(true),
                 )
@@ -231,6 +250,41 @@ impl PlaybackEmbed {
                     player.play().await
                 }
             }
+            Some("rewind") => {
+                player
+                    .seek(playback_info.current_position().saturating_sub(SEEK_STEP))
+                    .await
+            }
+            Some("forward") => {
+                let position = playback_info.current_position() + SEEK_STEP;
+
+                // Seeking past the end of the track is the same as skipping it
+                if position >= playback_info.duration() {
+                    player.next_track().await
+                } else {
+                    player.seek(position).await
+                }
+            }
+            Some("voldown") => {
+                if let Ok(volume) = player.volume().await {
+                    player.set_volume(volume.saturating_sub(VOLUME_STEP)).await;
+                }
+            }
+            Some("volup") => {
+                if let Ok(volume) = player.volume().await {
+                    player.set_volume(volume.saturating_add(VOLUME_STEP)).await;
+                }
+            }
+            Some("shuffle") => player.set_shuffle(!playback_info.shuffle()).await,
+            Some("repeat") => {
+                let repeat = match playback_info.repeat() {
+                    RepeatMode::Off => RepeatMode::Context,
+                    RepeatMode::Context => RepeatMode::Track,
+                    RepeatMode::Track => RepeatMode::Off,
+                };
+
+                player.set_repeat(repeat).await
+            }
 
             _ => {}
         }
@@ -240,6 +294,25 @@ impl PlaybackEmbed {
             .await;
     }
 
+    async fn is_dj(&self, press: &ComponentInteraction) -> bool {
+        let Some(member) = &press.member else {
+            return false;
+        };
+
+        match self
+            .storage
+            .get_guild_settings(self.session.guild().get())
+            .await
+        {
+            Ok(settings) => settings.is_dj(member.roles.iter().map(|role| role.get())),
+            Err(why) => {
+                error!("Failed to load guild settings: {why}");
+
+                false
+            }
+        }
+    }
+
     async fn get_info(&self) -> Result<(PlayerHandle, PlaybackInfo, User)> {
         let player = self.session.player().await?;
         let owner = self.session.owner().await?.to_user(&self.ctx).await?;
@@ -281,6 +354,12 @@ impl PlaybackEmbed {
             }
         };
 
+        let autoplay = self.session.autoplay().await.unwrap_or_default();
+        let requester = match self.session.queue().await {
+            Ok(queue) => requester(&queue, &playback_info),
+            Err(_) => None,
+        };
+
         let should_pin = !force_edit && self.update_behavior.is_pinned();
 
         if should_pin {
@@ -292,8 +371,8 @@ impl PlaybackEmbed {
                 .send_message(
                     &self.ctx,
                     CreateMessage::new()
-                        .embed(build_embed(&playback_info, &owner))
-                        .components(vec![build_buttons(self.id, playback_info.playing())]),
+                        .embed(build_embed(&playback_info, &owner, requester, autoplay))
+                        .components(build_buttons(self.id, &playback_info)),
                 )
                 .await
             {
@@ -309,8 +388,8 @@ impl PlaybackEmbed {
             .edit(
                 &self.ctx,
                 EditMessage::new()
-                    .embed(build_embed(&playback_info, &owner))
-                    .components(vec![build_buttons(self.id, playback_info.playing())]),
+                    .embed(build_embed(&playback_info, &owner, requester, autoplay))
+                    .components(build_buttons(self.id, &playback_info)),
             )
             .await
         {
@@ -383,7 +462,19 @@ fn not_playing_embed() -> CreateEmbed {
         .color(Colors::Error)
 }
 
-fn build_embed(playback_info: &PlaybackInfo, owner: &User) -> CreateEmbed {
+/// Find who queued the current track through the bot, if anyone
+fn requester(queue: &GuildQueue, playback_info: &PlaybackInfo) -> Option<UserId> {
+    let uri = playback_info.track_id().to_uri().ok()?;
+
+    queue.requester(&uri)
+}
+
+fn build_embed(
+    playback_info: &PlaybackInfo,
+    owner: &User,
+    requester: Option<UserId>,
+    autoplay: bool,
+) -> CreateEmbed {
     let mut description = String::new();
 
     description += &format!("## [{}]({})\n", playback_info.name(), playback_info.url());
@@ -412,6 +503,19 @@ fn build_embed(playback_info: &PlaybackInfo, owner: &User) -> CreateEmbed {
         description += &format!("On {show_name}\n");
     }
 
+    if let Some(context) = playback_info.context() {
+        let name = escape(context.name.as_deref().unwrap_or("Unknown"));
+
+        match spotify_url(&context.uri) {
+            Some(url) => description += &format!("Playing from [{name}]({url})\n"),
+            None => description += &format!("Playing from **{name}**\n"),
+        }
+    }
+
+    if let Some(requester) = requester {
+        description += &format!("Requested by <@{requester}>\n");
+    }
+
     description += "\n";
 
     let position = playback_info.current_position();
@@ -438,7 +542,45 @@ fn build_embed(playback_info: &PlaybackInfo, owner: &User) -> CreateEmbed {
         spoticord_utils::time_to_string(playback_info.duration() / 1000)
     );
 
-    CreateEmbed::new()
+    description += &format!("\n:loud_sound: {}%", playback_info.volume());
+
+    if playback_info.shuffle() {
+        description += " • :twisted_rightwards_arrows: Shuffle";
+    }
+
+    match playback_info.repeat() {
+        RepeatMode::Off => {}
+        RepeatMode::Context => description += " • :repeat: Repeat",
+        RepeatMode::Track => description += " • :repeat_one: Repeat track",
+    }
+
+    if autoplay {
+        description += " • :radio: Autoplay";
+    }
+
+    let mut embed = CreateEmbed::new();
+
+    if !playback_info.next_tracks().is_empty() {
+        let next_tracks = playback_info
+            .next_tracks()
+            .iter()
+            .take(UP_NEXT)
+            .enumerate()
+            .map(|(index, track)| {
+                let (name, artists) = (escape(&track.name), escape(&track.artists));
+
+                match spotify_url(&track.uri) {
+                    Some(url) => format!("{}. [{name}]({url}) - {artists}", index + 1),
+                    None => format!("{}. {name} - {artists}", index + 1),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        embed = embed.field("Up next", next_tracks, false);
+    }
+
+    embed
         .author(
             CreateEmbedAuthor::new("Currently Playing")
                 .icon_url("https://spoticord.com/spotify-logo.png"),
@@ -452,7 +594,17 @@ fn build_embed(playback_info: &PlaybackInfo, owner: &User) -> CreateEmbed {
         .color(Colors::Info)
 }
 
-fn build_buttons(id: u64, playing: bool) -> CreateActionRow {
+/// Turn a Spotify URI like `spotify:album:<id>` into a link to the Spotify web player
+fn spotify_url(uri: &str) -> Option<String> {
+    match uri.split(':').collect::<Vec<_>>()[..] {
+        ["spotify", kind, id] => Some(format!("https://open.spotify.com/{kind}/{id}")),
+        _ => None,
+    }
+}
+
+fn build_buttons(id: u64, playback_info: &PlaybackInfo) -> Vec<CreateActionRow> {
+    let playing = playback_info.playing();
+
     let prev_button_id = format!("{id}-prev");
     let next_button_id = format!("{id}-next");
     let pause_button_id = format!("{id}-pause");
@@ -473,5 +625,54 @@ fn build_buttons(id: u64, playing: bool) -> CreateActionRow {
         })
         .label(if playing { "Pause" } else { "Play" });
 
-    CreateActionRow::Buttons(vec![prev_button, pause_button, next_button])
+    let rewind_button = CreateButton::new(format!("{id}-rewind"))
+        .style(ButtonStyle::Secondary)
+        .label("-15s");
+
+    let forward_button = CreateButton::new(format!("{id}-forward"))
+        .style(ButtonStyle::Secondary)
+        .label("+15s");
+
+    let volume_down_button = CreateButton::new(format!("{id}-voldown"))
+        .style(ButtonStyle::Secondary)
+        .label("Volume -");
+
+    let volume_up_button = CreateButton::new(format!("{id}-volup"))
+        .style(ButtonStyle::Secondary)
+        .label("Volume +");
+
+    let shuffle_button = CreateButton::new(format!("{id}-shuffle"))
+        .style(if playback_info.shuffle() {
+            ButtonStyle::Success
+        } else {
+            ButtonStyle::Secondary
+        })
+        .label("Shuffle");
+
+    let repeat_button = CreateButton::new(format!("{id}-repeat"))
+        .style(match playback_info.repeat() {
+            RepeatMode::Off => ButtonStyle::Secondary,
+            _ => ButtonStyle::Success,
+        })
+        .label(match playback_info.repeat() {
+            RepeatMode::Off => "Repeat: Off",
+            RepeatMode::Context => "Repeat: All",
+            RepeatMode::Track => "Repeat: One",
+        });
+
+    vec![
+        CreateActionRow::Buttons(vec![
+            prev_button,
+            rewind_button,
+            pause_button,
+            forward_button,
+            next_button,
+        ]),
+        CreateActionRow::Buttons(vec![
+            volume_down_button,
+            volume_up_button,
+            shuffle_button,
+            repeat_button,
+        ]),
+    ]
 }
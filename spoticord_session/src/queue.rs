@@ -0,0 +1,412 @@
+use std::collections::{HashMap, VecDeque};
+
+use rand::seq::SliceRandom;
+use serenity::all::UserId;
+use spoticord_storage::GuildSettings;
+use thiserror::Error;
+
+/// A track or episode that a member of the guild asked for
+#[derive(Debug, Clone)]
+pub struct QueueEntry {
+    pub uri: String,
+    pub name: String,
+    /// The artists of a track, or the show of an episode
+    pub artists: String,
+    /// The duration in milliseconds
+    pub duration: u32,
+    pub requester: UserId,
+}
+
+/// The per-member limit that a track would exceed if it were queued
+#[derive(Error, Debug, Clone, Copy)]
+pub enum QueueLimit {
+    /// The member already has the maximum amount of tracks queued
+    #[error("Members may only have {0} tracks queued at once")]
+    Tracks(u32),
+
+    /// The track would bring the member over the maximum queued duration, in seconds
+    #[error("Members may only have {} minutes of music queued at once", .0 / 60)]
+    Duration(u64),
+}
+
+/// The reason an entry could not be removed from the queue
+#[derive(Error, Debug, Clone, Copy)]
+pub enum RemoveError {
+    #[error("There is no track at position {} in the queue", .0 + 1)]
+    NotFound(usize),
+
+    /// The entry was queued by someone else than the member that is removing it
+    #[error("Only the requester, the host or a DJ may remove this track")]
+    NotRequester,
+}
+
+/// The tracks that members of a guild have queued, which are handed to the player one at a time.
+///
+/// Entries are only handed to Spotify once the previous entry has started playing, so that the
+/// rest of the queue can still be reordered and pruned.
+#[derive(Debug, Clone, Default)]
+pub struct GuildQueue {
+    /// The entry that is playing right now, if it came from this queue
+    current: Option<QueueEntry>,
+    /// The entry that has been handed to Spotify, but hasn't started playing yet
+    next: Option<QueueEntry>,
+    entries: VecDeque<QueueEntry>,
+}
+
+impl GuildQueue {
+    pub fn current(&self) -> Option<&QueueEntry> {
+        self.current.as_ref()
+    }
+
+    pub fn next(&self) -> Option<&QueueEntry> {
+        self.next.as_ref()
+    }
+
+    /// The entries that are waiting to be played, which can still be changed
+    pub fn entries(&self) -> &VecDeque<QueueEntry> {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Find who asked for a track, if it was queued through the bot
+    pub fn requester(&self, uri: &str) -> Option<UserId> {
+        self.current
+            .as_ref()
+            .filter(|entry| entry.uri == uri)
+            .map(|entry| entry.requester)
+    }
+
+    /// Check whether the requester of an entry may queue it without exceeding the limits of the guild
+    pub fn check_limits(
+        &self,
+        entry: &QueueEntry,
+        settings: &GuildSettings,
+    ) -> Result<(), QueueLimit> {
+        let pending = self
+            .next
+            .iter()
+            .chain(&self.entries)
+            .filter(|pending| pending.requester == entry.requester);
+
+        let (count, duration) = pending.fold((0u32, 0u64), |(count, duration), pending| {
+            (count + 1, duration + pending.duration as u64)
+        });
+
+        if let Some(max_tracks) = settings.max_queued_tracks {
+            if count >= max_tracks {
+                return Err(QueueLimit::Tracks(max_tracks));
+            }
+        }
+
+        if let Some(max_duration) = settings.max_queued_duration {
+            if (duration + entry.duration as u64) / 1000 > max_duration {
+                return Err(QueueLimit::Duration(max_duration));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add an entry to the queue, and return its zero-based index.
+    ///
+    /// When `fair` is set, the entry is placed in the first round in which its requester doesn't have a track yet,
+    /// so that the tracks of different requesters take turns instead of playing in the order they were queued.
+    pub fn push(&mut self, entry: QueueEntry, fair: bool) -> usize {
+        if !fair {
+            self.entries.push_back(entry);
+            return self.entries.len() - 1;
+        }
+
+        // The entry that has been handed to Spotify already counts as the first round
+        let mut rounds: HashMap<UserId, usize> = HashMap::new();
+        if let Some(next) = &self.next {
+            rounds.insert(next.requester, 1);
+        }
+
+        let round = rounds.get(&entry.requester).copied().unwrap_or(0)
+            + self
+                .entries
+                .iter()
+                .filter(|pending| pending.requester == entry.requester)
+                .count();
+
+        // Insert before the first entry that belongs to a later round than the new entry
+        let index = self
+            .entries
+            .iter()
+            .position(|pending| {
+                let pending_round = rounds.entry(pending.requester).or_default();
+                *pending_round += 1;
+
+                *pending_round > round + 1
+            })
+            .unwrap_or(self.entries.len());
+
+        self.entries.insert(index, entry);
+
+        index
+    }
+
+    /// Remove the entry at a zero-based index.
+    ///
+    /// When a `requester` is given, the entry is only removed if that member queued it.
+    pub fn remove(
+        &mut self,
+        index: usize,
+        requester: Option<UserId>,
+    ) -> Result<QueueEntry, RemoveError> {
+        let entry = self
+            .entries
+            .get(index)
+            .ok_or(RemoveError::NotFound(index))?;
+
+        if requester.is_some_and(|requester| entry.requester != requester) {
+            return Err(RemoveError::NotRequester);
+        }
+
+        self.entries
+            .remove(index)
+            .ok_or(RemoveError::NotFound(index))
+    }
+
+    /// Move the entry at a zero-based index to another index, and return it
+    pub fn move_entry(&mut self, from: usize, to: usize) -> Option<&QueueEntry> {
+        if to >= self.entries.len() {
+            return None;
+        }
+
+        let entry = self.entries.remove(from)?;
+        self.entries.insert(to, entry);
+
+        self.entries.get(to)
+    }
+
+    pub fn shuffle(&mut self) {
+        self.entries
+            .make_contiguous()
+            .shuffle(&mut rand::thread_rng());
+    }
+
+    /// Remove all entries that haven't been handed to Spotify yet, and return how many there were
+    pub fn clear(&mut self) -> usize {
+        let count = self.entries.len();
+        self.entries.clear();
+
+        count
+    }
+
+    /// Take the entry that should be handed to Spotify, unless the previous one is still waiting to be played
+    pub(crate) fn take_next(&mut self) -> Option<QueueEntry> {
+        if self.next.is_some() {
+            return None;
+        }
+
+        let entry = self.entries.pop_front()?;
+        self.next = Some(entry.clone());
+
+        Some(entry)
+    }
+
+    /// Attribute the track that is about to start playing to an entry that skipped the queue
+    pub(crate) fn play_now(&mut self, entry: QueueEntry) {
+        self.current = Some(entry);
+    }
+
+    /// Forget the entry that was handed to Spotify, for when Spotify didn't accept it
+    pub(crate) fn discard_next(&mut self) {
+        self.next = None;
+    }
+
+    /// Put the entry that was handed to Spotify back in front of the queue, for when the player has been replaced
+    pub(crate) fn requeue_next(&mut self) {
+        if let Some(entry) = self.next.take() {
+            self.entries.push_front(entry);
+        }
+    }
+
+    /// Keep track of which entry is playing after the player moved on to another track.
+    ///
+    /// The entry that was handed to Spotify is forgotten when another track than the one that skipped the queue
+    /// starts instead, as Spotify has then dropped or skipped past it, and the rest of the queue would never be
+    /// handed over otherwise.
+    pub(crate) fn track_changed(&mut self, uri: &str) {
+        if self.next.as_ref().is_some_and(|entry| entry.uri == uri) {
+            self.current = self.next.take();
+            return;
+        }
+
+        if self.current.as_ref().is_some_and(|entry| entry.uri == uri) {
+            return;
+        }
+
+        self.current = None;
+        self.next = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(uri: &str, requester: u64) -> QueueEntry {
+        QueueEntry {
+            uri: uri.to_string(),
+            name: uri.to_string(),
+            artists: String::from("Artist"),
+            duration: 180_000,
+            requester: UserId::new(requester),
+        }
+    }
+
+    fn uris(queue: &GuildQueue) -> Vec<&str> {
+        queue
+            .entries()
+            .iter()
+            .map(|entry| entry.uri.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn push_appends_without_fair_flag() {
+        let mut queue = GuildQueue::default();
+
+        assert_eq!(queue.push(entry("a1", 1), false), 0);
+        assert_eq!(queue.push(entry("a2", 1), false), 1);
+        assert_eq!(queue.push(entry("b1", 2), false), 2);
+
+        assert_eq!(uris(&queue), ["a1", "a2", "b1"]);
+    }
+
+    #[test]
+    fn push_takes_turns_with_fair_flag() {
+        let mut queue = GuildQueue::default();
+
+        queue.push(entry("a1", 1), true);
+        queue.push(entry("a2", 1), true);
+        queue.push(entry("a3", 1), true);
+        assert_eq!(queue.push(entry("b1", 2), true), 1);
+        assert_eq!(queue.push(entry("b2", 2), true), 3);
+        assert_eq!(queue.push(entry("c1", 3), true), 2);
+
+        assert_eq!(uris(&queue), ["a1", "b1", "c1", "a2", "b2", "a3"]);
+    }
+
+    #[test]
+    fn push_counts_next_entry_as_first_round() {
+        let mut queue = GuildQueue::default();
+
+        queue.push(entry("a1", 1), true);
+        queue.take_next();
+        queue.push(entry("a2", 1), true);
+
+        assert_eq!(queue.push(entry("b1", 2), true), 0);
+        assert_eq!(uris(&queue), ["b1", "a2"]);
+    }
+
+    #[test]
+    fn move_entry_reorders_queue() {
+        let mut queue = GuildQueue::default();
+        for uri in ["a", "b", "c", "d"] {
+            queue.push(entry(uri, 1), false);
+        }
+
+        assert_eq!(
+            queue.move_entry(0, 2).map(|entry| entry.uri.as_str()),
+            Some("a")
+        );
+        assert_eq!(uris(&queue), ["b", "c", "a", "d"]);
+
+        assert_eq!(
+            queue.move_entry(3, 0).map(|entry| entry.uri.as_str()),
+            Some("d")
+        );
+        assert_eq!(uris(&queue), ["d", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn move_entry_rejects_out_of_range_indices() {
+        let mut queue = GuildQueue::default();
+        queue.push(entry("a", 1), false);
+        queue.push(entry("b", 1), false);
+
+        assert!(queue.move_entry(0, 2).is_none());
+        assert!(queue.move_entry(2, 0).is_none());
+        assert_eq!(uris(&queue), ["a", "b"]);
+    }
+
+    #[test]
+    fn remove_checks_requester() {
+        let mut queue = GuildQueue::default();
+        queue.push(entry("a", 1), false);
+
+        assert!(matches!(
+            queue.remove(0, Some(UserId::new(2))),
+            Err(RemoveError::NotRequester)
+        ));
+        assert!(matches!(
+            queue.remove(1, None),
+            Err(RemoveError::NotFound(1))
+        ));
+        assert!(queue.remove(0, Some(UserId::new(1))).is_ok());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn track_changed_promotes_next_entry() {
+        let mut queue = GuildQueue::default();
+        queue.push(entry("a", 1), false);
+        queue.push(entry("b", 2), false);
+
+        queue.take_next();
+        assert!(queue.take_next().is_none());
+
+        queue.track_changed("a");
+        assert_eq!(queue.current().map(|entry| entry.uri.as_str()), Some("a"));
+        assert_eq!(queue.requester("a"), Some(UserId::new(1)));
+        assert!(queue.next().is_none());
+        assert_eq!(
+            queue.take_next().map(|entry| entry.uri),
+            Some(String::from("b"))
+        );
+    }
+
+    #[test]
+    fn track_changed_forgets_skipped_next_entry() {
+        let mut queue = GuildQueue::default();
+        queue.push(entry("a", 1), false);
+        queue.push(entry("b", 1), false);
+
+        queue.take_next();
+        queue.track_changed("spotify:track:other");
+
+        assert!(queue.current().is_none());
+        assert!(queue.next().is_none());
+        assert_eq!(
+            queue.take_next().map(|entry| entry.uri),
+            Some(String::from("b"))
+        );
+    }
+
+    #[test]
+    fn track_changed_keeps_next_entry_behind_play_now() {
+        let mut queue = GuildQueue::default();
+        queue.push(entry("a", 1), false);
+
+        queue.take_next();
+        queue.play_now(entry("now", 2));
+        queue.track_changed("now");
+
+        assert_eq!(queue.current().map(|entry| entry.uri.as_str()), Some("now"));
+        assert_eq!(queue.next().map(|entry| entry.uri.as_str()), Some("a"));
+
+        queue.track_changed("a");
+        assert_eq!(queue.current().map(|entry| entry.uri.as_str()), Some("a"));
+    }
+}
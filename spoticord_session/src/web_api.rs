@@ -0,0 +1,334 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use rspotify::{
+    model::{
+        AlbumId, ArtistId, EpisodeId, Offset, PlayContextId, PlayableId, PlayableItem, PlaylistId,
+        ShowId, TrackId, Type,
+    },
+    prelude::*,
+    AuthCodeSpotify, Token,
+};
+use spoticord_player::info::{PlaybackContext, UpcomingTrack};
+use spoticord_storage::Storage;
+
+/// The amount of upcoming tracks that are retrieved at once, which last until they have all been played
+const UPCOMING_TRACKS: usize = 20;
+
+/// The amount of tracks that autoplay adds to the queue at once
+const AUTOPLAY_TRACKS: usize = 5;
+
+/// The amount of most recently played tracks that autoplay seeds recommendations with, Spotify allows at most 5
+const AUTOPLAY_SEEDS: usize = 5;
+
+/// The amount of most recently played tracks, including the current one, that autoplay never picks from the history
+const RECENT_TRACKS: usize = 5;
+
+/// Create a Spotify Web API client for one of the accounts that are linked to the bot
+pub async fn client(storage: &Storage, account: &str) -> Result<AuthCodeSpotify> {
+    let access_token = storage
+        .get_spotify_token(account)
+        .await?
+        .ok_or_else(|| anyhow!("Spotify account {account} is no longer linked"))?;
+
+    Ok(spoticord_config::get_spotify(Token {
+        access_token,
+        ..Default::default()
+    }))
+}
+
+/// Retrieve the context and the upcoming tracks of the playback on a device.
+///
+/// The name of the context is only looked up if it differs from the `known` context.
+/// Returns `None` if the device isn't the one that is currently playing on the account.
+pub async fn upcoming(
+    spotify: &AuthCodeSpotify,
+    device_id: &str,
+    known: Option<&PlaybackContext>,
+) -> Result<Option<(Option<PlaybackContext>, Vec<UpcomingTrack>)>> {
+    let Some(playback) = spotify.current_playback(None, None::<Vec<_>>).await? else {
+        return Ok(None);
+    };
+
+    if playback.device.id.as_deref() != Some(device_id) {
+        return Ok(None);
+    }
+
+    let context = match playback.context {
+        Some(context) => match known {
+            Some(known) if known.uri == context.uri => Some(known.clone()),
+            _ => Some(PlaybackContext {
+                name: context_name(spotify, &context.uri, context._type).await,
+                uri: context.uri,
+            }),
+        },
+        None => None,
+    };
+
+    let next_tracks = spotify
+        .current_user_queue()
+        .await?
+        .queue
+        .into_iter()
+        .filter_map(upcoming_track)
+        .take(UPCOMING_TRACKS)
+        .collect();
+
+    Ok(Some((context, next_tracks)))
+}
+
+/// Add tracks that fit the recently played tracks of a guild to the queue of a device.
+///
+/// Uses Spotify's recommendations if they are available, and replays older tracks from the history otherwise.
+/// Returns the amount of tracks that have been queued.
+pub async fn autoplay(
+    spotify: &AuthCodeSpotify,
+    history: &[String],
+    device_id: &str,
+) -> Result<usize> {
+    let tracks = match recommendations(spotify, history).await {
+        Ok(tracks) if !tracks.is_empty() => tracks,
+        Ok(_) => from_history(history),
+        Err(why) => {
+            debug!("Recommendations unavailable, autoplaying from history instead: {why}");
+
+            from_history(history)
+        }
+    };
+
+    for track in &tracks {
+        spotify
+            .add_item_to_queue(PlayableId::Track(track.as_ref()), Some(device_id))
+            .await?;
+    }
+
+    Ok(tracks.len())
+}
+
+/// Start playing a track or episode on a device at a position, within the context it was played from if possible
+pub async fn restore(
+    spotify: &AuthCodeSpotify,
+    device_id: &str,
+    context: Option<&str>,
+    track: &str,
+    position: u32,
+) -> Result<()> {
+    let position = Some(chrono::Duration::milliseconds(position as i64));
+
+    if let Some(context) = context.and_then(play_context_id) {
+        let result = spotify
+            .start_context_playback(
+                context,
+                Some(device_id),
+                Some(Offset::Uri(track.to_string())),
+                position,
+            )
+            .await;
+
+        match result {
+            Ok(()) => return Ok(()),
+            Err(why) => debug!("Failed to restore context, playing the track by itself: {why}"),
+        }
+    }
+
+    spotify
+        .start_uris_playback([playable_id(track)?], Some(device_id), None, position)
+        .await?;
+
+    Ok(())
+}
+
+/// Hand a track or episode to a device, which either starts playing it right away or adds it to the Spotify queue
+pub async fn enqueue(
+    spotify: &AuthCodeSpotify,
+    device_id: &str,
+    uri: &str,
+    play_now: bool,
+) -> Result<()> {
+    let id = playable_id(uri)?;
+
+    if play_now {
+        spotify
+            .start_uris_playback([id], Some(device_id), None, None)
+            .await?;
+    } else {
+        spotify.add_item_to_queue(id, Some(device_id)).await?;
+    }
+
+    Ok(())
+}
+
+/// Start playing an album, playlist, artist or show on a device, and return its name if it can be found
+pub async fn play_context(
+    spotify: &AuthCodeSpotify,
+    device_id: &str,
+    uri: &str,
+) -> Result<Option<String>> {
+    let id = play_context_id(uri)
+        .ok_or_else(|| anyhow!("{uri} is not an album, playlist, artist or show"))?;
+    let kind = id._type();
+
+    spotify
+        .start_context_playback(id, Some(device_id), None, None)
+        .await?;
+
+    Ok(context_name(spotify, uri, kind).await)
+}
+
+fn playable_id(uri: &str) -> Result<PlayableId<'_>> {
+    match TrackId::from_uri(uri) {
+        Ok(id) => Ok(PlayableId::Track(id)),
+        Err(_) => Ok(PlayableId::Episode(EpisodeId::from_uri(uri)?)),
+    }
+}
+
+fn play_context_id(uri: &str) -> Option<PlayContextId<'_>> {
+    PlaylistId::from_uri(uri)
+        .map(PlayContextId::Playlist)
+        .or_else(|_| AlbumId::from_uri(uri).map(PlayContextId::Album))
+        .or_else(|_| ArtistId::from_uri(uri).map(PlayContextId::Artist))
+        .or_else(|_| ShowId::from_uri(uri).map(PlayContextId::Show))
+        .ok()
+}
+
+async fn recommendations(
+    spotify: &AuthCodeSpotify,
+    history: &[String],
+) -> Result<Vec<TrackId<'static>>> {
+    let seeds = history
+        .iter()
+        .rev()
+        .filter_map(|uri| TrackId::from_uri(uri).ok())
+        .take(AUTOPLAY_SEEDS)
+        .collect::<Vec<_>>();
+
+    if seeds.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let recommendations = spotify
+        .recommendations(
+            [],
+            None::<Vec<ArtistId>>,
+            None::<Vec<&str>>,
+            Some(seeds),
+            None,
+            Some((AUTOPLAY_TRACKS * 2) as u32),
+        )
+        .await?;
+
+    Ok(recommendations
+        .tracks
+        .into_iter()
+        .filter_map(|track| track.id)
+        .filter(|id| !history.contains(&id.uri()))
+        .take(AUTOPLAY_TRACKS)
+        .collect())
+}
+
+/// Pick tracks from the history that haven't been played for a while.
+///
+/// The current track and the ones right before it are never picked, even if that leaves nothing to pick.
+fn from_history(history: &[String]) -> Vec<TrackId<'static>> {
+    let skip = history
+        .len()
+        .saturating_sub(AUTOPLAY_TRACKS)
+        .min(AUTOPLAY_TRACKS * 2)
+        .max(RECENT_TRACKS);
+
+    history
+        .iter()
+        .rev()
+        .skip(skip)
+        .filter_map(|uri| TrackId::from_uri(uri).ok())
+        .map(TrackId::into_static)
+        .take(AUTOPLAY_TRACKS)
+        .collect()
+}
+
+async fn context_name(spotify: &AuthCodeSpotify, uri: &str, kind: Type) -> Option<String> {
+    match kind {
+        Type::Playlist => {
+            let id = PlaylistId::from_uri(uri).ok()?;
+            Some(spotify.playlist(id, None, None).await.ok()?.name)
+        }
+        Type::Album => {
+            let id = AlbumId::from_uri(uri).ok()?;
+            Some(spotify.album(id, None).await.ok()?.name)
+        }
+        Type::Artist => {
+            let id = ArtistId::from_uri(uri).ok()?;
+            Some(spotify.artist(id).await.ok()?.name)
+        }
+        Type::Show => {
+            let id = ShowId::from_uri(uri).ok()?;
+            Some(spotify.get_a_show(id, None).await.ok()?.name)
+        }
+        Type::Collection => Some("Liked Songs".into()),
+        _ => None,
+    }
+}
+
+fn upcoming_track(item: PlayableItem) -> Option<UpcomingTrack> {
+    match item {
+        PlayableItem::Track(track) => Some(UpcomingTrack {
+            uri: track.id?.uri(),
+            name: track.name,
+            artists: track
+                .artists
+                .into_iter()
+                .map(|artist| artist.name)
+                .collect::<Vec<_>>()
+                .join(", "),
+        }),
+        PlayableItem::Episode(episode) => Some(UpcomingTrack {
+            uri: episode.id.uri(),
+            name: episode.name,
+            artists: episode.show.name,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(len: usize) -> Vec<String> {
+        (0..len)
+            .map(|index| format!("spotify:track:{index:0>22}"))
+            .collect()
+    }
+
+    fn picked(history: &[String]) -> Vec<String> {
+        from_history(history)
+            .into_iter()
+            .map(|track| track.uri())
+            .collect()
+    }
+
+    #[test]
+    fn from_history_skips_recent_tracks() {
+        let history = history(8);
+
+        assert_eq!(
+            picked(&history),
+            vec![history[2].clone(), history[1].clone(), history[0].clone()]
+        );
+    }
+
+    #[test]
+    fn from_history_short() {
+        assert!(picked(&history(RECENT_TRACKS)).is_empty());
+        assert!(picked(&history(1)).is_empty());
+        assert!(picked(&[]).is_empty());
+    }
+
+    #[test]
+    fn from_history_long() {
+        let history = history(50);
+        let picked = picked(&history);
+
+        assert_eq!(picked.len(), AUTOPLAY_TRACKS);
+        assert_eq!(picked[0], history[39]);
+        assert!(picked.iter().all(|uri| !history[45..].contains(uri)));
+    }
+}
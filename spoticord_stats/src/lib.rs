@@ -13,4 +13,19 @@ impl StatsManager {
         info!("Active guild count: {}", count);
         Ok(())
     }
+
+    /// Report how many tracks were played from the audio cache, and how many had to be downloaded
+    pub fn set_cache_stats(&mut self, hits: u64, misses: u64) -> Result<(), ()> {
+        let total = hits + misses;
+        if total > 0 {
+            info!(
+                "Audio cache hit rate: {:.1}% ({} hits, {} misses)",
+                hits as f64 / total as f64 * 100.0,
+                hits,
+                misses
+            );
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,110 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+/// The length of the nonce that is prepended to every encrypted value
+const NONCE_SIZE: usize = 12;
+
+fn cipher() -> Aes256Gcm {
+    Aes256Gcm::new(&Sha256::digest(spoticord_config::credentials_key()))
+}
+
+/// Encrypt a value and encode it as base64, with the nonce prepended to it
+pub fn encrypt(plaintext: &[u8]) -> Result<String> {
+    encrypt_with(&cipher(), plaintext)
+}
+
+/// Decrypt a value that was created with [`encrypt`]
+pub fn decrypt(encoded: &str) -> Result<Vec<u8>> {
+    decrypt_with(&cipher(), encoded)
+}
+
+fn encrypt_with(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow!("Failed to encrypt value"))?;
+
+    let mut data = nonce.to_vec();
+    data.extend(ciphertext);
+
+    Ok(STANDARD.encode(data))
+}
+
+fn decrypt_with(cipher: &Aes256Gcm, encoded: &str) -> Result<Vec<u8>> {
+    let data = STANDARD
+        .decode(encoded)
+        .context("Encrypted value is not valid base64")?;
+
+    if data.len() < NONCE_SIZE {
+        bail!("Encrypted value is too short");
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Failed to decrypt value, the credentials key may have changed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(key: &str) -> Aes256Gcm {
+        Aes256Gcm::new(&Sha256::digest(key))
+    }
+
+    #[test]
+    fn roundtrip() {
+        let cipher = cipher("secret");
+
+        for plaintext in [&b""[..], b"x", b"reusable credentials \x00\xff"] {
+            let encrypted = encrypt_with(&cipher, plaintext).unwrap();
+            assert_eq!(decrypt_with(&cipher, &encrypted).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn nonce_is_random() {
+        let cipher = cipher("secret");
+
+        let first = encrypt_with(&cipher, b"credentials").unwrap();
+        let second = encrypt_with(&cipher, b"credentials").unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(decrypt_with(&cipher, &second).unwrap(), b"credentials");
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let encrypted = encrypt_with(&cipher("secret"), b"credentials").unwrap();
+
+        assert!(decrypt_with(&cipher("another secret"), &encrypted).is_err());
+    }
+
+    #[test]
+    fn tampering_fails() {
+        let cipher = cipher("secret");
+        let encrypted = encrypt_with(&cipher, b"credentials").unwrap();
+
+        let mut data = STANDARD.decode(&encrypted).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+
+        assert!(decrypt_with(&cipher, &STANDARD.encode(data)).is_err());
+    }
+
+    #[test]
+    fn invalid_input_fails() {
+        let cipher = cipher("secret");
+
+        assert!(decrypt_with(&cipher, "not base64!").is_err());
+        assert!(decrypt_with(&cipher, &STANDARD.encode([0u8; NONCE_SIZE - 1])).is_err());
+        assert!(decrypt_with(&cipher, &STANDARD.encode([0u8; NONCE_SIZE])).is_err());
+    }
+}
@@ -1,15 +1,40 @@
+mod crypto;
+
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Utc};
-use rspotify::{clients::BaseClient, Token};
+use rspotify::{
+    clients::{BaseClient, OAuthClient},
+    Token,
+};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use tokio::fs;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    fs,
+    sync::{Mutex as AsyncMutex, OwnedMutexGuard},
+};
+
+/// The amount of recently played tracks that are remembered per guild
+const HISTORY_LENGTH: usize = 50;
+
+/// The ID under which the account that was linked before accounts were pooled is stored
+pub const LEGACY_ACCOUNT: &str = "default";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpotifyCredentials {
     pub access_token: String,
     pub refresh_token: String,
     pub expires_at: DateTime<Utc>,
+
+    /// Encrypted [`ReusableCredentials`], see [`Storage::get_reusable_credentials`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reusable_credentials: Option<String>,
+    /// How the bot logged in to Spotify the last time it connected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_login: Option<LoginMethod>,
 }
 
 impl SpotifyCredentials {
@@ -18,6 +43,19 @@ impl SpotifyCredentials {
             access_token,
             refresh_token,
             expires_at,
+
+            reusable_credentials: None,
+            last_login: None,
+        }
+    }
+
+    /// The token to create a Spotify Web API client with, which can refresh itself once it expires
+    pub fn token(&self) -> Token {
+        Token {
+            access_token: self.access_token.clone(),
+            refresh_token: Some(self.refresh_token.clone()),
+            expires_at: Some(self.expires_at),
+            ..Default::default()
         }
     }
 
@@ -31,12 +69,7 @@ impl SpotifyCredentials {
             return Ok(false);
         }
 
-        let spotify = spoticord_config::get_spotify(Token {
-            access_token: self.access_token.clone(),
-            refresh_token: Some(self.refresh_token.clone()),
-            expires_at: Some(self.expires_at),
-            ..Default::default()
-        });
+        let spotify = spoticord_config::get_spotify(self.token());
 
         let new_token = spotify
             .refetch_token()
@@ -56,28 +89,271 @@ impl SpotifyCredentials {
     }
 }
 
+/// Credentials handed out by Spotify after logging in, which can be used to log in again
+/// without requesting a new access token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReusableCredentials {
+    pub username: String,
+    pub auth_data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginMethod {
+    /// Logged in with the stored reusable credentials
+    ReusableCredentials,
+    /// Logged in with an OAuth access token
+    AccessToken,
+}
+
+/// Which loudness metadata is used to even out the volume between tracks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    #[default]
+    Off,
+    Track,
+    Album,
+}
+
+/// The equalizer preset that is applied to the audio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Equalizer {
+    #[default]
+    Flat,
+    BassBoost,
+    Vocal,
+}
+
+/// Settings that are configured per Discord server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    /// The length of the crossfade between tracks in seconds, 0 disables crossfading
+    pub crossfade: u64,
+
+    pub equalizer: Equalizer,
+
+    pub normalization: Normalization,
+    /// The loudness in LUFS that normalization aims for
+    pub target_loudness: f64,
+
+    /// The last volume that was used in this guild, in percent
+    pub volume: u8,
+    /// The volume in percent that the bot always starts at, instead of the last used volume
+    pub default_volume: Option<u8>,
+
+    /// How long in seconds the bot stays in a call without playing, overrides the timeout of the deployment
+    pub idle_timeout: Option<u64>,
+    /// The channel that the bot posts its announcements to, instead of the channel it was summoned from
+    pub announce_channel: Option<u64>,
+    /// Members with this role may control the playback, just like the host
+    pub dj_role: Option<u64>,
+
+    /// The streaming bitrate in kbps, overrides the bitrate of the deployment
+    pub bitrate: Option<u16>,
+
+    /// Skip to the next track when a track can't be played
+    pub skip_unavailable: bool,
+
+    /// Keep playing similar tracks once the queue runs out
+    pub autoplay: bool,
+
+    /// Allow the lyrics of the current track to be shown
+    pub lyrics: bool,
+
+    /// Interleave queued tracks round-robin by requester, instead of playing them in the order they were queued
+    pub fair_queue: bool,
+    /// The amount of tracks a single member may have queued at once
+    pub max_queued_tracks: Option<u32>,
+    /// The total duration in seconds of the tracks a single member may have queued at once
+    pub max_queued_duration: Option<u64>,
+}
+
+impl GuildSettings {
+    /// Check whether a member with the given roles is a DJ in this guild
+    pub fn is_dj(&self, roles: impl IntoIterator<Item = u64>) -> bool {
+        self.dj_role
+            .is_some_and(|dj_role| roles.into_iter().any(|role| role == dj_role))
+    }
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            crossfade: 0,
+
+            equalizer: Equalizer::Flat,
+
+            normalization: Normalization::Off,
+            // Spotify's own default
+            target_loudness: -14.0,
+
+            volume: 75,
+            default_volume: None,
+
+            idle_timeout: None,
+            announce_channel: None,
+            dj_role: None,
+
+            bitrate: None,
+
+            skip_unavailable: true,
+
+            autoplay: false,
+
+            lyrics: true,
+
+            fair_queue: false,
+            max_queued_tracks: None,
+            max_queued_duration: None,
+        }
+    }
+}
+
+/// A session as it was last seen, so it can be picked up again after the bot restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub guild_id: u64,
+    pub voice_channel_id: u64,
+    pub text_channel_id: u64,
+    pub owner_id: u64,
+
+    /// The URI of the playlist, album or other context that was being played
+    pub context: Option<String>,
+    /// The URI of the track or episode that was being played, if any
+    pub track: Option<String>,
+    /// The playback position within the track in milliseconds
+    pub position: u32,
+    pub playing: bool,
+
+    pub saved_at: DateTime<Utc>,
+}
+
 #[derive(Clone)]
 pub struct Storage {
     data_dir: PathBuf,
+
+    /// Held while guild settings are being updated
+    settings_lock: Arc<AsyncMutex<()>>,
+    /// Held while the credentials file of an account is being updated, per account
+    account_locks: Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>,
 }
 
 impl Storage {
     pub fn new(data_dir: impl Into<PathBuf>) -> Self {
         Self {
             data_dir: data_dir.into(),
+            settings_lock: Arc::new(AsyncMutex::new(())),
+            account_locks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Wait until nothing else is updating the credentials file of an account, and keep others from doing so
+    async fn lock_account(&self, account: &str) -> OwnedMutexGuard<()> {
+        let lock = self
+            .account_locks
+            .lock()
+            .expect("mutex poisoned")
+            .entry(account.to_string())
+            .or_default()
+            .clone();
+
+        lock.lock_owned().await
+    }
+
     pub async fn init(&self) -> Result<()> {
-        fs::create_dir_all(&self.data_dir)
+        fs::create_dir_all(self.accounts_dir())
             .await
-            .context("Failed to create data directory")?;
+            .context("Failed to create accounts directory")?;
+
+        // Before accounts were pooled, the one and only account was stored in the root of the data directory
+        let legacy_path = self.data_dir.join("spotify_credentials.json");
+        if legacy_path.exists() {
+            fs::rename(&legacy_path, self.account_path(LEGACY_ACCOUNT))
+                .await
+                .context("Failed to move legacy credentials file")?;
+        }
+
         Ok(())
     }
 
-    pub async fn get_spotify_credentials(&self) -> Result<Option<SpotifyCredentials>> {
-        let path = self.data_dir.join("spotify_credentials.json");
-        
+    /// Store the legacy account under its Spotify user ID, like accounts that are linked now.
+    ///
+    /// Otherwise linking the same account again would add it a second time. Returns the ID the legacy account
+    /// has been moved to, if there was one. If the account has been linked again already, the newer credentials are kept.
+    pub async fn migrate_legacy_account(&self) -> Result<Option<String>> {
+        let Some(access_token) = self.get_spotify_token(LEGACY_ACCOUNT).await? else {
+            return Ok(None);
+        };
+
+        let spotify = spoticord_config::get_spotify(Token {
+            access_token,
+            ..Default::default()
+        });
+        let user = spotify
+            .me()
+            .await
+            .context("Failed to retrieve Spotify user of legacy account")?;
+        let account = user.id.id().to_string();
+
+        let _legacy_guard = self.lock_account(LEGACY_ACCOUNT).await;
+        let _guard = self.lock_account(&account).await;
+
+        let legacy_path = self.account_path(LEGACY_ACCOUNT);
+        let path = self.account_path(&account);
+
+        if path.exists() {
+            fs::remove_file(legacy_path)
+                .await
+                .context("Failed to remove legacy credentials file")?;
+        } else {
+            fs::rename(legacy_path, path)
+                .await
+                .context("Failed to move legacy credentials file")?;
+        }
+
+        Ok(Some(account))
+    }
+
+    /// Retrieve the IDs of all linked Spotify accounts, in a stable order
+    pub async fn list_spotify_accounts(&self) -> Result<Vec<String>> {
+        let mut accounts = Vec::new();
+
+        let mut entries = match fs::read_dir(self.accounts_dir()).await {
+            Ok(entries) => entries,
+            Err(why) if why.kind() == std::io::ErrorKind::NotFound => return Ok(accounts),
+            Err(why) => return Err(why).context("Failed to read accounts directory"),
+        };
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .context("Failed to read accounts directory")?
+        {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                if let Some(account) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    accounts.push(account.to_string());
+                }
+            }
+        }
+
+        accounts.sort();
+
+        Ok(accounts)
+    }
+
+    pub async fn get_spotify_credentials(
+        &self,
+        account: &str,
+    ) -> Result<Option<SpotifyCredentials>> {
+        let path = self.account_path(account);
+
         if !path.exists() {
             return Ok(None);
         }
@@ -85,36 +361,345 @@ impl Storage {
         let content = fs::read_to_string(path)
             .await
             .context("Failed to read credentials file")?;
-        
-        let credentials: SpotifyCredentials = serde_json::from_str(&content)
-            .context("Failed to parse credentials file")?;
-        
+
+        let credentials: SpotifyCredentials =
+            serde_json::from_str(&content).context("Failed to parse credentials file")?;
+
         Ok(Some(credentials))
     }
 
-    pub async fn save_spotify_credentials(&self, credentials: &SpotifyCredentials) -> Result<()> {
-        let path = self.data_dir.join("spotify_credentials.json");
-        let content = serde_json::to_string_pretty(credentials)
-            .context("Failed to serialize credentials")?;
-        
+    pub async fn save_spotify_credentials(
+        &self,
+        account: &str,
+        credentials: &SpotifyCredentials,
+    ) -> Result<()> {
+        let _guard = self.lock_account(account).await;
+
+        self.write_spotify_credentials(account, credentials).await
+    }
+
+    /// Write the credentials file of an account, the caller must hold the lock of the account
+    async fn write_spotify_credentials(
+        &self,
+        account: &str,
+        credentials: &SpotifyCredentials,
+    ) -> Result<()> {
+        let path = self.account_path(account);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .context("Failed to create accounts directory")?;
+        }
+
+        let content =
+            serde_json::to_string_pretty(credentials).context("Failed to serialize credentials")?;
+
         fs::write(path, content)
             .await
             .context("Failed to write credentials file")?;
-        
+
         Ok(())
     }
 
-    pub async fn get_spotify_token(&self) -> Result<Option<String>> {
-        let mut credentials = match self.get_spotify_credentials().await? {
+    /// Unlink a Spotify account, returns whether the account existed
+    pub async fn remove_spotify_account(&self, account: &str) -> Result<bool> {
+        let _guard = self.lock_account(account).await;
+
+        let path = self.account_path(account);
+        if !path.exists() {
+            return Ok(false);
+        }
+
+        fs::remove_file(path)
+            .await
+            .context("Failed to remove credentials file")?;
+
+        Ok(true)
+    }
+
+    pub async fn get_spotify_token(&self, account: &str) -> Result<Option<String>> {
+        let credentials = self.get_fresh_spotify_credentials(account).await?;
+
+        Ok(credentials.map(|credentials| credentials.access_token))
+    }
+
+    /// Retrieve the credentials of an account, after refreshing its access token if it has expired
+    pub async fn get_fresh_spotify_credentials(
+        &self,
+        account: &str,
+    ) -> Result<Option<SpotifyCredentials>> {
+        let _guard = self.lock_account(account).await;
+
+        let mut credentials = match self.get_spotify_credentials(account).await? {
             Some(creds) => creds,
             None => return Ok(None),
         };
 
         if credentials.refresh_if_needed().await? {
             // Save updated credentials
-            self.save_spotify_credentials(&credentials).await?;
+            self.write_spotify_credentials(account, &credentials)
+                .await?;
+        }
+
+        Ok(Some(credentials))
+    }
+
+    /// Retrieve the reusable credentials that were stored after the last login of an account, if any
+    pub async fn get_reusable_credentials(
+        &self,
+        account: &str,
+    ) -> Result<Option<ReusableCredentials>> {
+        let Some(encrypted) = self
+            .get_spotify_credentials(account)
+            .await?
+            .and_then(|credentials| credentials.reusable_credentials)
+        else {
+            return Ok(None);
+        };
+
+        let decrypted = crypto::decrypt(&encrypted)?;
+        let reusable =
+            serde_json::from_slice(&decrypted).context("Failed to parse reusable credentials")?;
+
+        Ok(Some(reusable))
+    }
+
+    /// Store the reusable credentials from a successful login, along with how the account logged in
+    pub async fn save_reusable_credentials(
+        &self,
+        account: &str,
+        reusable: &ReusableCredentials,
+        login: LoginMethod,
+    ) -> Result<()> {
+        let _guard = self.lock_account(account).await;
+
+        let Some(mut credentials) = self.get_spotify_credentials(account).await? else {
+            // The account was unlinked in the meantime
+            return Ok(());
+        };
+
+        let serialized =
+            serde_json::to_vec(reusable).context("Failed to serialize reusable credentials")?;
+
+        credentials.reusable_credentials = Some(crypto::encrypt(&serialized)?);
+        credentials.last_login = Some(login);
+
+        self.write_spotify_credentials(account, &credentials).await
+    }
+
+    /// Forget the reusable credentials of an account, for when Spotify no longer accepts them
+    pub async fn clear_reusable_credentials(&self, account: &str) -> Result<()> {
+        let _guard = self.lock_account(account).await;
+
+        let Some(mut credentials) = self.get_spotify_credentials(account).await? else {
+            return Ok(());
+        };
+
+        credentials.reusable_credentials = None;
+
+        self.write_spotify_credentials(account, &credentials).await
+    }
+
+    fn accounts_dir(&self) -> PathBuf {
+        self.data_dir.join("accounts")
+    }
+
+    fn account_path(&self, account: &str) -> PathBuf {
+        self.accounts_dir().join(format!("{account}.json"))
+    }
+
+    /// Retrieve the settings of a guild, falling back to the defaults if none have been saved yet
+    pub async fn get_guild_settings(&self, guild_id: u64) -> Result<GuildSettings> {
+        let path = self.guild_settings_path(guild_id);
+        if !path.exists() {
+            return Ok(GuildSettings::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .context("Failed to read guild settings file")?;
+
+        let settings =
+            serde_json::from_str(&content).context("Failed to parse guild settings file")?;
+
+        Ok(settings)
+    }
+
+    /// Change the settings of a guild and return the result.
+    ///
+    /// Updates are applied one at a time, so that concurrent updates of different settings don't undo each other.
+    pub async fn update_guild_settings(
+        &self,
+        guild_id: u64,
+        update: impl FnOnce(&mut GuildSettings),
+    ) -> Result<GuildSettings> {
+        let _guard = self.settings_lock.lock().await;
+
+        let mut settings = self.get_guild_settings(guild_id).await?;
+        update(&mut settings);
+        self.save_guild_settings(guild_id, &settings).await?;
+
+        Ok(settings)
+    }
+
+    async fn save_guild_settings(&self, guild_id: u64, settings: &GuildSettings) -> Result<()> {
+        let path = self.guild_settings_path(guild_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .context("Failed to create guild settings directory")?;
         }
 
-        Ok(Some(credentials.access_token))
+        let content =
+            serde_json::to_string_pretty(settings).context("Failed to serialize guild settings")?;
+
+        fs::write(path, content)
+            .await
+            .context("Failed to write guild settings file")?;
+
+        Ok(())
+    }
+
+    fn guild_settings_path(&self, guild_id: u64) -> PathBuf {
+        self.data_dir
+            .join("guilds")
+            .join(format!("{guild_id}.json"))
+    }
+
+    /// Retrieve the URIs of the tracks that were recently played in a guild, from least to most recent
+    pub async fn get_guild_history(&self, guild_id: u64) -> Result<Vec<String>> {
+        let path = self.guild_history_path(guild_id);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .context("Failed to read guild history file")?;
+
+        let history =
+            serde_json::from_str(&content).context("Failed to parse guild history file")?;
+
+        Ok(history)
+    }
+
+    /// Remember that a track was played in a guild, moving it to the end if it was played before
+    pub async fn add_to_guild_history(&self, guild_id: u64, uri: &str) -> Result<()> {
+        let mut history = self.get_guild_history(guild_id).await?;
+
+        history.retain(|played| played != uri);
+        history.push(uri.to_string());
+
+        if history.len() > HISTORY_LENGTH {
+            history.drain(..history.len() - HISTORY_LENGTH);
+        }
+
+        let path = self.guild_history_path(guild_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .context("Failed to create guild history directory")?;
+        }
+
+        let content =
+            serde_json::to_string(&history).context("Failed to serialize guild history")?;
+
+        fs::write(path, content)
+            .await
+            .context("Failed to write guild history file")?;
+
+        Ok(())
+    }
+
+    fn guild_history_path(&self, guild_id: u64) -> PathBuf {
+        self.data_dir
+            .join("history")
+            .join(format!("{guild_id}.json"))
+    }
+
+    /// Retrieve the IDs of all guilds that have a saved session
+    pub async fn list_saved_sessions(&self) -> Result<Vec<u64>> {
+        let mut guilds = Vec::new();
+
+        let mut entries = match fs::read_dir(self.sessions_dir()).await {
+            Ok(entries) => entries,
+            Err(why) if why.kind() == std::io::ErrorKind::NotFound => return Ok(guilds),
+            Err(why) => return Err(why).context("Failed to read sessions directory"),
+        };
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .context("Failed to read sessions directory")?
+        {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                if let Some(guild_id) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse().ok())
+                {
+                    guilds.push(guild_id);
+                }
+            }
+        }
+
+        guilds.sort_unstable();
+
+        Ok(guilds)
+    }
+
+    pub async fn get_saved_session(&self, guild_id: u64) -> Result<Option<SavedSession>> {
+        let path = self.saved_session_path(guild_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .context("Failed to read saved session file")?;
+
+        let session =
+            serde_json::from_str(&content).context("Failed to parse saved session file")?;
+
+        Ok(Some(session))
+    }
+
+    pub async fn save_session(&self, session: &SavedSession) -> Result<()> {
+        let path = self.saved_session_path(session.guild_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .context("Failed to create sessions directory")?;
+        }
+
+        let content =
+            serde_json::to_string_pretty(session).context("Failed to serialize session")?;
+
+        fs::write(path, content)
+            .await
+            .context("Failed to write saved session file")?;
+
+        Ok(())
+    }
+
+    /// Forget the saved session of a guild, so that it isn't restored after a restart
+    pub async fn remove_saved_session(&self, guild_id: u64) -> Result<()> {
+        match fs::remove_file(self.saved_session_path(guild_id)).await {
+            Ok(()) => Ok(()),
+            Err(why) if why.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(why) => Err(why).context("Failed to remove saved session file"),
+        }
+    }
+
+    fn sessions_dir(&self) -> PathBuf {
+        self.data_dir.join("sessions")
+    }
+
+    fn saved_session_path(&self, guild_id: u64) -> PathBuf {
+        self.sessions_dir().join(format!("{guild_id}.json"))
     }
 }
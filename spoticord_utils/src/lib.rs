@@ -26,3 +26,165 @@ pub fn time_to_string(time: u32) -> String {
         format!("{}s", time)
     }
 }
+
+/// Parse a timestamp like `83`, `1:23` or `1:01:23` into an amount of seconds
+pub fn parse_time(input: &str) -> Option<u32> {
+    let mut parts = input.trim().split(':');
+    let mut seconds = parts.next()?.parse::<u32>().ok()?;
+
+    for (i, part) in parts.enumerate() {
+        // Hours and minutes are the only units above seconds
+        if i >= 2 || part.len() != 2 {
+            return None;
+        }
+
+        let value = part.parse::<u32>().ok().filter(|value| *value < 60)?;
+        seconds = seconds.checked_mul(60)?.checked_add(value)?;
+    }
+
+    Some(seconds)
+}
+
+/// The kinds of Spotify items that can be linked to the bot
+const SPOTIFY_LINK_KINDS: [&str; 6] = ["track", "album", "playlist", "artist", "show", "episode"];
+
+/// Parse an `open.spotify.com` link or a `spotify:` URI into a URI like `spotify:album:<id>`
+///
+/// Returns `None` if the input isn't a link to a track, album, playlist, artist, show or episode.
+pub fn parse_spotify_link(input: &str) -> Option<String> {
+    let input = input.trim();
+
+    let (kind, id) = if let Some(uri) = input.strip_prefix("spotify:") {
+        uri.split_once(':')?
+    } else {
+        let path = input
+            .strip_prefix("https://")
+            .or_else(|| input.strip_prefix("http://"))
+            .unwrap_or(input)
+            .strip_prefix("open.spotify.com/")?;
+
+        // Drop the query, like `?si=...`, and the locale prefix, like `intl-de/`
+        let path = path.split(['?', '#']).next()?;
+        let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+
+        let mut kind = segments.next()?;
+        if kind.starts_with("intl-") {
+            kind = segments.next()?;
+        }
+
+        (kind, segments.next()?)
+    };
+
+    let valid_id = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric());
+    if !SPOTIFY_LINK_KINDS.contains(&kind) || !valid_id {
+        return None;
+    }
+
+    Some(format!("spotify:{kind}:{id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_time_units() {
+        assert_eq!(parse_time("0"), Some(0));
+        assert_eq!(parse_time("83"), Some(83));
+        assert_eq!(parse_time("1:23"), Some(83));
+        assert_eq!(parse_time("0:05"), Some(5));
+        assert_eq!(parse_time("1:01:23"), Some(3683));
+        assert_eq!(parse_time(" 2:00 "), Some(120));
+    }
+
+    #[test]
+    fn parse_time_rejects_invalid_input() {
+        for input in [
+            "",
+            ":",
+            "1:",
+            ":23",
+            "1:2",
+            "1:123",
+            "1:60",
+            "1:00:60",
+            "1:01:01:01",
+            "-5",
+            "1.5",
+            "a:bc",
+        ] {
+            assert_eq!(parse_time(input), None, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn parse_time_rejects_overflow() {
+        assert_eq!(parse_time("4294967295"), Some(u32::MAX));
+        assert_eq!(parse_time("4294967295:00"), None);
+        assert_eq!(parse_time("99999999:00:00"), None);
+    }
+
+    #[test]
+    fn parse_spotify_uris() {
+        for kind in SPOTIFY_LINK_KINDS {
+            let uri = format!("spotify:{kind}:4uLU6hMCjMI75M1A2tKUQC");
+            assert_eq!(parse_spotify_link(&uri), Some(uri.clone()), "{uri:?}");
+        }
+
+        assert_eq!(
+            parse_spotify_link(" spotify:album:1DFixLWuPkv3KT3TnV35m3 "),
+            Some(String::from("spotify:album:1DFixLWuPkv3KT3TnV35m3"))
+        );
+    }
+
+    #[test]
+    fn parse_spotify_links() {
+        for kind in SPOTIFY_LINK_KINDS {
+            let link = format!("https://open.spotify.com/{kind}/4uLU6hMCjMI75M1A2tKUQC");
+            let uri = format!("spotify:{kind}:4uLU6hMCjMI75M1A2tKUQC");
+            assert_eq!(parse_spotify_link(&link), Some(uri), "{link:?}");
+        }
+
+        for link in [
+            "open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M",
+            "http://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M",
+            "https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M/",
+            "https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M?si=1a2b3c4d5e6f",
+            "https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M#details",
+            "https://open.spotify.com/intl-de/playlist/37i9dQZF1DXcBWIGoYBM5M",
+            "https://open.spotify.com/intl-pt/playlist/37i9dQZF1DXcBWIGoYBM5M?si=1a2b3c4d5e6f",
+        ] {
+            assert_eq!(
+                parse_spotify_link(link),
+                Some(String::from("spotify:playlist:37i9dQZF1DXcBWIGoYBM5M")),
+                "{link:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_spotify_link_rejects_other_input() {
+        for input in [
+            "",
+            "never gonna give you up",
+            "spotify:",
+            "spotify:track",
+            "spotify:track:",
+            "spotify:user:spotify",
+            "spotify:user:spotify:playlist:37i9dQZF1DXcBWIGoYBM5M",
+            "spotify:local:artist:album:title:180",
+            "spotify:track:4uLU6hMCjMI75M1A2tKUQC:extra",
+            "spotify:track:4uLU6hMC-jMI75M1A2tKUQC",
+            "https://open.spotify.com/",
+            "https://open.spotify.com/user/spotify",
+            "https://open.spotify.com/track",
+            "https://open.spotify.com/intl-de/",
+            "https://open.spotify.com/track/4uLU6hMC_jMI75M1A2tKUQC",
+            "https://open.spotify.com/track/%34uLU6hMCjMI75M1A2tKUQC",
+            "https://example.com/track/4uLU6hMCjMI75M1A2tKUQC",
+            "https://spotify.link/abcdef",
+        ] {
+            assert_eq!(parse_spotify_link(input), None, "{input:?}");
+        }
+    }
+}
@@ -8,14 +8,22 @@ use axum::{
 };
 use chrono::Utc;
 use log::{error, info};
-use rspotify::{prelude::*, AuthCodeSpotify, Config, Credentials, OAuth, scopes, model::PlayableId};
+use rspotify::{prelude::*, AuthCodeSpotify, Config, Credentials, OAuth, scopes};
 use serde::{Deserialize, Serialize};
+use serenity::all::GuildId;
+use spoticord_session::{
+    manager::{SessionManager, SessionQuery},
+    queue::QueueEntry,
+    SessionHandle,
+};
 use spoticord_storage::{SpotifyCredentials, Storage};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 #[derive(Clone)]
 pub struct WebServer {
     storage: Storage,
+    /// The sessions of the bot, which are only available once it has connected to Discord
+    sessions: Arc<OnceLock<SessionManager>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,9 +36,17 @@ struct CallbackQuery {
 
 #[derive(Debug, Deserialize)]
 struct PlayTrackRequest {
+    /// The server whose session the track is played in
+    guild_id: GuildId,
     query: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct ClearQueueRequest {
+    /// The server whose session the queue is cleared in
+    guild_id: GuildId,
+}
+
 #[derive(Debug, Serialize)]
 struct ApiResponse {
     success: bool,
@@ -39,7 +55,15 @@ struct ApiResponse {
 
 impl WebServer {
     pub fn new(storage: Storage) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            sessions: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Let the web API find the sessions of the bot, once the bot has connected to Discord
+    pub fn set_session_manager(&self, manager: SessionManager) {
+        _ = self.sessions.set(manager);
     }
 
     pub async fn start(&self, port: u16) -> Result<()> {        let app = Router::new()
@@ -69,6 +93,33 @@ impl WebServer {
         Ok(auth_url)
     }
 
+    /// Retrieve the session of the bot in a server
+    fn session(&self, guild_id: GuildId) -> Option<SessionHandle> {
+        self.sessions
+            .get()
+            .and_then(|sessions| sessions.get_session(SessionQuery::Guild(guild_id)))
+    }
+
+    /// Retrieve the credentials of the Spotify account that the session of a server plays on, along with the ID of
+    /// the bot's Connect device in that session, which the web API plays on
+    async fn bot_device(&self, guild_id: GuildId) -> Result<Option<(SpotifyCredentials, String)>> {
+        let Some(session) = self.session(guild_id) else {
+            return Ok(None);
+        };
+
+        let Some(credentials) = self
+            .storage
+            .get_spotify_credentials(session.account())
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let player = session.player().await?;
+
+        Ok(Some((credentials, player.device_id().to_string())))
+    }
+
     fn create_spotify_client(&self) -> AuthCodeSpotify {
         let oauth = OAuth {
             redirect_uri: format!("{}/callback", spoticord_config::base_url()),
@@ -232,7 +283,18 @@ async fn callback_handler(
                     }),
                 );
 
-                match server.storage.save_spotify_credentials(&credentials).await {
+                // Accounts are stored by their Spotify user ID, so linking the same account twice replaces it
+                let result = match spotify.me().await {
+                    Ok(user) => {
+                        server
+                            .storage
+                            .save_spotify_credentials(user.id.id(), &credentials)
+                            .await
+                    }
+                    Err(why) => Err(why).context("Failed to retrieve Spotify user"),
+                };
+
+                match result {
                     Ok(()) => {
                         info!("Successfully saved Spotify credentials");
                         Html(
@@ -335,17 +397,32 @@ async fn callback_handler(
     }
 }
 
+/// Queue the first track that matches a search in the guild queue of a server.
+///
+/// The web API has no Discord member to attribute the track to, so it is queued on behalf of the host of the
+/// session, and counts towards the queue limits of the host.
 async fn play_track_handler(
     State(server): State<Arc<WebServer>>,
     Json(request): Json<PlayTrackRequest>,
 ) -> impl IntoResponse {
-    // Get Spotify credentials
-    let credentials = match server.storage.get_spotify_credentials().await {
-        Ok(Some(creds)) => creds,
+    let Some(session) = server.session(request.guild_id) else {
+        return Json(ApiResponse {
+            success: false,
+            message: "The bot isn't playing in this server, use /join first".to_string(),
+        });
+    };
+
+    // Get Spotify credentials of the account the session plays on
+    let credentials = match server
+        .storage
+        .get_spotify_credentials(session.account())
+        .await
+    {
+        Ok(Some(credentials)) => credentials,
         Ok(None) => {
             return Json(ApiResponse {
                 success: false,
-                message: "No Spotify account linked".to_string(),
+                message: "The Spotify account of the bot is no longer linked".to_string(),
             });
         }
         Err(e) => {
@@ -358,19 +435,18 @@ async fn play_track_handler(
     };
 
     // Create Spotify client with OAuth credentials
-    let token = rspotify::Token {
-        access_token: credentials.access_token.clone(),
-        expires_in: chrono::TimeDelta::seconds(3600),
-        expires_at: Some(credentials.expires_at),
-        refresh_token: Some(credentials.refresh_token.clone()),
-        scopes: std::collections::HashSet::new(),
-    };
-
-    let spotify = spoticord_config::get_spotify(token);
+    let spotify = spoticord_config::get_spotify(credentials.token());
 
     // Search for tracks
     let search_result = match spotify
-        .search(&request.query, rspotify::model::SearchType::Track, None, None, Some(5), None)
+        .search(
+            &request.query,
+            rspotify::model::SearchType::Track,
+            None,
+            None,
+            Some(5),
+            None,
+        )
         .await
     {
         Ok(result) => result,
@@ -385,7 +461,8 @@ async fn play_track_handler(
 
     let track = match search_result {
         rspotify::model::SearchResult::Tracks(page) => {
-            if let Some(track) = page.items.into_iter().next() {
+            // Local files don't have an ID, and can't be played by the bot
+            if let Some(track) = page.items.into_iter().find(|track| track.id.is_some()) {
                 track
             } else {
                 return Json(ApiResponse {
@@ -400,119 +477,18 @@ async fn play_track_handler(
                 message: "Unexpected search result type".to_string(),
             });
         }
-    };    // Check current playback state first
-    let track_id = track.id.as_ref().unwrap();
-    let playback_state = spotify.current_playback(None, None::<Vec<_>>).await;
-    
-    match playback_state {
-        Ok(Some(playback)) => {
-            // There's an active playback session, we can add to queue
-            let playable_id = PlayableId::Track(track_id.clone());
-            match spotify.add_item_to_queue(playable_id, None).await {
-                Ok(_) => {
-                    // If playback is paused, resume it
-                    if !playback.is_playing {
-                        if let Err(why) = spotify.resume_playback(playback.device.id.as_deref(), None).await {
-                            error!("Failed to resume playback: {why}");
-                        }
-                    }
-                }
-                Err(why) => {
-                    error!("Failed to add track to queue: {why}");
-                    return Json(ApiResponse {
-                        success: false,
-                        message: "Failed to add track to queue".to_string(),
-                    });
-                }
-            }
-        }        Ok(None) => {
-            // No active playback session, try to find our librespot device and transfer playback to it
-            match spotify.device().await {
-                Ok(devices) => {
-                    // Look for our bot's device
-                    let bot_device = devices.iter().find(|device| {
-                        device.name.contains("Spoticord") || device.name.contains("spoticord")
-                    });
-                    
-                    if let Some(device) = bot_device {
-                        // Check if device has an ID
-                        if let Some(device_id) = &device.id {
-                            // Transfer playback to our device first
-                            match spotify.transfer_playback(device_id, Some(true)).await {
-                                Ok(_) => {
-                                    // Now add the track to queue
-                                    let playable_id = PlayableId::Track(track_id.clone());
-                                    match spotify.add_item_to_queue(playable_id, Some(device_id)).await {
-                                        Ok(_) => {
-                                            // Successfully added to queue on our device
-                                        }
-                                        Err(why) => {
-                                            error!("Failed to add track to queue after transfer: {why}");
-                                            return Json(ApiResponse {
-                                                success: false,
-                                                message: "Failed to add track to queue after transferring playback".to_string(),
-                                            });
-                                        }
-                                    }
-                                }
-                                Err(why) => {
-                                    error!("Failed to transfer playback to device: {why}");
-                                    return Json(ApiResponse {
-                                        success: false,
-                                        message: "Failed to transfer playback to bot device".to_string(),
-                                    });
-                                }
-                            }
-                        } else {
-                            // Device found but no ID, fallback to direct playback
-                            let track_playable = PlayableId::Track(track_id.clone());
-                            match spotify.start_uris_playback([track_playable], None, None, None).await {
-                                Ok(_) => {
-                                    // Successfully started playback
-                                }
-                                Err(why) => {
-                                    error!("Failed to start playback: {why}");
-                                    return Json(ApiResponse {
-                                        success: false,
-                                        message: "Found bot device but failed to start playback".to_string(),
-                                    });
-                                }
-                            }
-                        }
-                    } else {
-                        // No bot device found, start playback directly with the track
-                        let track_playable = PlayableId::Track(track_id.clone());
-                        match spotify.start_uris_playback([track_playable], None, None, None).await {
-                            Ok(_) => {
-                                // Successfully started playback
-                            }
-                            Err(why) => {
-                                error!("Failed to start playback: {why}");
-                                return Json(ApiResponse {
-                                    success: false,
-                                    message: "Failed to start playback. Make sure you have an active Spotify device.".to_string(),
-                                });
-                            }
-                        }
-                    }
-                }
-                Err(why) => {
-                    error!("Failed to get devices: {why}");
-                    return Json(ApiResponse {
-                        success: false,
-                        message: "Failed to get available Spotify devices".to_string(),
-                    });
-                }
-            }
-        }
+    };
+
+    let requester = match session.owner().await {
+        Ok(owner) => owner,
         Err(why) => {
-            error!("Failed to check playback state: {why}");
+            error!("Failed to get the host of the session: {why}");
             return Json(ApiResponse {
                 success: false,
-                message: "Failed to connect to Spotify".to_string(),
+                message: "The bot isn't playing in this server, use /join first".to_string(),
             });
         }
-    }
+    };
 
     let artists = track
         .artists
@@ -521,20 +497,63 @@ async fn play_track_handler(
         .collect::<Vec<_>>()
         .join(", ");
 
+    let entry = QueueEntry {
+        uri: track.id.as_ref().map(|id| id.uri()).unwrap_or_default(),
+        name: track.name.clone(),
+        artists: artists.clone(),
+        duration: track.duration.num_milliseconds() as u32,
+        requester,
+    };
+
+    // The session hands the track to Spotify once it is up, and starts playing it if nothing is playing
+    let position = match session.enqueue(entry).await {
+        Ok(Ok(position)) => position,
+        Ok(Err(limit)) => {
+            return Json(ApiResponse {
+                success: false,
+                message: format!("'{}' was not added to the queue. {limit}", track.name),
+            });
+        }
+        Err(why) => {
+            error!("Failed to add track to queue: {why}");
+            return Json(ApiResponse {
+                success: false,
+                message: "Failed to add track to queue".to_string(),
+            });
+        }
+    };
+
+    // If playback is paused, resume it
+    if let Ok(player) = session.player().await {
+        if let Ok(Some(playback_info)) = player.playback_info().await {
+            if !playback_info.playing() {
+                player.play().await;
+            }
+        }
+    }
+
+    let position = match position {
+        0 => "up next".to_string(),
+        position => format!("#{position} in the queue"),
+    };
+
     Json(ApiResponse {
         success: true,
-        message: format!("Now playing '{}' by {}", track.name, artists),
+        message: format!("Queued '{}' by {}, {position}", track.name, artists),
     })
 }
 
-async fn clear_queue_handler(State(server): State<Arc<WebServer>>) -> impl IntoResponse {
-    // Get Spotify credentials
-    let credentials = match server.storage.get_spotify_credentials().await {
-        Ok(Some(creds)) => creds,
+async fn clear_queue_handler(
+    State(server): State<Arc<WebServer>>,
+    Json(request): Json<ClearQueueRequest>,
+) -> impl IntoResponse {
+    // Get Spotify credentials and the device of the bot
+    let (credentials, device_id) = match server.bot_device(request.guild_id).await {
+        Ok(Some(device)) => device,
         Ok(None) => {
             return Json(ApiResponse {
                 success: false,
-                message: "No Spotify account linked".to_string(),
+                message: "The bot isn't playing in this server, use /join first".to_string(),
             });
         }
         Err(e) => {
@@ -547,23 +566,15 @@ async fn clear_queue_handler(State(server): State<Arc<WebServer>>) -> impl IntoR
     };
 
     // Create Spotify client with OAuth credentials
-    let token = rspotify::Token {
-        access_token: credentials.access_token.clone(),
-        expires_in: chrono::TimeDelta::seconds(3600),
-        expires_at: Some(credentials.expires_at),
-        refresh_token: Some(credentials.refresh_token.clone()),
-        scopes: std::collections::HashSet::new(),
-    };
-
-    let spotify = spoticord_config::get_spotify(token);
+    let spotify = spoticord_config::get_spotify(credentials.token());
 
     // Get current playback state
     let playback = match spotify.current_playback(None, None::<Vec<_>>).await {
-        Ok(Some(playback)) => playback,
-        Ok(None) => {
+        Ok(Some(playback)) if playback.device.id.as_deref() == Some(device_id.as_str()) => playback,
+        Ok(_) => {
             return Json(ApiResponse {
                 success: false,
-                message: "No active Spotify playback".to_string(),
+                message: "The bot isn't playing anything".to_string(),
             });
         }
         Err(why) => {
@@ -575,8 +586,6 @@ async fn clear_queue_handler(State(server): State<Arc<WebServer>>) -> impl IntoR
         }
     };
 
-    let device_id = playback.device.id;
-
     // Clear queue by seeking to end and pausing
     if let Some(item) = playback.item {
         match item {
@@ -584,9 +593,9 @@ async fn clear_queue_handler(State(server): State<Arc<WebServer>>) -> impl IntoR
                 let duration_ms = track.duration.num_milliseconds() as u32;
                 let seek_position = chrono::TimeDelta::milliseconds((duration_ms.saturating_sub(1000)) as i64);
                 
-                match spotify.seek_track(seek_position, device_id.as_deref()).await {
+                match spotify.seek_track(seek_position, Some(device_id.as_str())).await {
                     Ok(_) => {
-                        match spotify.pause_playback(device_id.as_deref()).await {
+                        match spotify.pause_playback(Some(device_id.as_str())).await {
                             Ok(_) => {
                                 Json(ApiResponse {
                                     success: true,
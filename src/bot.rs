@@ -1,11 +1,13 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::{anyhow, Result};
-use log::{debug, info};
+use log::{debug, error, info};
 use poise::{serenity_prelude, Framework, FrameworkContext, FrameworkOptions};
-use serenity::all::{ActivityData, FullEvent, Ready, ShardManager};
-use spoticord_storage::Storage;
-use spoticord_session::manager::SessionManager;
+use serenity::all::{ActivityData, ChannelId, FullEvent, GuildId, Ready, ShardManager, UserId};
+use spoticord_player::cache::AudioCache;
+use spoticord_storage::{SavedSession, Storage};
+use spoticord_session::{discord::Gateway, manager::SessionManager};
+use spoticord_web::WebServer;
 
 use crate::commands;
 
@@ -17,25 +19,46 @@ pub type FrameworkError<'a> = poise::FrameworkError<'a, Data, anyhow::Error>;
 
 type Data = SessionManager;
 
+/// How long a guild gets to show up in the cache after startup before its saved session is dropped
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub fn framework_opts() -> FrameworkOptions<Data, anyhow::Error> {
     poise::FrameworkOptions {        commands: vec![
             #[cfg(debug_assertions)]
             commands::debug::ping(),
             #[cfg(debug_assertions)]
             commands::debug::token(),
+            commands::debug::debug(),
             commands::core::help(),
             commands::core::version(),
             commands::core::rename(),
             commands::core::link(),
             commands::core::unlink(),
+            commands::core::accounts(),
+            commands::core::settings(),
             commands::music::join(),
             commands::music::disconnect(),
             commands::music::stop(),
+            commands::music::eq(),
+            commands::music::crossfade(),
+            commands::music::normalization(),
+            commands::music::bitrate(),
+            commands::music::autoskip(),
+            commands::music::volume(),
+            commands::music::shuffle(),
+            commands::music::repeat(),
+            commands::music::autoplay(),
             commands::music::playing(),
             commands::music::lyrics(),
             commands::music::play(),
+            commands::music::search(),
+            commands::music::queue(),
+            commands::music::remove(),
+            commands::music::move_track(),
+            commands::music::shuffle_queue(),
             commands::music::clear(),
             commands::music::skip(),
+            commands::music::seek(),
         ],
         event_handler: |ctx, event, framework, data| {
             Box::pin(event_handler(ctx, event, framework, data))
@@ -49,6 +72,7 @@ pub async fn setup(
     ready: &Ready,
     framework: &Framework<Data, anyhow::Error>,
     storage: Storage,
+    web_server: WebServer,
 ) -> Result<Data> {
     info!("Successfully logged in as {}", ready.user.name);
 
@@ -65,7 +89,20 @@ pub async fn setup(
         .await
         .ok_or_else(|| anyhow!("Songbird was not registered during setup"))?;
 
-    let manager = SessionManager::new(songbird, storage);
+    let discord = Gateway::new(ctx.http.clone(), songbird);
+    let mut manager = SessionManager::new(Arc::new(discord), storage);
+
+    let cache_size = spoticord_config::cache_size();
+    if cache_size > 0 {
+        match AudioCache::new(spoticord_config::cache_dir(), cache_size) {
+            Ok(cache) => manager = manager.with_audio_cache(cache),
+            Err(why) => error!("Failed to create audio cache, continuing without it: {why}"),
+        }
+    }
+
+    web_server.set_session_manager(manager.clone());
+
+    tokio::spawn(restore_sessions(ctx.clone(), manager.clone()));
 
     #[cfg(feature = "stats")]
     let stats = StatsManager::new();
@@ -100,14 +137,92 @@ async fn event_handler(
     Ok(())
 }
 
+/// Rejoin the voice channels the bot was playing in before it restarted, as long as people are still listening
+async fn restore_sessions(ctx: serenity_prelude::Context, session_manager: SessionManager) {
+    let storage = session_manager.storage();
+
+    let guilds = match storage.list_saved_sessions().await {
+        Ok(guilds) => guilds,
+        Err(why) => {
+            error!("Failed to retrieve saved sessions: {why}");
+            return;
+        }
+    };
+
+    for guild_id in guilds {
+        let saved = match storage.get_saved_session(guild_id).await {
+            Ok(Some(saved)) => saved,
+            Ok(None) => continue,
+            Err(why) => {
+                error!("Failed to load saved session of guild {guild_id}: {why}");
+                _ = storage.remove_saved_session(guild_id).await;
+
+                continue;
+            }
+        };
+
+        let Some(owner) = find_owner(&ctx, &saved).await else {
+            debug!("Nobody is listening in guild {guild_id} anymore, not restoring session");
+            _ = storage.remove_saved_session(guild_id).await;
+
+            continue;
+        };
+
+        let saved = SavedSession {
+            owner_id: owner.get(),
+            ..saved
+        };
+
+        // A restored session keeps saving itself, and a session that failed to restore is kept for the next start
+        match session_manager.restore_session(saved).await {
+            Ok(_) => info!("Restored session in guild {guild_id}"),
+            Err(why) => error!("Failed to restore session in guild {guild_id}: {why}"),
+        }
+    }
+}
+
+/// Find who should own a restored session: the previous owner if they are still listening, otherwise any listener
+async fn find_owner(ctx: &serenity_prelude::Context, saved: &SavedSession) -> Option<UserId> {
+    let guild_id = GuildId::new(saved.guild_id);
+    let voice_channel = ChannelId::new(saved.voice_channel_id);
+    let started = std::time::Instant::now();
+
+    // Guilds are only cached once Discord sends them, which happens after the bot is ready
+    loop {
+        if let Some(guild) = ctx.cache.guild(guild_id) {
+            let current_user = ctx.cache.current_user().id;
+            let listeners = guild
+                .voice_states
+                .values()
+                .filter(|state| state.channel_id == Some(voice_channel))
+                .filter(|state| {
+                    state.user_id != current_user
+                        && !state.member.as_ref().is_some_and(|member| member.user.bot)
+                })
+                .map(|state| state.user_id)
+                .collect::<Vec<_>>();
+
+            let owner = UserId::new(saved.owner_id);
+            if listeners.contains(&owner) {
+                return Some(owner);
+            }
+
+            return listeners.first().copied();
+        }
+
+        if started.elapsed() > RESTORE_TIMEOUT {
+            return None;
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
 async fn background_loop(
     session_manager: SessionManager,
     shard_manager: Arc<ShardManager>,
     #[cfg(feature = "stats")] mut stats_manager: spoticord_stats::StatsManager,
 ) {
-    #[cfg(feature = "stats")]
-    use log::error;
-
     loop {
         tokio::select! {
             _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => {
@@ -128,13 +243,22 @@ async fn background_loop(
                     } else {
                         debug!("Active session count set to: {count}");
                     }
+
+                    if let Some(cache) = session_manager.audio_cache() {
+                        let stats = cache.stats();
+
+                        if let Err(why) = stats_manager.set_cache_stats(stats.hits, stats.misses) {
+                            error!("Failed to update cache stats: {why:?}");
+                        }
+                    }
                 }
             }
 
             _ = tokio::signal::ctrl_c() => {
                 info!("Received interrupt signal, shutting down...");
 
-                session_manager.shutdown_all().await;
+                // Keep the sessions saved, so they can be restored once the bot is back
+                session_manager.suspend_all().await;
                 shard_manager.shutdown_all().await;
 
                 #[cfg(feature = "stats")]
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::{CreateEmbed, CreateEmbedFooter};
+use spoticord_utils::discord::Colors;
+
+use crate::bot::Context;
+
+/// List the bot's Spotify accounts and the servers that are using them (Owner only)
+#[poise::command(slash_command, owners_only)]
+pub async fn accounts(ctx: Context<'_>) -> Result<()> {
+    let manager = ctx.data();
+    let accounts = manager.storage().list_spotify_accounts().await?;
+
+    if accounts.is_empty() {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("No Spotify accounts")
+                        .description("The bot doesn't have a Spotify account linked yet.")
+                        .footer(CreateEmbedFooter::new(
+                            "You can use /link to link a Spotify account.",
+                        ))
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let leases = manager.get_leases();
+    let mut lines = Vec::with_capacity(accounts.len());
+
+    for account in &accounts {
+        let status = match leases.get(account) {
+            Some(guild) => {
+                let name = match guild.to_partial_guild(&ctx).await {
+                    Ok(guild) => spoticord_utils::discord::escape(guild.name),
+                    Err(_) => guild.to_string(),
+                };
+
+                format!("in use in `{name}`")
+            }
+            None => "free".to_string(),
+        };
+
+        lines.push(format!(
+            "`{}`: {status}",
+            spoticord_utils::discord::escape(account)
+        ));
+    }
+
+    let busy = accounts
+        .iter()
+        .filter(|account| leases.contains_key(*account))
+        .count();
+
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .title("Spotify accounts")
+                    .description(lines.join("\n"))
+                    .footer(CreateEmbedFooter::new(format!(
+                        "{busy} of {} accounts in use",
+                        accounts.len()
+                    )))
+                    .color(Colors::Info),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -8,29 +8,10 @@ use spoticord_utils::discord::Colors;
 
 use crate::bot::{Context, FrameworkError};
 
-/// Link the bot's Spotify account (Admin only)
-#[poise::command(slash_command, on_error = on_error)]
+/// Link a Spotify account to the bot (Owner only)
+#[poise::command(slash_command, owners_only, on_error = on_error)]
 pub async fn link(ctx: Context<'_>) -> Result<()> {
-    // Check if the user has permission to link the bot's account
-    // For simplicity, we'll allow anyone for now, but in production you might want to restrict this
-    let storage = ctx.data().storage();
-    
-    // Check if Spotify is already linked
-    if storage.get_spotify_credentials().await?.is_some() {
-        ctx.send(
-            CreateReply::default().embed(
-                CreateEmbed::new()
-                    .title("Spotify account already linked")
-                    .description("The bot already has a Spotify account linked. If you need to re-link, contact the bot administrator.")
-                    .color(Colors::Info),
-            ).ephemeral(true),
-        )
-        .await?;
-
-        return Ok(());
-    }
-
-    // Direct to web interface for linking
+    // Direct to web interface for linking, every linked account adds one more simultaneous session
     let link = spoticord_config::base_url();
 
     ctx.send(
@@ -42,7 +23,11 @@ pub async fn link(ctx: Context<'_>) -> Result<()> {
                             .url(link)
                             .icon_url("https://spoticord.com/spotify-logo.png"),
                     )
-                    .description("Click on the button below to link the bot's Spotify account.")
+                    .description(
+                        "Click on the button below to link a Spotify account to the bot.\n\n\
+                        Each linked account can play in one server at a time. \
+                        Linking an account that is already linked will refresh its credentials.",
+                    )
                     .footer(CreateEmbedFooter::new(
                         "This will allow the bot to play music for everyone in this server.",
                     ))
@@ -73,7 +58,7 @@ async fn on_error(error: FrameworkError<'_>) {
                     .ephemeral(true),
             )
             .await;
-    } else {
-        error!("{error}")
+    } else if let Err(why) = poise::builtins::on_error(error).await {
+        error!("Failed to handle error: {why}");
     }
 }
@@ -1,11 +1,15 @@
+mod accounts;
 mod help;
 mod link;
 mod rename;
+mod settings;
 mod unlink;
 mod version;
 
+pub use accounts::*;
 pub use help::*;
 pub use link::*;
 pub use rename::*;
+pub use settings::*;
 pub use unlink::*;
 pub use version::*;
@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::{CreateEmbed, CreateEmbedFooter, GuildChannel, Role};
+use spoticord_session::manager::SessionQuery;
+use spoticord_storage::GuildSettings;
+use spoticord_utils::discord::Colors;
+
+use crate::bot::Context;
+
+/// View or change the settings of this server
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_GUILD",
+    subcommands(
+        "view",
+        "idle_timeout",
+        "volume",
+        "announce_channel",
+        "dj_role",
+        "lyrics",
+        "fair_queue",
+        "queue_limits"
+    ),
+    subcommand_required
+)]
+pub async fn settings(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Show the current settings of this server
+#[poise::command(slash_command)]
+async fn view(ctx: Context<'_>) -> Result<()> {
+    let settings = load(ctx).await?;
+
+    let idle_timeout = match settings.idle_timeout {
+        Some(seconds) => format_minutes(seconds),
+        None => format!(
+            "{} (default)",
+            format_minutes(spoticord_config::DISCONNECT_TIME)
+        ),
+    };
+
+    let volume = match settings.default_volume {
+        Some(volume) => format!("{volume}%"),
+        None => format!("Last used volume ({}%)", settings.volume),
+    };
+
+    let announce_channel = match settings.announce_channel {
+        Some(channel) => format!("<#{channel}>"),
+        None => "The channel the bot is summoned from".to_string(),
+    };
+
+    let dj_role = match settings.dj_role {
+        Some(role) => format!("<@&{role}>"),
+        None => "None, only the host controls playback".to_string(),
+    };
+
+    let queue_limits = match (settings.max_queued_tracks, settings.max_queued_duration) {
+        (None, None) => "None".to_string(),
+        (tracks, duration) => [
+            tracks.map(|tracks| format!("{tracks} tracks")),
+            duration.map(format_minutes),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", "),
+    };
+
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .title("Server settings")
+                    .field("Idle timeout", idle_timeout, true)
+                    .field("Default volume", volume, true)
+                    .field("Announcement channel", announce_channel, true)
+                    .field("DJ role", dj_role, true)
+                    .field("Autoplay", on_off(settings.autoplay), true)
+                    .field("Lyrics", on_off(settings.lyrics), true)
+                    .field("Fair queue", on_off(settings.fair_queue), true)
+                    .field("Queue limits per member", queue_limits, true)
+                    .footer(CreateEmbedFooter::new(
+                        "Use the /settings subcommands and /autoplay to change these settings.",
+                    ))
+                    .color(Colors::Info),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Change how long the bot stays in a call without playing music
+#[poise::command(slash_command, rename = "idle-timeout")]
+async fn idle_timeout(
+    ctx: Context<'_>,
+
+    #[description = "The timeout in minutes, resets to the default if omitted"]
+    #[min = 1]
+    #[max = 60]
+    minutes: Option<u64>,
+) -> Result<()> {
+    let settings = update(ctx, |settings| {
+        settings.idle_timeout = minutes.map(|minutes| minutes * 60)
+    })
+    .await?;
+
+    let timeout = settings
+        .idle_timeout
+        .unwrap_or(spoticord_config::DISCONNECT_TIME);
+
+    // Apply the new timeout to the current session immediately
+    if let Some(session) = ctx
+        .guild_id()
+        .and_then(|guild| ctx.data().get_session(SessionQuery::Guild(guild)))
+    {
+        session
+            .set_idle_timeout(Duration::from_secs(timeout))
+            .await?;
+    }
+
+    let timeout = format_minutes(timeout);
+
+    respond(
+        ctx,
+        format!("The bot will now leave after being idle for **{timeout}**."),
+    )
+    .await
+}
+
+/// Change the volume the bot starts playing at
+#[poise::command(slash_command)]
+async fn volume(
+    ctx: Context<'_>,
+
+    #[description = "The volume in percent, uses the last used volume if omitted"]
+    #[min = 0]
+    #[max = 100]
+    percentage: Option<u8>,
+) -> Result<()> {
+    update(ctx, |settings| settings.default_volume = percentage).await?;
+
+    let description = match percentage {
+        Some(percentage) => format!("The bot will now start playing at **{percentage}%**."),
+        None => "The bot will now start playing at the last used volume.".to_string(),
+    };
+
+    respond(ctx, description).await
+}
+
+/// Change the channel the bot posts its announcements in
+#[poise::command(slash_command, rename = "announce-channel")]
+async fn announce_channel(
+    ctx: Context<'_>,
+
+    #[description = "The channel to announce in, uses the channel the bot is summoned from if omitted"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+) -> Result<()> {
+    update(ctx, |settings| {
+        settings.announce_channel = channel.as_ref().map(|channel| channel.id.get())
+    })
+    .await?;
+
+    let description = match channel {
+        Some(channel) => format!(
+            "The bot will now post its announcements in <#{}>.",
+            channel.id
+        ),
+        None => "The bot will now post its announcements in the channel it is summoned from."
+            .to_string(),
+    };
+
+    respond(ctx, description).await
+}
+
+/// Change the role that may control playback, just like the host
+#[poise::command(slash_command, rename = "dj-role")]
+async fn dj_role(
+    ctx: Context<'_>,
+
+    #[description = "The DJ role, only the host controls playback if omitted"] role: Option<Role>,
+) -> Result<()> {
+    update(ctx, |settings| {
+        settings.dj_role = role.as_ref().map(|role| role.id.get())
+    })
+    .await?;
+
+    let description = match role {
+        Some(role) => format!("Members with <@&{}> may now control playback.", role.id),
+        None => "Only the host may now control playback.".to_string(),
+    };
+
+    respond(ctx, description).await
+}
+
+/// Allow or disallow showing the lyrics of the current song
+#[poise::command(slash_command)]
+async fn lyrics(
+    ctx: Context<'_>,
+
+    #[description = "Whether lyrics may be shown"] enabled: bool,
+) -> Result<()> {
+    update(ctx, |settings| settings.lyrics = enabled).await?;
+
+    respond(ctx, format!("Lyrics are now **{}**.", on_off(enabled))).await
+}
+
+/// Let the tracks of different members take turns in the queue
+#[poise::command(slash_command, rename = "fair-queue")]
+async fn fair_queue(
+    ctx: Context<'_>,
+
+    #[description = "Whether to interleave queued tracks by requester"] enabled: bool,
+) -> Result<()> {
+    update(ctx, |settings| settings.fair_queue = enabled).await?;
+
+    let description = if enabled {
+        "Queued tracks will now take turns between the members who requested them."
+    } else {
+        "Queued tracks will now play in the order they were requested."
+    };
+
+    respond(ctx, description).await
+}
+
+/// Limit how much music a single member may have queued at once
+#[poise::command(slash_command, rename = "queue-limits")]
+async fn queue_limits(
+    ctx: Context<'_>,
+
+    #[description = "The amount of tracks per member, unlimited if omitted"]
+    #[min = 1]
+    #[max = 100]
+    tracks: Option<u32>,
+
+    #[description = "The total duration in minutes per member, unlimited if omitted"]
+    #[min = 1]
+    #[max = 600]
+    minutes: Option<u64>,
+) -> Result<()> {
+    let settings = update(ctx, |settings| {
+        settings.max_queued_tracks = tracks;
+        settings.max_queued_duration = minutes.map(|minutes| minutes * 60);
+    })
+    .await?;
+
+    let tracks = match tracks {
+        Some(tracks) => format!("**{tracks}** tracks"),
+        None => "any amount of tracks".to_string(),
+    };
+
+    let duration = match settings.max_queued_duration {
+        Some(seconds) => format!("up to **{}**", format_minutes(seconds)),
+        None => "any duration".to_string(),
+    };
+
+    respond(
+        ctx,
+        format!("Members may now have {tracks} queued at once, lasting {duration} in total."),
+    )
+    .await
+}
+
+async fn load(ctx: Context<'_>) -> Result<GuildSettings> {
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    ctx.data().storage().get_guild_settings(guild.get()).await
+}
+
+async fn update(
+    ctx: Context<'_>,
+    update: impl FnOnce(&mut GuildSettings),
+) -> Result<GuildSettings> {
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    ctx.data()
+        .storage()
+        .update_guild_settings(guild.get(), update)
+        .await
+}
+
+async fn respond(ctx: Context<'_>, description: impl Into<String>) -> Result<()> {
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Settings changed")
+                .description(description)
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn format_minutes(seconds: u64) -> String {
+    match seconds / 60 {
+        1 => "1 minute".to_string(),
+        minutes => format!("{minutes} minutes"),
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "On"
+    } else {
+        "Off"
+    }
+}
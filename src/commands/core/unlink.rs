@@ -1,34 +1,52 @@
 use anyhow::Result;
 use log::error;
 use poise::CreateReply;
-use serenity::all::{CreateEmbed, CreateEmbedFooter};
+use serenity::all::{AutocompleteChoice, CreateEmbed, CreateEmbedFooter};
+use spoticord_session::manager::SessionQuery;
 use spoticord_utils::discord::Colors;
 
 use crate::bot::{Context, FrameworkError};
 
-/// Unlink the bot's Spotify account (Admin only)
-#[poise::command(slash_command, on_error = on_error)]
-pub async fn unlink(ctx: Context<'_>) -> Result<()> {
-    let manager = ctx.data();
-    let storage = manager.storage();
+async fn account_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    let accounts = ctx
+        .data()
+        .storage()
+        .list_spotify_accounts()
+        .await
+        .unwrap_or_default();
+
+    accounts
+        .into_iter()
+        .filter(|account| account.starts_with(partial))
+        .map(|account| AutocompleteChoice::new(account.clone(), account))
+        .collect()
+}
 
-    // Disconnect all sessions since we're unlinking the central account
-    manager.shutdown_all().await;
+/// Unlink one of the bot's Spotify accounts (Owner only)
+#[poise::command(slash_command, owners_only, on_error = on_error)]
+pub async fn unlink(
+    ctx: Context<'_>,
 
-    // Check if there's actually a linked account
-    let has_credentials = storage.get_spotify_credentials().await?.is_some();
+    #[description = "The Spotify account to unlink, see /accounts"]
+    #[autocomplete = "account_autocomplete"]
+    account: String,
+) -> Result<()> {
+    let manager = ctx.data();
+    let storage = manager.storage();
 
-    if !has_credentials {
+    // Check if there's actually such a linked account
+    if !storage.list_spotify_accounts().await?.contains(&account) {
         ctx.send(
             CreateReply::default()
                 .embed(
                     CreateEmbed::new()
-                        .title("No Spotify account linked")
-                        .description(
-                            "The bot doesn't have a Spotify account linked.",
-                        )
+                        .title("No such Spotify account")
+                        .description(format!(
+                            "The bot doesn't have a Spotify account `{}` linked.",
+                            spoticord_utils::discord::escape(&account)
+                        ))
                         .footer(CreateEmbedFooter::new(
-                            "You can use /link to link a Spotify account.",
+                            "You can use /accounts to see the linked Spotify accounts.",
                         ))
                         .color(Colors::Error),
                 )
@@ -38,18 +56,25 @@ pub async fn unlink(ctx: Context<'_>) -> Result<()> {
         return Ok(());
     }
 
-    // For now, we'll just inform the user that they need to manually remove the credentials file
-    // In a production setup, you might want to implement actual file deletion
+    // Disconnect the session that is playing on this account
+    let guild = manager.get_leases().get(&account).copied();
+    if let Some(session) = guild.and_then(|guild| manager.get_session(SessionQuery::Guild(guild))) {
+        session.disconnect().await;
+    }
+
+    storage.remove_spotify_account(&account).await?;
+
     ctx.send(
         CreateReply::default()
             .embed(
                 CreateEmbed::new()
-                    .title("Unlink Request")
-                    .description(
-                        "To unlink the Spotify account, please contact the bot administrator to remove the credentials.",
-                    )
+                    .title("Spotify account unlinked")
+                    .description(format!(
+                        "The Spotify account `{}` has been unlinked from the bot.",
+                        spoticord_utils::discord::escape(&account)
+                    ))
                     .footer(CreateEmbedFooter::new(
-                        "All music sessions have been stopped.",
+                        "Any session that was using this account has been stopped.",
                     ))
                     .color(Colors::Info),
             )
@@ -75,7 +100,7 @@ async fn on_error(error: FrameworkError<'_>) {
                     .ephemeral(true),
             )
             .await;
-    } else {
-        error!("{error}")
+    } else if let Err(why) = poise::builtins::on_error(error).await {
+        error!("Failed to handle error: {why}");
     }
 }
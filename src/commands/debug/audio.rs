@@ -0,0 +1,96 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use crate::bot::Context;
+
+/// Diagnostic commands for server administrators
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("audio"),
+    subcommand_required,
+    default_member_permissions = "MANAGE_GUILD"
+)]
+pub async fn debug(_ctx: Context<'_>) -> Result<()> {
+    Ok(())
+}
+
+/// Show audio pipeline telemetry for the current session
+#[poise::command(slash_command, guild_only)]
+async fn audio(ctx: Context<'_>) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let stats = match manager.get_session(SessionQuery::Guild(guild)) {
+        Some(session) => match session.player().await {
+            Ok(player) => player.audio_stats().await.ok(),
+            Err(_) => None,
+        },
+        None => None,
+    };
+
+    let Some(stats) = stats else {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot display audio telemetry")
+                        .description("I'm currently not playing any music in this server.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .title("Audio telemetry")
+                    .field(
+                        "Buffer",
+                        format!(
+                            "{} / {} bytes\nPeak: {} bytes",
+                            stats.fill_level, stats.capacity, stats.max_fill_level
+                        ),
+                        true,
+                    )
+                    .field(
+                        "Throughput",
+                        format!(
+                            "Written: {} bytes\nRead: {} bytes",
+                            stats.bytes_written, stats.bytes_read
+                        ),
+                        true,
+                    )
+                    .field(
+                        "Underruns",
+                        format!(
+                            "{} underruns\n{} zero-filled bytes",
+                            stats.underruns, stats.zero_filled_bytes
+                        ),
+                        true,
+                    )
+                    .field(
+                        "Writer blocked",
+                        format!(
+                            "{} ms in total\n{} ms longest",
+                            stats.writer_blocked.as_millis(),
+                            stats.longest_writer_block.as_millis()
+                        ),
+                        true,
+                    )
+                    .color(Colors::Info),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
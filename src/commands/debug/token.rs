@@ -1,22 +1,39 @@
 use anyhow::Result;
 use poise::CreateReply;
+use spoticord_session::manager::SessionQuery;
 
 use crate::bot::Context;
 
 /// Retrieve the Spotify access token. For debugging purposes.
 #[poise::command(slash_command)]
 pub async fn token(ctx: Context<'_>) -> Result<()> {
-    let token = ctx
-        .data()
-        .storage()
-        .get_spotify_token()
-        .await;
+    let manager = ctx.data();
+
+    // Prefer the account this server is playing on, otherwise the first linked account
+    let account = match ctx
+        .guild_id()
+        .and_then(|guild| manager.get_session(SessionQuery::Guild(guild)))
+    {
+        Some(session) => Some(session.account().to_string()),
+        None => manager
+            .storage()
+            .list_spotify_accounts()
+            .await?
+            .into_iter()
+            .next(),
+    };
+
+    let token = match &account {
+        Some(account) => manager.storage().get_spotify_token(account).await,
+        None => Ok(None),
+    };
 
     let content = match token {
-        Ok(Some(token)) => format!("Bot's Spotify token:\n```\n{token}\n```"),
-        Ok(None) => {
-            "The bot doesn't have a Spotify account linked".to_string()
-        }
+        Ok(Some(token)) => format!(
+            "Spotify token of account `{}`:\n```\n{token}\n```",
+            account.unwrap_or_default()
+        ),
+        Ok(None) => "The bot doesn't have a Spotify account linked".to_string(),
         Err(why) => format!("Failed to retrieve access token: {why}"),
     };
 
@@ -1,5 +1,3 @@
 pub mod core;
-pub mod music;
-
-#[cfg(debug_assertions)]
 pub mod debug;
+pub mod music;
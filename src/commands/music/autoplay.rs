@@ -0,0 +1,45 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use crate::bot::Context;
+
+/// Keep playing similar music once the queue runs out in this server
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn autoplay(
+    ctx: Context<'_>,
+
+    #[description = "Whether to autoplay, toggles autoplay if omitted"] enabled: Option<bool>,
+) -> Result<()> {
+    let manager = ctx.data();
+    let storage = manager.storage();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let settings = storage
+        .update_guild_settings(guild.get(), |settings| {
+            settings.autoplay = enabled.unwrap_or(!settings.autoplay)
+        })
+        .await?;
+
+    if let Some(session) = manager.get_session(SessionQuery::Guild(guild)) {
+        session.set_autoplay(settings.autoplay).await?;
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Autoplay changed")
+                .description(if settings.autoplay {
+                    "Autoplay has been **enabled**, similar tracks will be played once the queue runs out."
+                } else {
+                    "Autoplay has been **disabled**."
+                })
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
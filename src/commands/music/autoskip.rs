@@ -0,0 +1,40 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_utils::discord::Colors;
+
+use crate::bot::Context;
+
+/// Choose whether tracks that can't be played are skipped automatically in this server
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn autoskip(
+    ctx: Context<'_>,
+
+    #[description = "Whether to skip unavailable tracks, toggles the setting if omitted"]
+    enabled: Option<bool>,
+) -> Result<()> {
+    let storage = ctx.data().storage();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let settings = storage
+        .update_guild_settings(guild.get(), |settings| {
+            settings.skip_unavailable = enabled.unwrap_or(!settings.skip_unavailable)
+        })
+        .await?;
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Auto skip changed")
+                .description(if settings.skip_unavailable {
+                    "Tracks that can't be played will now be **skipped** automatically."
+                } else {
+                    "Tracks that can't be played will **no longer be skipped** automatically."
+                })
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
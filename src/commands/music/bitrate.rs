@@ -0,0 +1,97 @@
+use anyhow::Result;
+use poise::{ChoiceParameter, CreateReply};
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use crate::bot::Context;
+
+#[derive(Debug, ChoiceParameter)]
+pub enum BitrateChoice {
+    #[name = "Default"]
+    Default,
+
+    #[name = "96 kbps"]
+    Low,
+
+    #[name = "160 kbps"]
+    Normal,
+
+    #[name = "320 kbps (Premium only)"]
+    High,
+}
+
+impl BitrateChoice {
+    fn kbps(&self) -> Option<u16> {
+        match self {
+            Self::Default => None,
+            Self::Low => Some(96),
+            Self::Normal => Some(160),
+            Self::High => Some(320),
+        }
+    }
+}
+
+/// Change the quality that music is streamed from Spotify at in this server
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn bitrate(
+    ctx: Context<'_>,
+
+    #[description = "The bitrate to stream at, Default uses the bitrate of this bot"]
+    bitrate: Option<BitrateChoice>,
+) -> Result<()> {
+    let manager = ctx.data();
+    let storage = manager.storage();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let Some(bitrate) = bitrate else {
+        let settings = storage.get_guild_settings(guild.get()).await?;
+
+        let description = match settings.bitrate {
+            Some(kbps) => format!("Music is currently streamed at **{kbps} kbps**."),
+            None => format!(
+                "Music is currently streamed at the default of **{} kbps**.",
+                spoticord_config::bitrate()
+            ),
+        };
+
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Bitrate")
+                        .description(description)
+                        .color(Colors::Info),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let settings = storage
+        .update_guild_settings(guild.get(), |settings| settings.bitrate = bitrate.kbps())
+        .await?;
+
+    let mut description = format!(
+        "Music will now be streamed at **{} kbps**.",
+        settings.bitrate.unwrap_or(spoticord_config::bitrate())
+    );
+
+    if manager.get_session(SessionQuery::Guild(guild)).is_some() {
+        description += "\n\nThe new bitrate will be used from the next time the player starts.";
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Bitrate changed")
+                .description(description)
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -7,6 +7,7 @@ use serenity::all::{CreateEmbed, CreateEmbedAuthor};
 use spoticord_session::manager::SessionQuery;
 use spoticord_utils::discord::Colors;
 
+use super::control::can_control;
 use crate::bot::Context;
 
 /// Clear the Spotify queue
@@ -15,7 +16,7 @@ pub async fn clear(ctx: Context<'_>) -> Result<()> {
     let manager = ctx.data();
     
     // Check if we're in a voice channel session
-    let _session = match manager.get_session(SessionQuery::Guild(ctx.guild_id().unwrap())) {
+    let session = match manager.get_session(SessionQuery::Guild(ctx.guild_id().unwrap())) {
         Some(session) => session,
         None => {
             ctx.send(
@@ -33,9 +34,29 @@ pub async fn clear(ctx: Context<'_>) -> Result<()> {
         }
     };
 
+    // Clearing the queue removes the tracks of other members as well
+    if !can_control(ctx, &session).await? {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot clear queue")
+                        .description("Only the host or a DJ may clear the queue.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    // Drop the tracks that were queued through the bot and haven't been handed to Spotify yet
+    session.clear_queue().await?;
+
     // Get Spotify credentials and create authenticated client
     let storage = manager.storage();
-    let credentials = match storage.get_spotify_credentials().await? {
+    let credentials = match storage.get_spotify_credentials(session.account()).await? {
         Some(creds) => creds,
         None => {
             ctx.send(
@@ -54,26 +75,23 @@ pub async fn clear(ctx: Context<'_>) -> Result<()> {
     };
 
     // Create Spotify client with OAuth credentials
-    let token = rspotify::Token {
-        access_token: credentials.access_token.clone(),
-        expires_in: chrono::TimeDelta::seconds(3600),
-        expires_at: Some(credentials.expires_at),
-        refresh_token: Some(credentials.refresh_token.clone()),
-        scopes: std::collections::HashSet::new(),
-    };
+    let spotify = spoticord_config::get_spotify(credentials.token());
 
-    let spotify = spoticord_config::get_spotify(token);
+    ctx.defer().await?;
 
-    ctx.defer().await?;    // Get current playback state to find the active device
+    let player = session.player().await?;
+    let device_id = Some(player.device_id());
+
+    // Get current playback state, which has to be on the bot's own device
     let playback = match spotify.current_playback(None, None::<Vec<_>>).await {
-        Ok(Some(playback)) => playback,
-        Ok(None) => {
+        Ok(Some(playback)) if playback.device.id.as_deref() == device_id => playback,
+        Ok(_) => {
             ctx.send(
                 CreateReply::default()
                     .embed(
                         CreateEmbed::new()
                             .title("No active playback")
-                            .description("No Spotify playback is currently active. Start playing something first.")
+                            .description("The bot isn't playing anything right now. Start playing something first.")
                             .color(Colors::Error),
                     )
                     .ephemeral(true),
@@ -98,8 +116,6 @@ pub async fn clear(ctx: Context<'_>) -> Result<()> {
         }
     };
 
-    let device_id = playback.device.id;
-
     // Unfortunately, Spotify's Web API doesn't have a direct "clear queue" endpoint.
     // We need to use a workaround: skip to the end of the current track and then
     // pause, which effectively clears the queue for the next playback session.
@@ -110,10 +126,10 @@ pub async fn clear(ctx: Context<'_>) -> Result<()> {
                 // Seek to 1 second before the end to avoid auto-advancing
                 let seek_position = chrono::TimeDelta::milliseconds((duration_ms.saturating_sub(1000)) as i64);
                 
-                match spotify.seek_track(seek_position, device_id.as_deref()).await {
+                match spotify.seek_track(seek_position, device_id).await {
                     Ok(_) => {
                         // Then pause to stop playback
-                        match spotify.pause_playback(device_id.as_deref()).await {
+                        match spotify.pause_playback(device_id).await {
                             Ok(_) => {
                                 ctx.send(
                                     CreateReply::default()
@@ -0,0 +1,27 @@
+use anyhow::Result;
+use spoticord_session::SessionHandle;
+
+use crate::bot::Context;
+
+/// Check whether the author may control the playback of a session, which is the host and anyone with the DJ role
+pub async fn can_control(ctx: Context<'_>, session: &SessionHandle) -> Result<bool> {
+    if session.owner().await? == ctx.author().id {
+        return Ok(true);
+    }
+
+    let settings = ctx
+        .data()
+        .storage()
+        .get_guild_settings(session.guild().get())
+        .await?;
+
+    if settings.dj_role.is_none() {
+        return Ok(false);
+    }
+
+    let Some(member) = ctx.author_member().await else {
+        return Ok(false);
+    };
+
+    Ok(settings.is_dj(member.roles.iter().map(|role| role.get())))
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use crate::bot::Context;
+
+/// Change the length of the crossfade between tracks in this server
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn crossfade(
+    ctx: Context<'_>,
+
+    #[description = "The crossfade length in seconds, 0 disables crossfading"]
+    #[min = 0]
+    #[max = 12]
+    seconds: Option<u64>,
+) -> Result<()> {
+    let manager = ctx.data();
+    let storage = manager.storage();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let Some(seconds) = seconds else {
+        let settings = storage.get_guild_settings(guild.get()).await?;
+
+        let description = match settings.crossfade {
+            0 => "Crossfading is currently disabled in this server.".to_string(),
+            seconds => format!("Tracks currently crossfade over **{seconds}** seconds."),
+        };
+
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Crossfade")
+                        .description(description)
+                        .color(Colors::Info),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    storage
+        .update_guild_settings(guild.get(), |settings| settings.crossfade = seconds)
+        .await?;
+
+    // Apply the new length to the current session immediately
+    if let Some(session) = manager.get_session(SessionQuery::Guild(guild)) {
+        session.set_crossfade(Duration::from_secs(seconds)).await?;
+    }
+
+    let description = match seconds {
+        0 => "Crossfading has been disabled.".to_string(),
+        seconds => format!("Tracks will now crossfade over **{seconds}** seconds."),
+    };
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Crossfade changed")
+                .description(description)
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
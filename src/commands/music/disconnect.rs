@@ -4,6 +4,7 @@ use serenity::all::CreateEmbed;
 use spoticord_session::manager::SessionQuery;
 use spoticord_utils::discord::Colors;
 
+use super::control::can_control;
 use crate::bot::Context;
 
 #[poise::command(slash_command, guild_only)]
@@ -27,13 +28,13 @@ pub async fn disconnect(ctx: Context<'_>) -> Result<(), Error> {
         return Ok(());
     };
 
-    if session.active().await? && session.owner().await? != ctx.author().id {
+    if session.active().await? && !can_control(ctx, &session).await? {
         ctx.send(
             CreateReply::default()
                 .embed(
                     CreateEmbed::new()
                         .title("Cannot disconnect bot")
-                        .description("Only the host may disconnect the bot.")
+                        .description("Only the host or a DJ may disconnect the bot.")
                         .color(Colors::Error),
                 )
                 .ephemeral(true),
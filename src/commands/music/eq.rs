@@ -0,0 +1,85 @@
+use anyhow::Result;
+use poise::{ChoiceParameter, CreateReply};
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_storage::Equalizer;
+use spoticord_utils::discord::Colors;
+
+use super::control::can_control;
+use crate::bot::Context;
+
+#[derive(Debug, ChoiceParameter)]
+pub enum EqualizerChoice {
+    #[name = "Flat (no equalizer)"]
+    Flat,
+
+    #[name = "Bass boost"]
+    BassBoost,
+
+    #[name = "Vocal"]
+    Vocal,
+}
+
+impl From<EqualizerChoice> for Equalizer {
+    fn from(value: EqualizerChoice) -> Self {
+        match value {
+            EqualizerChoice::Flat => Self::Flat,
+            EqualizerChoice::BassBoost => Self::BassBoost,
+            EqualizerChoice::Vocal => Self::Vocal,
+        }
+    }
+}
+
+/// Change the equalizer that is applied to the music in this server
+#[poise::command(slash_command, guild_only)]
+pub async fn eq(
+    ctx: Context<'_>,
+    #[description = "The equalizer preset to use"] preset: EqualizerChoice,
+) -> Result<()> {
+    let manager = ctx.data();
+    let storage = manager.storage();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let session = manager.get_session(SessionQuery::Guild(guild));
+
+    if let Some(session) = &session {
+        if session.active().await? && !can_control(ctx, session).await? {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Cannot change equalizer")
+                            .description("Only the host or a DJ may change the equalizer.")
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    }
+
+    let name = preset.name();
+
+    let settings = storage
+        .update_guild_settings(guild.get(), |settings| settings.equalizer = preset.into())
+        .await?;
+
+    // Apply the new preset to the current session immediately
+    if let Some(session) = session {
+        session.set_equalizer(settings.equalizer).await?;
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Equalizer changed")
+                .description(format!("The equalizer is now set to **{name}**."))
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
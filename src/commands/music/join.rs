@@ -94,7 +94,7 @@ pub async fn join(ctx: Context<'_>) -> Result<()> {
 
         return Ok(());
     }    // Check whether the bot has a linked Spotify account
-    if manager.storage().get_spotify_credentials().await?.is_none() {
+    if manager.storage().list_spotify_accounts().await?.is_empty() {
         ctx.send(
             CreateReply::default()
                 .embed(
@@ -190,7 +190,6 @@ pub async fn join(ctx: Context<'_>) -> Result<()> {
         }
     } else if let Err(why) = manager
         .create_session(
-            ctx.serenity_context(),
             guild.id,
             channel,
             ctx.channel_id(),
@@ -202,6 +201,8 @@ pub async fn join(ctx: Context<'_>) -> Result<()> {
 
         let description = if matches!(why, spoticord_session::error::Error::AuthenticationFailed) {
             "Unable to authenticate with Spotify. Did you change your password?\n\nThe broken credentials used have been deleted.\n\nYou might need to relink your account using `/link`."
+        } else if matches!(why, spoticord_session::error::Error::AllAccountsBusy) {
+            "All Spotify accounts are busy.\n\nEvery linked Spotify account is already playing in another server. Try again once one of them is free, or ask an administrator to `/link` another account."
         } else {
             "An error occured whilst trying to create a session. Please try again."
         };
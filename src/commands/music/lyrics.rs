@@ -28,12 +28,29 @@ pub async fn lyrics(ctx: Context<'_>) -> Result<()> {
         return Ok(());
     };
 
+    let settings = manager.storage().get_guild_settings(guild.get()).await?;
+    if !settings.lyrics {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot get lyrics")
+                        .description("Lyrics have been turned off in this server.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
     let Context::Application(context) = ctx else {
         panic!("Slash command is a prefix command?");
     };
 
     session
-        .create_lyrics_embed(context.interaction.clone())
+        .create_lyrics_embed(context.serenity_context(), context.interaction.clone())
         .await?;
 
     Ok(())
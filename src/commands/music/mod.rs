@@ -1,17 +1,46 @@
+mod autoplay;
+mod autoskip;
+mod bitrate;
 mod clear;
+mod control;
+mod crossfade;
 mod disconnect;
+mod eq;
 mod join;
 mod lyrics;
+mod move_track;
+mod normalization;
 mod playing;
 mod queue;
+mod remove;
+mod repeat;
+mod search;
+mod seek;
+mod shuffle;
+mod shuffle_queue;
 mod skip;
 mod stop;
+mod volume;
 
+pub use autoplay::*;
+pub use autoskip::*;
+pub use bitrate::*;
 pub use clear::*;
+pub use crossfade::*;
 pub use disconnect::*;
+pub use eq::*;
 pub use join::*;
 pub use lyrics::*;
+pub use move_track::*;
+pub use normalization::*;
 pub use playing::*;
 pub use queue::*;
+pub use remove::*;
+pub use repeat::*;
+pub use search::*;
+pub use seek::*;
+pub use shuffle::*;
+pub use shuffle_queue::*;
 pub use skip::*;
 pub use stop::*;
+pub use volume::*;
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use super::control::can_control;
+use crate::bot::Context;
+
+/// Move a track to another position in the queue
+#[poise::command(slash_command, guild_only, rename = "move")]
+pub async fn move_track(
+    ctx: Context<'_>,
+
+    #[description = "The current position of the track, see /queue"]
+    #[min = 1]
+    from: usize,
+
+    #[description = "The position to move the track to"]
+    #[min = 1]
+    to: usize,
+) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let Some(session) = manager.get_session(SessionQuery::Guild(guild)) else {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot move track")
+                        .description("I'm currently not playing any music in this server.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    if !can_control(ctx, &session).await? {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot move track")
+                        .description("Only the host or a DJ may reorder the queue.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let Some(entry) = session.move_in_queue(from - 1, to - 1).await? else {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot move track")
+                        .description("Both positions must be within the queue, see `/queue`.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Track moved")
+                .description(format!(
+                    "**{}** is now at position {to} in the queue.",
+                    spoticord_utils::discord::escape(&entry.name)
+                ))
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::ops::RangeInclusive;
+
+use anyhow::Result;
+use poise::{ChoiceParameter, CreateReply};
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_storage::Normalization;
+use spoticord_utils::discord::Colors;
+
+use crate::bot::Context;
+
+/// The range of target loudness values that can be chosen, in LUFS
+const TARGET_RANGE: RangeInclusive<f64> = -30.0..=-5.0;
+
+#[derive(Debug, ChoiceParameter)]
+pub enum NormalizationChoice {
+    #[name = "Off"]
+    Off,
+
+    #[name = "Per track"]
+    Track,
+
+    #[name = "Per album"]
+    Album,
+}
+
+impl From<NormalizationChoice> for Normalization {
+    fn from(value: NormalizationChoice) -> Self {
+        match value {
+            NormalizationChoice::Off => Self::Off,
+            NormalizationChoice::Track => Self::Track,
+            NormalizationChoice::Album => Self::Album,
+        }
+    }
+}
+
+/// Even out the volume differences between tracks in this server
+#[poise::command(slash_command, guild_only, default_member_permissions = "MANAGE_GUILD")]
+pub async fn normalization(
+    ctx: Context<'_>,
+
+    #[description = "Which loudness information to normalize with"] mode: NormalizationChoice,
+
+    #[description = "The loudness to aim for in LUFS (-30 to -5), defaults to Spotify's -14 LUFS"]
+    target: Option<f64>,
+) -> Result<()> {
+    if target.is_some_and(|target| !TARGET_RANGE.contains(&target)) {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot change normalization")
+                        .description(format!(
+                            "The target loudness must be between {} and {} LUFS.",
+                            TARGET_RANGE.start(),
+                            TARGET_RANGE.end()
+                        ))
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let manager = ctx.data();
+    let storage = manager.storage();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let settings = storage
+        .update_guild_settings(guild.get(), |settings| {
+            settings.normalization = mode.into();
+            if let Some(target) = target {
+                settings.target_loudness = target;
+            }
+        })
+        .await?;
+
+    let mut description = match settings.normalization {
+        Normalization::Off => "Loudness normalization has been disabled.".to_string(),
+        Normalization::Track => format!(
+            "Every track is now normalized to **{} LUFS**.",
+            settings.target_loudness
+        ),
+        Normalization::Album => format!(
+            "Albums are now normalized to **{} LUFS**, keeping the differences between their tracks.",
+            settings.target_loudness
+        ),
+    };
+
+    if let Some(session) = manager.get_session(SessionQuery::Guild(guild)) {
+        session
+            .set_normalization(settings.normalization, settings.target_loudness)
+            .await?;
+
+        description += "\n\nThe loudness metadata from Spotify will be used from the next time the player starts.";
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Normalization changed")
+                .description(description)
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
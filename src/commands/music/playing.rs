@@ -38,7 +38,11 @@ pub async fn playing(
     };
 
     session
-        .create_playback_embed(context.interaction, update_behavior.unwrap_or_default())
+        .create_playback_embed(
+            context.serenity_context(),
+            context.interaction,
+            update_behavior.unwrap_or_default(),
+        )
         .await?;
 
     Ok(())
@@ -1,18 +1,21 @@
 use anyhow::Result;
-use chrono;
 use log::error;
-use poise::CreateReply;
+use poise::{ChoiceParameter, CreateReply};
 use rspotify::{
-    model::{SearchResult, PlayableId},
+    model::{EpisodeId, FullTrack, SearchResult, SearchType, TrackId},
     prelude::*,
+    AuthCodeSpotify,
 };
-use serenity::all::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, AutocompleteChoice};
-use spoticord_session::manager::SessionQuery;
+use serenity::all::{
+    AutocompleteChoice, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, UserId,
+};
+use spoticord_session::{manager::SessionQuery, queue::QueueEntry, SessionHandle};
 use spoticord_utils::discord::Colors;
 use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::collections::HashMap;
 
+use super::control::can_control;
 use crate::bot::Context;
 
 // Cache for autocomplete results with debouncing
@@ -25,6 +28,30 @@ fn get_cache() -> &'static Arc<Mutex<HashMap<String, (Vec<AutocompleteChoice>, I
 const AUTOCOMPLETE_CACHE_DURATION: Duration = Duration::from_secs(300); // 5 minutes
 const DEBOUNCE_DURATION: Duration = Duration::from_millis(1000); // 1 second
 
+/// The amount of queued tracks that are shown per page of `/queue`
+const QUEUE_PAGE_SIZE: usize = 10;
+
+#[derive(Debug, ChoiceParameter)]
+pub enum SearchKind {
+    #[name = "Track"]
+    Track,
+
+    #[name = "Album"]
+    Album,
+
+    #[name = "Playlist"]
+    Playlist,
+}
+
+/// What `/play` ends up playing
+#[derive(Clone)]
+pub(super) enum PlayTarget {
+    /// A track or episode that is added to the queue of the guild
+    Entry(QueueEntry),
+    /// The URI of an album, playlist, artist or show that starts playing right away
+    Context(String),
+}
+
 async fn track_autocomplete(
     ctx: Context<'_>,
     partial: &str,
@@ -50,24 +77,24 @@ async fn track_autocomplete(
 
     let manager = ctx.data();
     let storage = manager.storage();
-    
+
+    // Search using the Spotify account of this server's session
+    let Some(session) = ctx
+        .guild_id()
+        .and_then(|guild| manager.get_session(SessionQuery::Guild(guild)))
+    else {
+        return vec![];
+    };
+
     // Get Spotify credentials for search
-    let credentials = match storage.get_spotify_credentials().await {
+    let credentials = match storage.get_spotify_credentials(session.account()).await {
         Ok(Some(creds)) => creds,
         Ok(None) => return vec![],
         Err(_) => return vec![],
     };
 
     // Create Spotify client for searching
-    let token = rspotify::Token {
-        access_token: credentials.access_token.clone(),
-        expires_in: chrono::TimeDelta::seconds(3600),
-        expires_at: Some(credentials.expires_at),
-        refresh_token: Some(credentials.refresh_token.clone()),
-        scopes: std::collections::HashSet::new(),
-    };
-
-    let spotify = spoticord_config::get_spotify(token);
+    let spotify = spoticord_config::get_spotify(credentials.token());
 
     // Search for tracks
     let search_result = match spotify
@@ -113,18 +140,21 @@ async fn track_autocomplete(
     choices
 }
 
-/// Play a track (add to queue and start playback)
+/// Play a track, album, playlist, artist, show or episode from a search or a Spotify link
 #[poise::command(slash_command)]
 pub async fn play(
     ctx: Context<'_>,
-    #[description = "The track to search for and play"]
+    #[description = "What to search for, or a Spotify link"]
     #[autocomplete = "track_autocomplete"]
     query: String,
+    #[description = "What to search for, tracks by default"]
+    #[rename = "type"]
+    kind: Option<SearchKind>,
 ) -> Result<()> {
     let manager = ctx.data();
     
     // Check if we're in a voice channel session
-    let _session = match manager.get_session(SessionQuery::Guild(ctx.guild_id().unwrap())) {
+    let session = match manager.get_session(SessionQuery::Guild(ctx.guild_id().unwrap())) {
         Some(session) => session,
         None => {
             ctx.send(
@@ -144,7 +174,7 @@ pub async fn play(
 
     // Get Spotify credentials and create authenticated client
     let storage = manager.storage();
-    let credentials = match storage.get_spotify_credentials().await? {
+    let credentials = match storage.get_spotify_credentials(session.account()).await? {
         Some(creds) => creds,
         None => {
             ctx.send(
@@ -161,265 +191,253 @@ pub async fn play(
             return Ok(());
         }
     };    // Create Spotify client with OAuth credentials
-    let token = rspotify::Token {
-        access_token: credentials.access_token.clone(),
-        expires_in: chrono::TimeDelta::seconds(3600),
-        expires_at: Some(credentials.expires_at),
-        refresh_token: Some(credentials.refresh_token.clone()),
-        scopes: std::collections::HashSet::new(),
-    };
-
-    let spotify = spoticord_config::get_spotify(token);
+    let spotify = spoticord_config::get_spotify(credentials.token());
 
     ctx.defer().await?;
 
-    // Search for tracks
-    let search_result = match spotify
-        .search(&query, rspotify::model::SearchType::Track, None, None, Some(5), None)
-        .await
-    {
-        Ok(result) => result,
+    let requester = ctx.author().id;
+    let target = match spoticord_utils::parse_spotify_link(&query) {
+        Some(uri) => resolve_link(&spotify, &uri, requester).await,
+        None => {
+            let kind = kind.unwrap_or(SearchKind::Track);
+            search(&spotify, &query, kind, requester).await
+        }
+    };
+
+    let target = match target {
+        Ok(Some(target)) => target,
+        Ok(None) => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("No results")
+                            .description("Nothing playable was found for your search query.")
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
         Err(why) => {
-            error!("Failed to search Spotify: {why}");
+            error!("Failed to look up {query} on Spotify: {why}");
+
             ctx.send(
                 CreateReply::default()
                     .embed(
                         CreateEmbed::new()
                             .title("Search failed")
-                            .description("Failed to search for tracks on Spotify.")
+                            .description("Failed to find this on Spotify.")
                             .color(Colors::Error),
                     )
                     .ephemeral(true),
             )
             .await?;
+
             return Ok(());
         }
-    };    let track = match search_result {
-        SearchResult::Tracks(page) => {
-            if let Some(track) = page.items.into_iter().next() {
-                track
-            } else {
+    };
+
+    match target {
+        PlayTarget::Entry(entry) => enqueue(ctx, &session, entry).await,
+        PlayTarget::Context(uri) => {
+            // Playing a context replaces whatever is playing right now
+            if !can_control(ctx, &session).await? {
                 ctx.send(
                     CreateReply::default()
                         .embed(
                             CreateEmbed::new()
-                                .title("No results")
-                                .description("No tracks found for your search query.")
+                                .title("Cannot start playback")
+                                .description("Only the host or a DJ may interrupt playback.")
                                 .color(Colors::Error),
                         )
                         .ephemeral(true),
                 )
                 .await?;
+
                 return Ok(());
             }
+
+            play_context(ctx, &session, &uri).await
         }
-        _ => {
+    }
+}
+
+/// Look up what a Spotify link points to
+async fn resolve_link(
+    spotify: &AuthCodeSpotify,
+    uri: &str,
+    requester: UserId,
+) -> Result<Option<PlayTarget>> {
+    if let Ok(id) = TrackId::from_uri(uri) {
+        let track = spotify.track(id, None).await?;
+
+        return Ok(track_entry(track, requester).map(PlayTarget::Entry));
+    }
+
+    if let Ok(id) = EpisodeId::from_uri(uri) {
+        let episode = spotify.get_an_episode(id, None).await?;
+
+        return Ok(Some(PlayTarget::Entry(QueueEntry {
+            uri: episode.id.uri(),
+            name: episode.name,
+            artists: episode.show.name,
+            duration: episode.duration.num_milliseconds() as u32,
+            requester,
+        })));
+    }
+
+    // Albums, playlists, artists and shows are played as a whole
+    Ok(Some(PlayTarget::Context(uri.to_string())))
+}
+
+/// Search Spotify and pick the best match
+async fn search(
+    spotify: &AuthCodeSpotify,
+    query: &str,
+    kind: SearchKind,
+    requester: UserId,
+) -> Result<Option<PlayTarget>> {
+    let search_type = match kind {
+        SearchKind::Track => SearchType::Track,
+        SearchKind::Album => SearchType::Album,
+        SearchKind::Playlist => SearchType::Playlist,
+    };
+
+    let target = match spotify
+        .search(query, search_type, None, None, Some(5), None)
+        .await?
+    {
+        SearchResult::Tracks(page) => page
+            .items
+            .into_iter()
+            .find_map(|track| track_entry(track, requester))
+            .map(PlayTarget::Entry),
+        SearchResult::Albums(page) => page
+            .items
+            .into_iter()
+            .find_map(|album| album.id)
+            .map(|id| PlayTarget::Context(id.uri())),
+        SearchResult::Playlists(page) => page
+            .items
+            .into_iter()
+            .next()
+            .map(|playlist| PlayTarget::Context(playlist.id.uri())),
+        _ => None,
+    };
+
+    Ok(target)
+}
+
+/// Add a track or episode to the queue of the guild
+pub(super) async fn enqueue(
+    ctx: Context<'_>,
+    session: &SessionHandle,
+    entry: QueueEntry,
+) -> Result<()> {
+    let name = entry.name.clone();
+    let artists = entry.artists.clone();
+    let duration = entry.duration / 1000;
+
+    let position = match session.enqueue(entry).await? {
+        Ok(position) => position,
+        Err(limit) => {
             ctx.send(
                 CreateReply::default()
                     .embed(
                         CreateEmbed::new()
-                            .title("Search error")
-                            .description("Unexpected search result type.")
+                            .title("Queue limit reached")
+                            .description(format!(
+                                "**{}** was not added to the queue. {limit}, wait until some of your tracks have played.",
+                                spoticord_utils::discord::escape(&name)
+                            ))
                             .color(Colors::Error),
                     )
                     .ephemeral(true),
             )
             .await?;
+
             return Ok(());
         }
-    };    // Check current playback state first
-    let track_id = track.id.as_ref().unwrap();
-    let playback_state = spotify.current_playback(None, None::<Vec<_>>).await;
-    
-    match playback_state {
-        Ok(Some(playback)) => {
-            // There's an active playback session, we can add to queue
-            let playable_id = PlayableId::Track(track_id.clone());
-            match spotify.add_item_to_queue(playable_id, None).await {
-                Ok(_) => {
-                    // If playback is paused, resume it
-                    if !playback.is_playing {
-                        if let Err(why) = spotify.resume_playback(playback.device.id.as_deref(), None).await {
-                            error!("Failed to resume playback: {why}");
-                        }
-                    }
-                }
-                Err(why) => {
-                    error!("Failed to add track to queue: {why}");
-                    ctx.send(
-                        CreateReply::default()
-                            .embed(
-                                CreateEmbed::new()
-                                    .title("Queue failed")
-                                    .description("Failed to add track to Spotify queue.")
-                                    .color(Colors::Error),
-                            )
-                            .ephemeral(true),
-                    )
-                    .await?;
-                    return Ok(());
-                }
-            }
-        }        Ok(None) => {
-            // No active playback session, try to find our librespot device and transfer playback to it
-            match spotify.device().await {
-                Ok(devices) => {
-                    // Look for our bot's device (you might need to adjust the name matching logic)
-                    let bot_device = devices.iter().find(|device| {
-                        device.name.contains("Spoticord") || device.name.contains("spoticord")
-                    });
-                      if let Some(device) = bot_device {
-                        // Check if device has an ID
-                        if let Some(device_id) = &device.id {
-                            // Transfer playback to our device first
-                            match spotify.transfer_playback(device_id, Some(true)).await {
-                                Ok(_) => {
-                                    // Now add the track to queue
-                                    let playable_id = PlayableId::Track(track_id.clone());
-                                    match spotify.add_item_to_queue(playable_id, Some(device_id)).await {
-                                        Ok(_) => {
-                                            // Successfully added to queue on our device
-                                        }
-                                        Err(why) => {
-                                            error!("Failed to add track to queue after transfer: {why}");
-                                            ctx.send(
-                                                CreateReply::default()
-                                                    .embed(
-                                                        CreateEmbed::new()
-                                                            .title("Queue failed")
-                                                            .description("Failed to add track to queue after transferring playback.")
-                                                            .color(Colors::Error),
-                                                    )
-                                                    .ephemeral(true),
-                                            )
-                                            .await?;
-                                            return Ok(());
-                                        }
-                                    }
-                                }
-                                Err(why) => {
-                                    error!("Failed to transfer playback to device: {why}");
-                                    ctx.send(
-                                        CreateReply::default()
-                                            .embed(
-                                                CreateEmbed::new()
-                                                    .title("Transfer failed")
-                                                    .description("Failed to transfer playback to bot device.")
-                                                    .color(Colors::Error),
-                                            )
-                                            .ephemeral(true),
-                                    )
-                                    .await?;
-                                    return Ok(());
-                                }
-                            }
-                        } else {
-                            // Device found but no ID, fallback to direct playback
-                            let track_playable = PlayableId::Track(track_id.clone());
-                            match spotify.start_uris_playback([track_playable], None, None, None).await {
-                                Ok(_) => {
-                                    // Successfully started playback
-                                }
-                                Err(why) => {
-                                    error!("Failed to start playback: {why}");
-                                    ctx.send(
-                                        CreateReply::default()
-                                            .embed(
-                                                CreateEmbed::new()
-                                                    .title("Playback failed")
-                                                    .description("Found bot device but failed to start playback.")
-                                                    .color(Colors::Error),
-                                            )
-                                            .ephemeral(true),
-                                    )
-                                    .await?;
-                                    return Ok(());
-                                }
-                            }
-                        }
-                    } else {
-                        // No bot device found, start playback directly with the track
-                        let track_playable = PlayableId::Track(track_id.clone());
-                        match spotify.start_uris_playback([track_playable], None, None, None).await {
-                            Ok(_) => {
-                                // Successfully started playback
-                            }
-                            Err(why) => {
-                                error!("Failed to start playback: {why}");
-                                ctx.send(
-                                    CreateReply::default()
-                                        .embed(
-                                            CreateEmbed::new()
-                                                .title("Playback failed")
-                                                .description("Failed to start Spotify playback. Make sure you have an active Spotify device or the bot is properly connected.")
-                                                .footer(CreateEmbedFooter::new("Open Spotify on any device and try again"))
-                                                .color(Colors::Error),
-                                        )
-                                        .ephemeral(true),
-                                )
-                                .await?;
-                                return Ok(());
-                            }
-                        }
-                    }
-                }
-                Err(why) => {
-                    error!("Failed to get devices: {why}");
-                    ctx.send(
-                        CreateReply::default()
-                            .embed(
-                                CreateEmbed::new()
-                                    .title("Device query failed")
-                                    .description("Failed to get available Spotify devices.")
-                                    .color(Colors::Error),
-                            )
-                            .ephemeral(true),
+    };
+
+    let duration_str = format!("{}:{:02}", duration / 60, duration % 60);
+
+    let position_str = match position {
+        0 => "Up next".to_string(),
+        position => format!("#{position}"),
+    };
+
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .author(
+                        CreateEmbedAuthor::new("Added to queue")
+                            .icon_url("https://spoticord.com/spotify-logo.png"),
                     )
-                    .await?;
-                    return Ok(());
-                }
-            }
-        }
+                    .title(&name)
+                    .description(format!("by {}", artists))
+                    .field("Duration", duration_str, true)
+                    .field("Position", position_str, true)
+                    .footer(CreateEmbedFooter::new(format!(
+                        "Requested by {}",
+                        ctx.author().display_name()
+                    )))
+                    .color(Colors::Success),
+            )
+            .ephemeral(false),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Start playing an album, playlist, artist or show on the device of the bot
+pub(super) async fn play_context(
+    ctx: Context<'_>,
+    session: &SessionHandle,
+    uri: &str,
+) -> Result<()> {
+    let name = match session.play_context(uri).await {
+        Ok(name) => name,
         Err(why) => {
-            error!("Failed to check playback state: {why}");
+            error!("Failed to play {uri}: {why}");
+
             ctx.send(
                 CreateReply::default()
                     .embed(
                         CreateEmbed::new()
-                            .title("Connection failed")
-                            .description("Failed to connect to Spotify. Please try again.")
+                            .title("Cannot start playback")
+                            .description("Failed to start playing this on Spotify.")
                             .color(Colors::Error),
                     )
                     .ephemeral(true),
             )
             .await?;
+
             return Ok(());
         }
-    }
-
-    let artists = track
-        .artists
-        .iter()
-        .map(|a| a.name.clone())
-        .collect::<Vec<_>>()
-        .join(", ");
+    };
 
-    let duration = track.duration.num_seconds();
-    let duration_str = format!("{}:{:02}", duration / 60, duration % 60);
+    let kind = uri.split(':').nth(1).unwrap_or("context");
 
     ctx.send(
         CreateReply::default()
             .embed(
                 CreateEmbed::new()
                     .author(
-                        CreateEmbedAuthor::new("Now Playing")
+                        CreateEmbedAuthor::new("Now playing")
                             .icon_url("https://spoticord.com/spotify-logo.png"),
                     )
-                    .title(&track.name)
-                    .description(format!("by {}", artists))
-                    .field("Duration", duration_str, true)
-                    .field("Popularity", format!("{}/100", track.popularity), true)
-                    .footer(CreateEmbedFooter::new("Track is now playing on Spotify"))
+                    .title(name.unwrap_or_else(|| format!("This {kind}")))
+                    .url(spotify_url(uri))
+                    .footer(CreateEmbedFooter::new(format!(
+                        "Requested by {}",
+                        ctx.author().display_name()
+                    )))
                     .color(Colors::Success),
             )
             .ephemeral(false),
@@ -428,3 +446,122 @@ pub async fn play(
 
     Ok(())
 }
+
+pub(super) fn track_entry(track: FullTrack, requester: UserId) -> Option<QueueEntry> {
+    Some(QueueEntry {
+        uri: track.id?.uri(),
+        name: track.name,
+        artists: track
+            .artists
+            .into_iter()
+            .map(|artist| artist.name)
+            .collect::<Vec<_>>()
+            .join(", "),
+        duration: track.duration.num_milliseconds() as u32,
+        requester,
+    })
+}
+
+/// Turn a URI like `spotify:album:<id>` into a link to the Spotify web player
+pub(super) fn spotify_url(uri: &str) -> String {
+    format!(
+        "https://open.spotify.com/{}",
+        uri.trim_start_matches("spotify:").replace(':', "/")
+    )
+}
+
+/// Show the tracks that have been queued in this server
+#[poise::command(slash_command, guild_only)]
+pub async fn queue(
+    ctx: Context<'_>,
+
+    #[description = "The page of the queue to show"]
+    #[min = 1]
+    page: Option<usize>,
+) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let Some(session) = manager.get_session(SessionQuery::Guild(guild)) else {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot show queue")
+                        .description("I'm currently not playing any music in this server.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let queue = session.queue().await?;
+    let pages = queue.len().div_ceil(QUEUE_PAGE_SIZE).max(1);
+    let page = page.unwrap_or(1).min(pages);
+
+    let mut description = String::new();
+
+    if let Some(entry) = queue.current() {
+        description += &format!("**Now playing:** {}\n", format_entry(entry));
+    }
+
+    if let Some(entry) = queue.next() {
+        description += &format!("**Up next:** {}\n", format_entry(entry));
+    }
+
+    if queue.is_empty() {
+        if description.is_empty() {
+            description += "The queue is empty. Use `/play` to add tracks to it.";
+        }
+    } else {
+        description += "\n";
+
+        for (index, entry) in queue
+            .entries()
+            .iter()
+            .enumerate()
+            .skip((page - 1) * QUEUE_PAGE_SIZE)
+            .take(QUEUE_PAGE_SIZE)
+        {
+            description += &format!("{}. {}\n", index + 1, format_entry(entry));
+        }
+    }
+
+    let total = queue
+        .entries()
+        .iter()
+        .map(|entry| entry.duration as u64)
+        .sum::<u64>();
+
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .title("Queue")
+                    .description(description)
+                    .footer(CreateEmbedFooter::new(format!(
+                        "Page {page}/{pages} • {} tracks • {} in total",
+                        queue.len(),
+                        spoticord_utils::time_to_string((total / 1000) as u32)
+                    )))
+                    .color(Colors::Info),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn format_entry(entry: &QueueEntry) -> String {
+    format!(
+        "**{}** - {} `{}` (<@{}>)",
+        spoticord_utils::discord::escape(&entry.name),
+        spoticord_utils::discord::escape(&entry.artists),
+        spoticord_utils::time_to_string(entry.duration / 1000),
+        entry.requester
+    )
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use super::control::can_control;
+use crate::bot::Context;
+
+/// Remove a track from the queue
+#[poise::command(slash_command, guild_only)]
+pub async fn remove(
+    ctx: Context<'_>,
+
+    #[description = "The position of the track in the queue, see /queue"]
+    #[min = 1]
+    position: usize,
+) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let Some(session) = manager.get_session(SessionQuery::Guild(guild)) else {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot remove track")
+                        .description("I'm currently not playing any music in this server.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    // Members that can't control the session may only remove the tracks they queued themselves
+    let requester = if can_control(ctx, &session).await? {
+        None
+    } else {
+        Some(ctx.author().id)
+    };
+
+    let removed = match session.remove_from_queue(position - 1, requester).await? {
+        Ok(removed) => removed,
+        Err(why) => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Cannot remove track")
+                            .description(format!("{why}."))
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Track removed")
+                .description(format!(
+                    "**{}** has been removed from the queue.",
+                    spoticord_utils::discord::escape(&removed.name)
+                ))
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
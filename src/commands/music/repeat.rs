@@ -0,0 +1,96 @@
+use anyhow::Result;
+use poise::{ChoiceParameter, CreateReply};
+use serenity::all::CreateEmbed;
+use spoticord_player::info::RepeatMode;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use super::control::can_control;
+use crate::bot::Context;
+
+#[derive(Debug, ChoiceParameter)]
+pub enum RepeatChoice {
+    #[name = "Off"]
+    Off,
+
+    #[name = "Playlist/album"]
+    Context,
+
+    #[name = "Track"]
+    Track,
+}
+
+impl From<RepeatChoice> for RepeatMode {
+    fn from(value: RepeatChoice) -> Self {
+        match value {
+            RepeatChoice::Off => Self::Off,
+            RepeatChoice::Context => Self::Context,
+            RepeatChoice::Track => Self::Track,
+        }
+    }
+}
+
+/// Repeat the current playlist, album or track
+#[poise::command(slash_command, guild_only, rename = "loop")]
+pub async fn repeat(
+    ctx: Context<'_>,
+
+    #[description = "What to repeat"] mode: RepeatChoice,
+) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let session = match manager.get_session(SessionQuery::Guild(guild)) {
+        Some(session) if session.active().await? => session,
+        _ => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Cannot change repeat")
+                            .description("I'm currently not playing any music in this server.")
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    if !can_control(ctx, &session).await? {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot change repeat")
+                        .description("Only the host or a DJ may change repeat.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let mode = RepeatMode::from(mode);
+    session.player().await?.set_repeat(mode).await;
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Repeat changed")
+                .description(match mode {
+                    RepeatMode::Off => "Repeat has been **disabled**.",
+                    RepeatMode::Context => "The current playlist or album will now **repeat**.",
+                    RepeatMode::Track => "The current track will now **repeat**.",
+                })
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,497 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use log::error;
+use poise::CreateReply;
+use rspotify::{
+    model::{SearchResult, SearchType},
+    prelude::*,
+    AuthCodeSpotify,
+};
+use serenity::{
+    all::{
+        ButtonStyle, ComponentInteraction, ComponentInteractionCollector,
+        ComponentInteractionDataKind, CreateActionRow, CreateButton, CreateEmbed,
+        CreateEmbedAuthor, CreateEmbedFooter, CreateInteractionResponse,
+        CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateSelectMenu,
+        CreateSelectMenuKind, CreateSelectMenuOption, UserId,
+    },
+    futures::StreamExt,
+};
+use spoticord_session::{manager::SessionQuery, queue::QueueEntry, SessionHandle};
+use spoticord_utils::discord::{escape, Colors};
+
+use super::{
+    control::can_control,
+    queue::{enqueue, play_context, spotify_url, track_entry, PlayTarget},
+};
+use crate::bot::Context;
+
+/// The amount of results that are shown per kind of result
+const RESULTS_PER_KIND: u32 = 5;
+
+/// How long the search results can be picked from, which must stay well within the 15 minutes an interaction lasts
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The maximum length of the label and description of a select menu option, as imposed by Discord
+const OPTION_TEXT_LIMIT: usize = 100;
+
+/// The maximum length of the value of an embed field, as imposed by Discord
+const FIELD_TEXT_LIMIT: usize = 1024;
+
+/// A single search result that can be picked from the select menu
+struct SearchItem {
+    kind: &'static str,
+    name: String,
+    /// The artists of a track or album, or the owner of a playlist
+    detail: String,
+    /// How long a track is, or how many tracks a playlist has
+    length: Option<String>,
+    explicit: bool,
+    target: PlayTarget,
+}
+
+/// Search Spotify for tracks, albums and playlists, and pick what to play
+#[poise::command(slash_command, guild_only)]
+pub async fn search(
+    ctx: Context<'_>,
+
+    #[description = "What to search for"] query: String,
+) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let Some(session) = manager.get_session(SessionQuery::Guild(guild)) else {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot search")
+                        .description("Use `/join` first to create a music session.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let Some(credentials) = manager
+        .storage()
+        .get_spotify_credentials(session.account())
+        .await?
+    else {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("No Spotify account")
+                        .description("The bot doesn't have a Spotify account linked yet.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let spotify = spoticord_config::get_spotify(credentials.token());
+
+    ctx.defer().await?;
+
+    let items = match search_all(&spotify, &query, ctx.author().id).await {
+        Ok(items) if !items.is_empty() => items,
+        Ok(_) => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("No results")
+                            .description("Nothing was found for your search query.")
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+        Err(why) => {
+            error!("Failed to search Spotify: {why}");
+
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Search failed")
+                            .description("Failed to search Spotify.")
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let id = ctx.id();
+    let mut selected = 0;
+
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .embed(build_embed(&query, &items))
+                .components(build_components(id, &items, selected)),
+        )
+        .await?;
+
+    let mut stream = ComponentInteractionCollector::new(ctx.serenity_context())
+        .filter(move |press| press.data.custom_id.starts_with(&format!("{id}-")))
+        .timeout(SEARCH_TIMEOUT)
+        .stream();
+
+    while let Some(press) = stream.next().await {
+        if press.user.id != ctx.author().id {
+            respond_error(
+                ctx,
+                &press,
+                "Only the member who searched may pick from these results.",
+            )
+            .await;
+
+            continue;
+        }
+
+        let result = match press.data.custom_id.split('-').last() {
+            Some("select") => {
+                if let ComponentInteractionDataKind::StringSelect { values } = &press.data.kind {
+                    selected = values
+                        .first()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|index| *index < items.len())
+                        .unwrap_or(selected);
+                }
+
+                _ = press
+                    .create_response(
+                        ctx,
+                        CreateInteractionResponse::UpdateMessage(
+                            CreateInteractionResponseMessage::new()
+                                .components(build_components(id, &items, selected)),
+                        ),
+                    )
+                    .await;
+
+                Ok(())
+            }
+            Some("play") => play_selected(ctx, &session, &press, &items[selected]).await,
+            Some("queue") => queue_selected(ctx, &session, &press, &items[selected]).await,
+
+            _ => Ok(()),
+        };
+
+        // Keep the results pickable, a failed action shouldn't take the other results down with it
+        if let Err(why) = result {
+            error!("Failed to handle search result action: {why}");
+
+            respond_error(ctx, &press, "Something went wrong, please try again.").await;
+        }
+    }
+
+    // The results can no longer be picked from once the collector has stopped
+    reply
+        .edit(
+            ctx,
+            CreateReply::default()
+                .embed(build_embed(&query, &items))
+                .components(Vec::new()),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Search for tracks, albums and playlists at once, in that order
+async fn search_all(
+    spotify: &AuthCodeSpotify,
+    query: &str,
+    requester: UserId,
+) -> Result<Vec<SearchItem>> {
+    let mut items = Vec::new();
+
+    for search_type in [SearchType::Track, SearchType::Album, SearchType::Playlist] {
+        let result = spotify
+            .search(query, search_type, None, None, Some(RESULTS_PER_KIND), None)
+            .await?;
+
+        match result {
+            SearchResult::Tracks(page) => {
+                items.extend(page.items.into_iter().filter_map(|track| {
+                    let explicit = track.explicit;
+                    let entry = track_entry(track, requester)?;
+
+                    Some(SearchItem {
+                        kind: "Track",
+                        name: entry.name.clone(),
+                        detail: entry.artists.clone(),
+                        length: Some(spoticord_utils::time_to_string(entry.duration / 1000)),
+                        explicit,
+                        target: PlayTarget::Entry(entry),
+                    })
+                }))
+            }
+            SearchResult::Albums(page) => {
+                items.extend(page.items.into_iter().filter_map(|album| {
+                    Some(SearchItem {
+                        kind: "Album",
+                        target: PlayTarget::Context(album.id?.uri()),
+                        name: album.name,
+                        detail: album
+                            .artists
+                            .into_iter()
+                            .map(|artist| artist.name)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        length: None,
+                        explicit: false,
+                    })
+                }))
+            }
+            SearchResult::Playlists(page) => items.extend(page.items.into_iter().map(|playlist| {
+                SearchItem {
+                    kind: "Playlist",
+                    target: PlayTarget::Context(playlist.id.uri()),
+                    name: playlist.name,
+                    detail: playlist
+                        .owner
+                        .display_name
+                        .unwrap_or_else(|| playlist.owner.id.id().to_string()),
+                    length: Some(format!("{} tracks", playlist.tracks.total)),
+                    explicit: false,
+                }
+            })),
+            _ => {}
+        }
+    }
+
+    Ok(items)
+}
+
+/// Handle the "Play now" button, which interrupts whatever is playing
+async fn play_selected(
+    ctx: Context<'_>,
+    session: &SessionHandle,
+    press: &ComponentInteraction,
+    item: &SearchItem,
+) -> Result<()> {
+    if !can_control(ctx, session).await? {
+        respond_error(ctx, press, "Only the host or a DJ may interrupt playback.").await;
+
+        return Ok(());
+    }
+
+    _ = press
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await;
+
+    match item.target.clone() {
+        PlayTarget::Entry(entry) => play_now(ctx, session, entry).await,
+        PlayTarget::Context(uri) => play_context(ctx, session, &uri).await,
+    }
+}
+
+/// Handle the "Add to queue" button, which is only available for tracks
+async fn queue_selected(
+    ctx: Context<'_>,
+    session: &SessionHandle,
+    press: &ComponentInteraction,
+    item: &SearchItem,
+) -> Result<()> {
+    let PlayTarget::Entry(entry) = item.target.clone() else {
+        respond_error(ctx, press, "Only tracks can be added to the queue.").await;
+
+        return Ok(());
+    };
+
+    _ = press
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await;
+
+    enqueue(ctx, session, entry).await
+}
+
+/// Start playing a track or episode right away, skipping the queue of the guild
+async fn play_now(ctx: Context<'_>, session: &SessionHandle, entry: QueueEntry) -> Result<()> {
+    let name = entry.name.clone();
+    let artists = entry.artists.clone();
+    let uri = entry.uri.clone();
+
+    if let Err(why) = session.play_now(entry).await {
+        error!("Failed to play {uri}: {why}");
+
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot start playback")
+                        .description("Failed to start playing this on Spotify.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .author(
+                    CreateEmbedAuthor::new("Now playing")
+                        .icon_url("https://spoticord.com/spotify-logo.png"),
+                )
+                .title(name)
+                .url(spotify_url(&uri))
+                .description(format!("by {artists}"))
+                .footer(CreateEmbedFooter::new(format!(
+                    "Requested by {}",
+                    ctx.author().display_name()
+                )))
+                .color(Colors::Success),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn respond_error(ctx: Context<'_>, press: &ComponentInteraction, description: &str) {
+    _ = press
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await;
+
+    _ = press
+        .create_followup(
+            ctx,
+            CreateInteractionResponseFollowup::new()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot perform action")
+                        .description(description)
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await;
+}
+
+fn build_embed(query: &str, items: &[SearchItem]) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .author(
+            CreateEmbedAuthor::new("Search results")
+                .icon_url("https://spoticord.com/spotify-logo.png"),
+        )
+        .title(format!("Results for \"{}\"", escape(query)))
+        .footer(CreateEmbedFooter::new(
+            "Pick a result below, then play it now or add it to the queue.",
+        ))
+        .color(Colors::Info);
+
+    for kind in ["Track", "Album", "Playlist"] {
+        let mut value = String::new();
+
+        for (index, item) in items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.kind == kind)
+        {
+            let mut line = format!(
+                "{}. **{}** - {}",
+                index + 1,
+                escape(&truncate(&item.name, OPTION_TEXT_LIMIT)),
+                escape(&truncate(&item.detail, OPTION_TEXT_LIMIT))
+            );
+
+            if item.explicit {
+                line += " `E`";
+            }
+
+            if let Some(length) = &item.length {
+                line += &format!(" `{length}`");
+            }
+
+            // Leave out the results that don't fit, they can still be picked from the select menu
+            if value.chars().count() + line.chars().count() + 1 > FIELD_TEXT_LIMIT {
+                break;
+            }
+
+            if !value.is_empty() {
+                value.push('\n');
+            }
+
+            value += &line;
+        }
+
+        if !value.is_empty() {
+            embed = embed.field(format!("{kind}s"), value, false);
+        }
+    }
+
+    embed
+}
+
+fn build_components(id: u64, items: &[SearchItem], selected: usize) -> Vec<CreateActionRow> {
+    let options = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let label = truncate(&format!("{}. {}", index + 1, item.name), OPTION_TEXT_LIMIT);
+            let description = truncate(
+                &format!("{} • {}", item.kind, item.detail),
+                OPTION_TEXT_LIMIT,
+            );
+
+            CreateSelectMenuOption::new(label, index.to_string())
+                .description(description)
+                .default_selection(index == selected)
+        })
+        .collect();
+
+    let select_menu = CreateSelectMenu::new(
+        format!("{id}-select"),
+        CreateSelectMenuKind::String { options },
+    )
+    .placeholder("Pick a result");
+
+    let is_track = matches!(items[selected].target, PlayTarget::Entry(_));
+
+    let play_button = CreateButton::new(format!("{id}-play"))
+        .style(ButtonStyle::Success)
+        .label("Play now");
+
+    let queue_button = CreateButton::new(format!("{id}-queue"))
+        .style(ButtonStyle::Primary)
+        .label("Add to queue")
+        .disabled(!is_track);
+
+    vec![
+        CreateActionRow::SelectMenu(select_menu),
+        CreateActionRow::Buttons(vec![play_button, queue_button]),
+    ]
+}
+
+/// Shorten text to at most `limit` characters
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+
+    let mut text = text.chars().take(limit - 1).collect::<String>();
+    text.push('…');
+
+    text
+}
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use super::control::can_control;
+use crate::bot::Context;
+
+/// Jump to a position in the current track
+#[poise::command(slash_command, guild_only)]
+pub async fn seek(
+    ctx: Context<'_>,
+
+    #[description = "A timestamp like 1:23, or an offset in seconds like +30 or -10"]
+    position: String,
+) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let session = match manager.get_session(SessionQuery::Guild(guild)) {
+        Some(session) if session.active().await? => session,
+        _ => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Cannot seek")
+                            .description("I'm currently not playing any music in this server.")
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    if !can_control(ctx, &session).await? {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot seek")
+                        .description("Only the host or a DJ may seek.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let player = session.player().await?;
+    let Some(playback_info) = player.playback_info().await? else {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot seek")
+                        .description("I'm currently not playing any music in this server.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let current = playback_info.current_position() as i64;
+    let target = match parse_position(&position) {
+        Some(Position::Absolute(position)) => position,
+        Some(Position::Relative(offset)) => current + offset,
+        None => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Cannot seek")
+                            .description(
+                                "Invalid position. Use a timestamp like `1:23`, or an offset in seconds like `+30` or `-10`.",
+                            )
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let duration = playback_info.duration();
+    let target = target.clamp(0, duration as i64) as u32;
+
+    let embed = if target >= duration {
+        // Seeking to the very end of a track is the same as skipping it
+        player.next_track().await;
+
+        CreateEmbed::new()
+            .title("Skipped")
+            .description(format!(
+                "Seeking to the end of **{}** skipped it.",
+                playback_info.name()
+            ))
+            .color(Colors::Info)
+    } else {
+        player.seek(target).await;
+
+        CreateEmbed::new()
+            .title("Seeked")
+            .description(format!(
+                "Jumped to **{}** in **{}**.",
+                spoticord_utils::time_to_string(target / 1000),
+                playback_info.name()
+            ))
+            .color(Colors::Info)
+    };
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Position {
+    /// A position in milliseconds from the start of the track
+    Absolute(i64),
+    /// An offset in milliseconds from the current position
+    Relative(i64),
+}
+
+fn parse_position(input: &str) -> Option<Position> {
+    let input = input.trim();
+
+    if let Some(offset) = input.strip_prefix('+') {
+        return Some(Position::Relative(
+            spoticord_utils::parse_time(offset)? as i64 * 1000,
+        ));
+    }
+
+    if let Some(offset) = input.strip_prefix('-') {
+        return Some(Position::Relative(
+            -(spoticord_utils::parse_time(offset)? as i64) * 1000,
+        ));
+    }
+
+    Some(Position::Absolute(
+        spoticord_utils::parse_time(input)? as i64 * 1000,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_positions() {
+        assert_eq!(parse_position("0"), Some(Position::Absolute(0)));
+        assert_eq!(parse_position("83"), Some(Position::Absolute(83_000)));
+        assert_eq!(parse_position(" 1:23 "), Some(Position::Absolute(83_000)));
+        assert_eq!(
+            parse_position("1:01:23"),
+            Some(Position::Absolute(3_683_000))
+        );
+    }
+
+    #[test]
+    fn relative_positions() {
+        assert_eq!(parse_position("+30"), Some(Position::Relative(30_000)));
+        assert_eq!(parse_position("-10"), Some(Position::Relative(-10_000)));
+        assert_eq!(parse_position("+1:00"), Some(Position::Relative(60_000)));
+        assert_eq!(parse_position("-0:05"), Some(Position::Relative(-5_000)));
+    }
+
+    #[test]
+    fn invalid_positions() {
+        for input in ["", "+", "-", "abc", "+-5", "--5", "1:2", "1:60", "1::23"] {
+            assert_eq!(parse_position(input), None, "{input:?}");
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use super::control::can_control;
+use crate::bot::Context;
+
+/// Turn shuffle on or off
+#[poise::command(slash_command, guild_only)]
+pub async fn shuffle(
+    ctx: Context<'_>,
+
+    #[description = "Whether to shuffle, toggles shuffle if omitted"] enabled: Option<bool>,
+) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let session = match manager.get_session(SessionQuery::Guild(guild)) {
+        Some(session) if session.active().await? => session,
+        _ => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Cannot change shuffle")
+                            .description("I'm currently not playing any music in this server.")
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    if !can_control(ctx, &session).await? {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot change shuffle")
+                        .description("Only the host or a DJ may change shuffle.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let player = session.player().await?;
+    let enabled = match enabled {
+        Some(enabled) => enabled,
+        None => !player
+            .playback_info()
+            .await?
+            .is_some_and(|playback_info| playback_info.shuffle()),
+    };
+
+    player.set_shuffle(enabled).await;
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Shuffle changed")
+                .description(if enabled {
+                    "Shuffle has been **enabled**."
+                } else {
+                    "Shuffle has been **disabled**."
+                })
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
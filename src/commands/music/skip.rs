@@ -1,5 +1,4 @@
 use anyhow::Result;
-use chrono;
 use log::error;
 use poise::CreateReply;
 use rspotify::clients::OAuthClient;
@@ -7,6 +6,7 @@ use serenity::all::CreateEmbed;
 use spoticord_session::manager::SessionQuery;
 use spoticord_utils::discord::Colors;
 
+use super::control::can_control;
 use crate::bot::Context;
 
 /// Skip the current track
@@ -14,7 +14,7 @@ use crate::bot::Context;
 pub async fn skip(ctx: Context<'_>) -> Result<()> {
     let manager = ctx.data();
       // Check if we're in a voice channel session
-    let _session = match manager.get_session(SessionQuery::Guild(ctx.guild_id().unwrap())) {
+    let session = match manager.get_session(SessionQuery::Guild(ctx.guild_id().unwrap())) {
         Some(session) => session,
         None => {
             ctx.send(
@@ -32,9 +32,28 @@ pub async fn skip(ctx: Context<'_>) -> Result<()> {
         }
     };
 
-    // Get Spotify credentials and create authenticated client
+    if !can_control(ctx, &session).await? {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot skip")
+                        .description("Only the host or a DJ may skip tracks.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    // Get Spotify credentials, refreshing the token if needed, and create authenticated client
     let storage = manager.storage();
-    let mut credentials = match storage.get_spotify_credentials().await? {
+    let credentials = match storage
+        .get_fresh_spotify_credentials(session.account())
+        .await?
+    {
         Some(creds) => creds,
         None => {
             ctx.send(
@@ -52,22 +71,13 @@ pub async fn skip(ctx: Context<'_>) -> Result<()> {
         }
     };
 
-    // Refresh token if needed and save if updated
-    if credentials.refresh_if_needed().await? {
-        storage.save_spotify_credentials(&credentials).await?;
-    }
-
     // Create Spotify client with OAuth credentials
-    let token = rspotify::Token {
-        access_token: credentials.access_token.clone(),
-        expires_in: chrono::TimeDelta::seconds(3600),
-        expires_at: Some(credentials.expires_at),
-        refresh_token: Some(credentials.refresh_token.clone()),
-        scopes: std::collections::HashSet::new(),
-    };
+    let spotify = spoticord_config::get_spotify(credentials.token());
 
-    let spotify = spoticord_config::get_spotify(token);    // Skip to next track on Spotify  
-    match spotify.next_track(None).await {
+    // Skip to next track on Spotify
+    // Always skip on the bot's own device, never on whatever device Spotify considers active
+    let player = session.player().await?;
+    match spotify.next_track(Some(player.device_id())).await {
         Ok(_) => {
             ctx.send(
                 CreateReply::default()
@@ -4,6 +4,7 @@ use serenity::all::CreateEmbed;
 use spoticord_session::manager::SessionQuery;
 use spoticord_utils::discord::Colors;
 
+use super::control::can_control;
 use crate::bot::Context;
 
 #[poise::command(slash_command, guild_only)]
@@ -27,13 +28,13 @@ pub async fn stop(ctx: Context<'_>) -> Result<(), Error> {
         return Ok(());
     };
 
-    if session.active().await? && session.owner().await? != ctx.author().id {
+    if session.active().await? && !can_control(ctx, &session).await? {
         ctx.send(
             CreateReply::default()
                 .embed(
                     CreateEmbed::new()
                         .title("Cannot stop playback")
-                        .description("Only the host may stop playback.")
+                        .description("Only the host or a DJ may stop playback.")
                         .color(Colors::Error),
                 )
                 .ephemeral(true),
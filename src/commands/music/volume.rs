@@ -0,0 +1,91 @@
+use anyhow::Result;
+use poise::CreateReply;
+use serenity::all::CreateEmbed;
+use spoticord_session::manager::SessionQuery;
+use spoticord_utils::discord::Colors;
+
+use super::control::can_control;
+use crate::bot::Context;
+
+/// Show or change the volume of the music
+#[poise::command(slash_command, guild_only)]
+pub async fn volume(
+    ctx: Context<'_>,
+
+    #[description = "The new volume in percent"]
+    #[min = 0]
+    #[max = 100]
+    percentage: Option<u8>,
+) -> Result<()> {
+    let manager = ctx.data();
+    let guild = ctx.guild_id().expect("poise lied to me");
+
+    let session = match manager.get_session(SessionQuery::Guild(guild)) {
+        Some(session) if session.active().await? => session,
+        _ => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(
+                        CreateEmbed::new()
+                            .title("Cannot change volume")
+                            .description("I'm currently not playing any music in this server.")
+                            .color(Colors::Error),
+                    )
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let player = session.player().await?;
+
+    let Some(percentage) = percentage else {
+        let volume = player.volume().await?;
+
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Volume")
+                        .description(format!("The volume is currently set to **{volume}%**."))
+                        .color(Colors::Info),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    if !can_control(ctx, &session).await? {
+        ctx.send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Cannot change volume")
+                        .description("Only the host or a DJ may change the volume.")
+                        .color(Colors::Error),
+                )
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    player.set_volume(percentage).await;
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("Volume changed")
+                .description(format!("The volume is now set to **{percentage}%**."))
+                .color(Colors::Info),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
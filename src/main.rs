@@ -1,7 +1,7 @@
 mod bot;
 mod commands;
 
-use log::{error, info};
+use log::{error, info, warn};
 use poise::Framework;
 use serenity::all::ClientBuilder;
 use songbird::SerenityInit;
@@ -38,12 +38,20 @@ async fn main() {
         return;
     }
 
+    // Tried again on the next start if Spotify can't be reached
+    match storage.migrate_legacy_account().await {
+        Ok(Some(account)) => info!("Moved legacy Spotify account to {account}"),
+        Ok(None) => {}
+        Err(why) => warn!("Failed to migrate legacy Spotify account: {why}"),
+    }
+
     // Start web server for OAuth
     let web_server = WebServer::new(storage.clone());
     let web_port = spoticord_config::web_port();
     
+    let server = web_server.clone();
     tokio::spawn(async move {
-        if let Err(why) = web_server.start(web_port).await {
+        if let Err(why) = server.start(web_port).await {
             error!("Web server error: {why}");
         }
     });
@@ -53,7 +61,9 @@ async fn main() {
 
     // Set up bot
     let framework = Framework::builder()
-        .setup(|ctx, ready, framework| Box::pin(bot::setup(ctx, ready, framework, storage)))
+        .setup(|ctx, ready, framework| {
+            Box::pin(bot::setup(ctx, ready, framework, storage, web_server))
+        })
         .options(bot::framework_opts())
         .build();
 